@@ -1,72 +1,174 @@
-use tokio::sync::mpsc;
-use common::Event;
-use crate::storage::Storage;
-use crate::llm::LlmClient;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use common::{AnomalyConfig, Event, MaintenanceWindow, MetricSample, Severity};
+use crate::storage::{Storage, StoredEvent};
+use crate::llm::LlmQueue;
+use crate::baseline::BaselineTracker;
+use crate::silences::SilenceSet;
+use crate::governor::MemoryGovernor;
+use crate::selfmon::metrics;
+use crate::discovery::ServiceMap;
 use log::{info, error};
 
+/// Service id recorded for events no discovered service claims
+const SYSTEM_SERVICE: &str = "system";
+
+/// Per-event processing state owned by the analyzer task
+struct Analyzer {
+    storage: Storage,
+    llm_queue: Option<LlmQueue>,
+    stored_tx: broadcast::Sender<Event>,
+    silences: SilenceSet,
+    governor: MemoryGovernor,
+    services: ServiceMap,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn start_analyzer(
     mut rx: mpsc::Receiver<Event>,
+    mut metrics_rx: mpsc::Receiver<MetricSample>,
     storage: Storage,
-    llm_client: Option<LlmClient>,
+    llm_queue: Option<LlmQueue>,
+    anomaly_config: AnomalyConfig,
+    maintenance: Vec<MaintenanceWindow>,
+    stored_tx: broadcast::Sender<Event>,
+    governor: MemoryGovernor,
+    services: ServiceMap,
 ) -> anyhow::Result<()> {
     info!("Starting event analyzer");
-    
+
+    let flush_secs = anomaly_config.flush_secs;
+    let mut baselines = if anomaly_config.enabled {
+        Some(BaselineTracker::load(anomaly_config, storage.clone()).await?)
+    } else {
+        info!("Anomaly detection disabled");
+        None
+    };
+
+    let mut analyzer = Analyzer {
+        silences: SilenceSet::new(storage.clone(), maintenance),
+        storage,
+        llm_queue,
+        stored_tx,
+        governor,
+        services,
+    };
+
     tokio::spawn(async move {
-        while let Some(mut event) = rx.recv().await {
-            info!("Analyzing event: {} ({})", event.event_id, event.severity);
-            
-            // For critical events, get LLM suggestion
-            if event.severity == "CRITICAL" && llm_client.is_some() {
-                if let Some(ref client) = llm_client {
-                    match client.analyze_event(&event).await {
-                        Ok(suggestion) => {
-                            event.suggestion = Some(suggestion);
-                            info!("LLM suggestion added to event {}", event.event_id);
-                        }
-                        Err(e) => {
-                            error!("LLM analysis failed for {}: {}", event.event_id, e);
-                        }
+        let mut flush = tokio::time::interval(Duration::from_secs(flush_secs.max(1)));
+        loop {
+            tokio::select! {
+                event = rx.recv() => {
+                    let Some(event) = event else { break };
+                    analyzer.handle_event(event).await;
+                }
+                Some(sample) = metrics_rx.recv() => {
+                    analyzer.record_sample(&sample).await;
+                    let Some(ref mut tracker) = baselines else { continue };
+                    match tracker.observe(&sample) {
+                        Ok(Some(event)) => analyzer.handle_event(event).await,
+                        Ok(None) => {}
+                        Err(e) => error!("Baseline update failed for {}: {}", sample.name, e),
+                    }
+                }
+                _ = flush.tick(), if baselines.is_some() => {
+                    if let Some(ref mut tracker) = baselines {
+                        tracker.flush().await;
                     }
                 }
             }
-            
-            // Store event in database
-            if let Err(e) = store_event(&storage, &event).await {
-                error!("Failed to store event {}: {}", event.event_id, e);
-            } else {
-                info!("Event {} stored successfully", event.event_id);
-            }
         }
-        
+        if let Some(ref mut tracker) = baselines {
+            tracker.flush().await;
+        }
+
         info!("Analyzer stopped");
     });
-    
+
     Ok(())
 }
 
-async fn store_event(storage: &Storage, event: &Event) -> anyhow::Result<()> {
+impl Analyzer {
+    /// Raw samples back metric summaries for `sia-cli ask`
+    async fn record_sample(&self, sample: &MetricSample) {
+        let ts = chrono::DateTime::parse_from_rfc3339(&sample.ts)
+            .map(|t| t.timestamp())
+            .unwrap_or_else(|_| chrono::Utc::now().timestamp());
+        if let Err(e) = self.storage.insert_metric_sample(&sample.name, ts, sample.value).await {
+            error!("Failed to store metric sample {}: {}", sample.name, e);
+        }
+    }
+
+    async fn handle_event(&mut self, mut event: Event) {
+        metrics().event_received(&event.r#type);
+        if event.severity == Severity::Debug && self.governor.drops_debug() {
+            metrics().event_dropped(&event.r#type);
+            return;
+        }
+        info!("Analyzing event: {} ({})", event.event_id, event.severity);
+
+        let service_id = self.services.service_for(&event).unwrap_or_else(|| SYSTEM_SERVICE.to_string());
+
+        // Silenced events are still stored but skip LLM analysis and notifications
+        event.silenced_by = self.silences.matching(&event, &service_id).await;
+        if let Some(ref silence) = event.silenced_by {
+            info!("Event {} silenced by {}", event.event_id, silence);
+        }
+
+        // Store event in database
+        let started = Instant::now();
+        if let Err(e) = store_event(&self.storage, &event, &service_id).await {
+            metrics().event_store_failed(&event.r#type, started.elapsed());
+            error!("Failed to store event {}: {}", event.event_id, e);
+            return;
+        }
+        metrics().event_stored(&event.r#type, started.elapsed());
+        info!("Event {} stored successfully", event.event_id);
+
+        // Critical events get an LLM suggestion in the background, written back when ready
+        if event.severity == Severity::Critical && event.silenced_by.is_none() {
+            if let Some(ref queue) = self.llm_queue {
+                if let Err(e) = queue.enqueue(&event).await {
+                    error!("Failed to queue LLM analysis for {}: {}", event.event_id, e);
+                }
+            }
+        }
+
+        // Downstream stages (correlation, ...) only see persisted events
+        let _ = self.stored_tx.send(event);
+    }
+}
+
+async fn store_event(storage: &Storage, event: &Event, service_id: &str) -> anyhow::Result<()> {
     let ts = chrono::DateTime::parse_from_rfc3339(&event.ts)?
         .timestamp();
-    
-    let entity_json = serde_json::to_vec(&event.entity)?;
-    let evidence_json = serde_json::to_vec(&event.evidence)?;
-    
-    let mut snapshot = entity_json.clone();
-    snapshot.extend_from_slice(&evidence_json);
-    
-    if let Some(ref suggestion) = event.suggestion {
-        let suggestion_json = serde_json::to_vec(suggestion)?;
-        snapshot.extend_from_slice(&suggestion_json);
-    }
-    
-    storage.insert_event(
-        &event.event_id,
+
+    let snapshot = serde_json::to_vec(&serde_json::json!({
+        "entity": event.entity,
+        "evidence": event.evidence,
+    }))?;
+
+    storage.insert_event(&StoredEvent {
+        event_id: event.event_id.clone(),
         ts,
-        &event.severity,
-        &event.r#type,
-        "system",
-        &snapshot,
-    ).await?;
-    
+        severity: event.severity,
+        type_: event.r#type.clone(),
+        service_id: service_id.to_string(),
+        fingerprint: Some(fingerprint(event)),
+        snapshot,
+        status: event.status.clone(),
+        silenced_by: event.silenced_by.clone(),
+        suggestion: event.suggestion.clone(),
+    }).await?;
+
     Ok(())
 }
+
+/// Events sharing a fingerprint describe the same ongoing condition; the
+/// sweeper uses it to count recurrences and to detect recovery.
+fn fingerprint(event: &Event) -> String {
+    let subject = ["metric", "unit", "mount", "type"].iter()
+        .find_map(|k| event.entity.get(*k).and_then(|v| v.as_str()))
+        .unwrap_or("system");
+    format!("{}:{}", event.r#type, subject)
+}
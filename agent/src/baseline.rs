@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use common::{AnomalyConfig, Event, EventKind, MetricSample, Severity};
+use crate::ids::unique_id;
+use crate::storage::{Storage, StoredBaseline};
+use anyhow::Result;
+use log::{info, warn};
+
+/// Bucket used when seasonality is disabled
+const GLOBAL_BUCKET: i64 = -1;
+
+/// Exponentially weighted mean/variance for one metric bucket
+#[derive(Debug, Clone, Default)]
+struct Ewma {
+    mean: f64,
+    variance: f64,
+    samples: i64,
+}
+
+impl Ewma {
+    fn update(&mut self, value: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean = value;
+            self.variance = 0.0;
+        } else {
+            let diff = value - self.mean;
+            let incr = alpha * diff;
+            self.mean += incr;
+            self.variance = (1.0 - alpha) * (self.variance + diff * incr);
+        }
+        self.samples += 1;
+    }
+}
+
+/// Tracks how long a metric has been outside its expected range
+struct Deviation {
+    since: DateTime<Utc>,
+    reported: bool,
+}
+
+pub struct BaselineTracker {
+    config: AnomalyConfig,
+    storage: Storage,
+    models: HashMap<(String, i64), Ewma>,
+    /// Buckets updated since the last `flush`
+    dirty: HashSet<(String, i64)>,
+    deviations: HashMap<String, Deviation>,
+}
+
+impl BaselineTracker {
+    /// Create a tracker seeded with the baselines persisted in storage
+    pub async fn load(config: AnomalyConfig, storage: Storage) -> Result<Self> {
+        let models = storage.load_baselines().await?
+            .into_iter()
+            .map(|b| ((b.metric, b.bucket), Ewma {
+                mean: b.mean,
+                variance: b.variance,
+                samples: b.samples,
+            }))
+            .collect::<HashMap<_, _>>();
+
+        info!("Loaded {} baseline buckets", models.len());
+
+        Ok(Self {
+            config,
+            storage,
+            models,
+            dirty: HashSet::new(),
+            deviations: HashMap::new(),
+        })
+    }
+
+    /// Feed a sample into its baseline. Returns an `anomaly` event once the
+    /// metric has deviated for longer than `sustained_secs`. The baseline is
+    /// only updated in memory; `flush` persists it.
+    pub fn observe(&mut self, sample: &MetricSample) -> Result<Option<Event>> {
+        let ts = DateTime::parse_from_rfc3339(&sample.ts)?.with_timezone(&Utc);
+        let bucket = if self.config.seasonality { seasonal_bucket(&ts) } else { GLOBAL_BUCKET };
+        let key = (sample.name.clone(), bucket);
+
+        let model = self.models.entry(key.clone()).or_default();
+
+        // Score against the baseline as it was before this sample
+        let warmed_up = model.samples >= self.config.warmup_samples as i64;
+        let mean = model.mean;
+        let stddev = model.variance.sqrt().max(self.config.min_stddev);
+        let z_score = (sample.value - mean) / stddev;
+
+        model.update(sample.value, self.config.alpha);
+        let baseline_samples = model.samples;
+        self.dirty.insert(key);
+
+        if !warmed_up || z_score.abs() < self.config.z_threshold {
+            self.deviations.remove(&sample.name);
+            return Ok(None);
+        }
+
+        let deviation = self.deviations
+            .entry(sample.name.clone())
+            .or_insert(Deviation { since: ts, reported: false });
+
+        let sustained = (ts - deviation.since).num_seconds();
+        if deviation.reported || sustained < self.config.sustained_secs as i64 {
+            return Ok(None);
+        }
+        deviation.reported = true;
+
+        let lower = mean - self.config.z_threshold * stddev;
+        let upper = mean + self.config.z_threshold * stddev;
+        warn!(
+            "Anomaly on {}: {:.2} outside expected range [{:.2}, {:.2}] for {}s",
+            sample.name, sample.value, lower, upper, sustained
+        );
+
+        Ok(Some(create_anomaly_event(sample, bucket, mean, stddev, z_score, (lower, upper), sustained, baseline_samples)))
+    }
+
+    /// Persist the buckets updated since the last flush
+    pub async fn flush(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        let baselines: Vec<StoredBaseline> = self.dirty.iter()
+            .filter_map(|key| self.models.get(key).map(|model| StoredBaseline {
+                metric: key.0.clone(),
+                bucket: key.1,
+                mean: model.mean,
+                variance: model.variance,
+                samples: model.samples,
+            }))
+            .collect();
+        match self.storage.upsert_baselines(&baselines, Utc::now().timestamp()).await {
+            Ok(()) => self.dirty.clear(),
+            Err(e) => warn!("Failed to persist {} baseline buckets: {}", baselines.len(), e),
+        }
+    }
+}
+
+/// Hour-of-week bucket: 0 = Monday 00:00-00:59, 167 = Sunday 23:00-23:59
+fn seasonal_bucket(ts: &DateTime<Utc>) -> i64 {
+    ts.weekday().num_days_from_monday() as i64 * 24 + ts.hour() as i64
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_anomaly_event(
+    sample: &MetricSample,
+    bucket: i64,
+    mean: f64,
+    stddev: f64,
+    z_score: f64,
+    expected_range: (f64, f64),
+    sustained_secs: i64,
+    samples: i64,
+) -> Event {
+    use serde_json::json;
+
+    let event_id = unique_id("anomaly");
+    let ts = Utc::now().to_rfc3339();
+
+    let entity = json!({
+        "type": "metric",
+        "metric": sample.name,
+        "value": sample.value,
+    });
+
+    let evidence = json!({
+        "expected_range": [expected_range.0, expected_range.1],
+        "mean": mean,
+        "stddev": stddev,
+        "z_score": z_score,
+        "direction": if z_score > 0.0 { "above" } else { "below" },
+        "sustained_secs": sustained_secs,
+        "bucket": bucket,
+        "baseline_samples": samples,
+        "timestamp": ts.clone()
+    });
+
+    Event {
+        event_id,
+        ts,
+        severity: Severity::Warning,
+        r#type: EventKind::Anomaly,
+        entity,
+        evidence,
+        suggestion: None,
+        status: "open".to_string(),
+        silenced_by: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    async fn test_tracker(name: &str, config: AnomalyConfig) -> BaselineTracker {
+        let path = std::env::temp_dir().join(format!("sia-baseline-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        std::fs::File::create(&path).unwrap();
+        let storage = Storage::new(path.to_str().unwrap()).await.unwrap();
+        BaselineTracker::load(config, storage).await.unwrap()
+    }
+
+    fn config(warmup_samples: u64, sustained_secs: u64, seasonality: bool) -> AnomalyConfig {
+        AnomalyConfig {
+            alpha: 0.01,
+            warmup_samples,
+            sustained_secs,
+            seasonality,
+            ..Default::default()
+        }
+    }
+
+    /// Monday 2026-01-12 10:00 UTC, hour-of-week bucket 10
+    fn monday_10am() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 12, 10, 0, 0).unwrap()
+    }
+
+    fn observe(tracker: &mut BaselineTracker, ts: DateTime<Utc>, value: f64) -> Option<Event> {
+        tracker.observe(&MetricSample {
+            name: "cpu.usage_percent".to_string(),
+            ts: ts.to_rfc3339(),
+            value,
+        }).unwrap()
+    }
+
+    /// `n` samples of `value` 10s apart from `start`; returns the time after the last
+    fn feed(tracker: &mut BaselineTracker, start: DateTime<Utc>, n: i64, value: f64) -> DateTime<Utc> {
+        for i in 0..n {
+            assert!(observe(tracker, start + chrono::Duration::seconds(i * 10), value).is_none());
+        }
+        start + chrono::Duration::seconds(n * 10)
+    }
+
+    #[test]
+    fn ewma_tracks_mean_and_variance() {
+        let mut ewma = Ewma::default();
+        ewma.update(10.0, 0.5);
+        assert_eq!((ewma.mean, ewma.variance, ewma.samples), (10.0, 0.0, 1));
+        ewma.update(20.0, 0.5);
+        assert_eq!((ewma.mean, ewma.variance, ewma.samples), (15.0, 25.0, 2));
+        ewma.update(15.0, 0.5);
+        assert_eq!((ewma.mean, ewma.variance, ewma.samples), (15.0, 12.5, 3));
+    }
+
+    #[tokio::test]
+    async fn warm_up_suppresses_anomalies() {
+        let mut tracker = test_tracker("warmup", config(20, 0, false)).await;
+        let next = feed(&mut tracker, monday_10am(), 19, 50.0);
+        assert!(observe(&mut tracker, next, 500.0).is_none());
+        // The outlier was the 20th sample, so the next one is scored
+        assert!(observe(&mut tracker, next + chrono::Duration::seconds(10), 500.0).is_some());
+    }
+
+    #[tokio::test]
+    async fn anomalies_must_be_sustained() {
+        let mut tracker = test_tracker("sustain", config(10, 60, false)).await;
+        let start = feed(&mut tracker, monday_10am(), 10, 50.0);
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        assert!(observe(&mut tracker, at(0), 90.0).is_none());
+        assert!(observe(&mut tracker, at(30), 90.0).is_none());
+        // Back in range: the deviation starts over
+        assert!(observe(&mut tracker, at(40), 50.0).is_none());
+        assert!(observe(&mut tracker, at(70), 90.0).is_none());
+        assert!(observe(&mut tracker, at(120), 90.0).is_none());
+
+        let event = observe(&mut tracker, at(130), 90.0).expect("anomaly after 60s");
+        assert_eq!(event.evidence["sustained_secs"], 60);
+        // Raised once per deviation
+        assert!(observe(&mut tracker, at(140), 90.0).is_none());
+    }
+
+    #[tokio::test]
+    async fn seasonal_buckets_learn_separately() {
+        let mut tracker = test_tracker("seasonal", config(10, 0, true)).await;
+        let ten = monday_10am();
+        let eleven = ten + chrono::Duration::hours(1);
+        feed(&mut tracker, ten, 10, 20.0);
+        feed(&mut tracker, eleven, 10, 70.0);
+
+        let metric = "cpu.usage_percent".to_string();
+        assert_eq!(tracker.models[&(metric.clone(), 10)].mean, 20.0);
+        assert_eq!(tracker.models[&(metric, 11)].mean, 70.0);
+
+        // Normal at 11:00 on a Monday, not at 10:00
+        assert!(observe(&mut tracker, eleven + chrono::Duration::minutes(30), 70.0).is_none());
+        let event = observe(&mut tracker, ten + chrono::Duration::minutes(30), 70.0).expect("anomaly in the 10:00 bucket");
+        assert_eq!(event.evidence["bucket"], 10);
+    }
+
+    #[tokio::test]
+    async fn evidence_reports_expected_range() {
+        let mut tracker = test_tracker("range", config(10, 0, false)).await;
+        let next = feed(&mut tracker, monday_10am(), 5, 40.0);
+        let next = feed(&mut tracker, next, 5, 60.0);
+        let model = tracker.models[&("cpu.usage_percent".to_string(), GLOBAL_BUCKET)].clone();
+
+        let event = observe(&mut tracker, next, 200.0).expect("anomaly");
+        let evidence = &event.evidence;
+        let stddev = model.variance.sqrt().max(tracker.config.min_stddev);
+        let z = tracker.config.z_threshold;
+        assert_eq!(evidence["mean"].as_f64().unwrap(), model.mean);
+        assert_eq!(evidence["stddev"].as_f64().unwrap(), stddev);
+        assert_eq!(evidence["expected_range"][0].as_f64().unwrap(), model.mean - z * stddev);
+        assert_eq!(evidence["expected_range"][1].as_f64().unwrap(), model.mean + z * stddev);
+        assert_eq!(evidence["z_score"].as_f64().unwrap(), (200.0 - model.mean) / stddev);
+        assert_eq!(evidence["direction"], "above");
+    }
+}
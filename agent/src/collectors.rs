@@ -1,86 +1,159 @@
 use sysinfo::{System, SystemExt, ProcessExt, CpuExt, PidExt};
 use tokio::time::{sleep, Duration};
 use tokio::sync::mpsc;
-use common::Event;
+use common::{Event, EventKind, MetricSample, Severity};
+use crate::governor::MemoryGovernor;
+use crate::selfmon::metrics;
 use log::{info, warn};
 
-pub async fn start_collectors(tx: mpsc::Sender<Event>, cpu_interval: u64) -> anyhow::Result<()> {
+/// Usage above these raises a WARNING event; below them the condition has cleared
+pub const CPU_WARNING_PERCENT: f32 = 80.0;
+pub const MEMORY_WARNING_PERCENT: f32 = 85.0;
+
+/// Samples recorded for the usage the CPU and memory events report on
+pub const CPU_METRIC: &str = "cpu.usage_percent";
+pub const MEMORY_METRIC: &str = "memory.used_percent";
+
+pub async fn start_collectors(
+    tx: mpsc::Sender<Event>,
+    metrics_tx: mpsc::Sender<MetricSample>,
+    cpu_interval: u64,
+    governor: MemoryGovernor,
+) -> anyhow::Result<()> {
     info!("Starting collectors with {}s CPU interval", cpu_interval);
-    
-    // CPU collector
+
+    // CPU collector; not critical, so it pauses under memory pressure
     let tx_cpu = tx.clone();
+    let metrics_cpu = metrics_tx.clone();
+    let governor_cpu = governor.clone();
     tokio::spawn(async move {
-        let mut sys = System::new_all();
+        let mut sys = System::new();
         let mut high_cpu_count = 0;
-        
+
         loop {
+            metrics().collector_tick("cpu");
+            if governor_cpu.pauses_collectors() {
+                // Let go of the process table while paused
+                sys = System::new();
+                high_cpu_count = 0;
+                sleep(Duration::from_secs(cpu_interval)).await;
+                continue;
+            }
+
             sys.refresh_cpu();
-            sys.refresh_memory();
-            
+
             let cpu_usage = sys.global_cpu_info().cpu_usage();
-            
+            if cpu_usage > CPU_WARNING_PERCENT {
+                // Only needed to name the top process in an event
+                sys.refresh_processes();
+            }
+            send_sample(&metrics_cpu, &governor_cpu, CPU_METRIC, cpu_usage as f64);
+
             // Generate events based on thresholds
             if cpu_usage > 95.0 {
                 warn!("Critical CPU usage: {:.1}%", cpu_usage);
-                let event = create_cpu_event(cpu_usage, "CRITICAL", &sys);
-                let _ = tx_cpu.send(event).await;
+                let event = create_cpu_event(cpu_usage, Severity::Critical, &sys);
+                send_event(&tx_cpu, event).await;
                 high_cpu_count = 0;
-            } else if cpu_usage > 80.0 {
+            } else if cpu_usage > CPU_WARNING_PERCENT {
                 high_cpu_count += 1;
                 if high_cpu_count >= 2 {
                     warn!("High CPU usage: {:.1}%", cpu_usage);
-                    let event = create_cpu_event(cpu_usage, "WARNING", &sys);
-                    let _ = tx_cpu.send(event).await;
+                    let event = create_cpu_event(cpu_usage, Severity::Warning, &sys);
+                    send_event(&tx_cpu, event).await;
                 }
             } else {
                 high_cpu_count = 0;
             }
-            
+
             sleep(Duration::from_secs(cpu_interval)).await;
         }
     });
-    
-    // Memory collector
+
+    // Memory collector; keeps running under memory pressure
     let tx_mem = tx.clone();
+    let metrics_mem = metrics_tx.clone();
     tokio::spawn(async move {
-        let mut sys = System::new_all();
-        
+        let mut sys = System::new();
+
         loop {
+            metrics().collector_tick("memory");
             sys.refresh_memory();
-            
+
             let total_mem = sys.total_memory();
             let used_mem = sys.used_memory();
             let mem_percent = (used_mem as f32 / total_mem as f32) * 100.0;
-            
+            send_sample(&metrics_mem, &governor, MEMORY_METRIC, mem_percent as f64);
+
             if mem_percent > 95.0 {
                 warn!("Critical memory usage: {:.1}%", mem_percent);
-                let event = create_memory_event(mem_percent, used_mem, total_mem, "CRITICAL", &sys);
-                let _ = tx_mem.send(event).await;
-            } else if mem_percent > 85.0 {
+                let event = create_memory_event(mem_percent, used_mem, total_mem, Severity::Critical);
+                send_event(&tx_mem, event).await;
+            } else if mem_percent > MEMORY_WARNING_PERCENT {
                 warn!("High memory usage: {:.1}%", mem_percent);
-                let event = create_memory_event(mem_percent, used_mem, total_mem, "WARNING", &sys);
-                let _ = tx_mem.send(event).await;
+                let event = create_memory_event(mem_percent, used_mem, total_mem, Severity::Warning);
+                send_event(&tx_mem, event).await;
             }
-            
+
             sleep(Duration::from_secs(cpu_interval)).await;
         }
     });
-    
+
     Ok(())
 }
 
-fn create_cpu_event(cpu_usage: f32, severity: &str, sys: &System) -> Event {
+/// Metric samples are best-effort: drop them rather than stall a collector,
+/// and stop producing them once the agent is shedding load
+fn send_sample(tx: &mpsc::Sender<MetricSample>, governor: &MemoryGovernor, name: &str, value: f64) {
+    let source = name.split('.').next().unwrap_or(name);
+    // The latest value is kept even when the sample itself is shed
+    metrics().host_sample(name, value);
+    if governor.drops_debug() {
+        metrics().sample_dropped(source);
+        return;
+    }
+    let sample = MetricSample {
+        name: name.to_string(),
+        ts: chrono::Utc::now().to_rfc3339(),
+        value,
+    };
+    if tx.try_send(sample).is_err() {
+        metrics().sample_dropped(source);
+    }
+}
+
+/// Events wait for room in the channel, but a wait is counted so the
+/// self-monitor can tell the analyzer is falling behind
+async fn send_event(tx: &mpsc::Sender<Event>, event: Event) {
+    let event = match tx.try_send(event) {
+        Ok(()) => return,
+        Err(mpsc::error::TrySendError::Full(event)) => {
+            metrics().send_blocked(&event.r#type);
+            event
+        }
+        Err(mpsc::error::TrySendError::Closed(event)) => {
+            metrics().event_dropped(&event.r#type);
+            return;
+        }
+    };
+    let kind = event.r#type.clone();
+    if tx.send(event).await.is_err() {
+        metrics().event_dropped(&kind);
+    }
+}
+
+fn create_cpu_event(cpu_usage: f32, severity: Severity, sys: &System) -> Event {
     use chrono::Utc;
     use serde_json::json;
-    
+
     let event_id = format!("cpu_{}", Utc::now().timestamp_millis());
     let ts = Utc::now().to_rfc3339();
-    
+
     // Find top CPU process
     let top_proc = sys.processes()
         .values()
         .max_by(|a, b| a.cpu_usage().partial_cmp(&b.cpu_usage()).unwrap());
-    
+
     let entity = json!({
         "cpu_usage": cpu_usage,
         "type": "system_cpu",
@@ -90,41 +163,44 @@ fn create_cpu_event(cpu_usage: f32, severity: &str, sys: &System) -> Event {
             "cpu": p.cpu_usage()
         }))
     });
-    
+
     let evidence = json!({
-        "threshold": if severity == "CRITICAL" { 95.0 } else { 80.0 },
-        "sustained": severity == "WARNING",
+        "threshold": if severity == Severity::Critical { 95.0 } else { CPU_WARNING_PERCENT },
+        "sustained": severity == Severity::Warning,
         "timestamp": ts.clone()
     });
-    
+
     Event {
         event_id,
         ts,
-        severity: severity.to_string(),
-        r#type: "cpu_high".to_string(),
+        severity,
+        r#type: EventKind::CpuHigh,
         entity,
         evidence,
         suggestion: None,
         status: "open".to_string(),
+        silenced_by: None,
     }
 }
 
-fn create_memory_event(mem_percent: f32, used: u64, total: u64, severity: &str, sys: &System) -> Event {
+fn create_memory_event(mem_percent: f32, used: u64, total: u64, severity: Severity) -> Event {
     use chrono::Utc;
     use serde_json::json;
-    
+
     let event_id = format!("mem_{}", Utc::now().timestamp_millis());
     let ts = Utc::now().to_rfc3339();
-    
-    // Find top memory processes
+
+    // Find top memory processes; the table is only needed for this event
+    let mut sys = System::new();
+    sys.refresh_processes();
     let mut procs: Vec<_> = sys.processes().values().collect();
-    procs.sort_by(|a, b| b.memory().cmp(&a.memory()));
+    procs.sort_by_key(|p| std::cmp::Reverse(p.memory()));
     let top_procs: Vec<_> = procs.iter().take(5).map(|p| json!({
         "name": p.name(),
         "pid": p.pid().as_u32(),
         "memory_mb": p.memory() / 1024 / 1024
     })).collect();
-    
+
     let entity = json!({
         "memory_percent": mem_percent,
         "used_mb": used / 1024 / 1024,
@@ -132,20 +208,21 @@ fn create_memory_event(mem_percent: f32, used: u64, total: u64, severity: &str,
         "type": "system_memory",
         "top_processes": top_procs
     });
-    
+
     let evidence = json!({
-        "threshold": if severity == "CRITICAL" { 95.0 } else { 85.0 },
+        "threshold": if severity == Severity::Critical { 95.0 } else { MEMORY_WARNING_PERCENT },
         "timestamp": ts.clone()
     });
-    
+
     Event {
         event_id,
         ts,
-        severity: severity.to_string(),
-        r#type: "memory_high".to_string(),
+        severity,
+        r#type: EventKind::MemoryHigh,
         entity,
         evidence,
         suggestion: None,
         status: "open".to_string(),
+        silenced_by: None,
     }
 }
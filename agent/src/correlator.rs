@@ -0,0 +1,345 @@
+use std::collections::{HashSet, VecDeque};
+use tokio::sync::broadcast;
+use common::{CorrelationConfig, Event, EventKind, Severity};
+use crate::storage::{Storage, StoredIncident, StoredIncidentMember};
+use crate::collectors::{CPU_METRIC, MEMORY_METRIC};
+use crate::governor::MemoryGovernor;
+use crate::ids::unique_id;
+use chrono::{DateTime, Utc};
+use log::{info, warn, error};
+
+/// Top-level entity fields that tie events to the same underlying resource.
+/// Of the processes listed in CPU and memory events only the heaviest counts
+/// (see `entity_keys`): a busy process shows up in every threshold event and
+/// would chain them all together.
+const CORRELATION_KEYS: &[&str] = &["pid", "cgroup", "unit", "service", "mount", "metric"];
+
+/// Events kept in the window while the agent is shrinking caches
+const MIN_RECENT: usize = 64;
+
+/// An event still inside the correlation window
+struct Member {
+    event_id: String,
+    ts: DateTime<Utc>,
+    severity: Severity,
+    kind: EventKind,
+    keys: HashSet<String>,
+    incident: Option<String>,
+}
+
+struct Correlator {
+    storage: Storage,
+    window_secs: i64,
+    recent: VecDeque<Member>,
+    governor: MemoryGovernor,
+}
+
+pub async fn start_correlator(
+    mut rx: broadcast::Receiver<Event>,
+    storage: Storage,
+    config: CorrelationConfig,
+    governor: MemoryGovernor,
+) -> anyhow::Result<()> {
+    if !config.enabled {
+        info!("Event correlation disabled");
+        return Ok(());
+    }
+
+    info!("Starting event correlator ({}s window)", config.window_secs);
+
+    let mut correlator = Correlator {
+        storage,
+        window_secs: config.window_secs as i64,
+        recent: VecDeque::new(),
+        governor,
+    };
+
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if let Err(e) = correlator.correlate(&event).await {
+                        error!("Correlation failed for {}: {}", event.event_id, e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Correlator lagged, skipped {} events", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+
+        info!("Correlator stopped");
+    });
+
+    Ok(())
+}
+
+impl Correlator {
+    async fn correlate(&mut self, event: &Event) -> anyhow::Result<()> {
+        let ts = DateTime::parse_from_rfc3339(&event.ts)?.with_timezone(&Utc);
+
+        // The sweeper re-publishes escalated events: refresh the member's
+        // severity, and leave events that have fallen out of the window alone
+        if let Some(member) = self.recent.iter_mut().find(|m| m.event_id == event.event_id) {
+            member.severity = event.severity;
+            return match member.incident.clone() {
+                Some(incident_id) => self.persist_incident(&incident_id).await,
+                None => Ok(()),
+            };
+        }
+        if self.recent.back().is_some_and(|newest| (newest.ts - ts).num_seconds() > self.window_secs) {
+            return Ok(());
+        }
+
+        self.recent.retain(|m| (ts - m.ts).num_seconds() <= self.window_secs);
+        if self.governor.shrinks_caches() && self.recent.len() > MIN_RECENT {
+            let excess = self.recent.len() - MIN_RECENT;
+            self.recent.drain(..excess);
+            self.recent.shrink_to_fit();
+        }
+
+        let keys = entity_keys(event);
+        let mut member = Member {
+            event_id: event.event_id.clone(),
+            ts,
+            severity: event.severity,
+            kind: event.r#type.clone(),
+            keys,
+            incident: None,
+        };
+
+        let related: Vec<usize> = self.recent.iter()
+            .enumerate()
+            .filter(|(_, m)| !m.keys.is_disjoint(&member.keys))
+            .map(|(i, _)| i)
+            .collect();
+
+        if related.is_empty() {
+            self.recent.push_back(member);
+            return Ok(());
+        }
+
+        // Join the most recent incident among related events, or open a new one
+        let incident_id = related.iter()
+            .rev()
+            .find_map(|&i| self.recent[i].incident.clone())
+            .unwrap_or_else(|| unique_id("inc"));
+
+        for &i in &related {
+            self.recent[i].incident.get_or_insert_with(|| incident_id.clone());
+        }
+        member.incident = Some(incident_id.clone());
+        self.recent.push_back(member);
+
+        self.persist_incident(&incident_id).await
+    }
+
+    async fn persist_incident(&self, incident_id: &str) -> anyhow::Result<()> {
+        let existing = self.storage.get_incident_by_id(incident_id).await?;
+        let mut ranked = match existing {
+            Some(_) => self.storage.get_incident_members(incident_id).await?,
+            None => Vec::new(),
+        };
+
+        for m in self.recent.iter().filter(|m| m.incident.as_deref() == Some(incident_id)) {
+            if !ranked.iter().any(|r| r.event_id == m.event_id) {
+                ranked.push(StoredIncidentMember {
+                    event_id: m.event_id.clone(),
+                    ts: m.ts.timestamp(),
+                    severity: m.severity,
+                    kind: m.kind.clone(),
+                });
+            }
+        }
+
+        ranked.sort_by_key(|m| (cause_rank(&m.kind), m.ts));
+
+        let mut entities: Vec<String> = existing.as_ref()
+            .map(|i| i.entities.clone())
+            .unwrap_or_default();
+        for m in self.recent.iter().filter(|m| m.incident.as_deref() == Some(incident_id)) {
+            entities.extend(m.keys.iter().cloned());
+        }
+        entities.sort();
+        entities.dedup();
+
+        let severity = ranked.iter()
+            .map(|m| m.severity)
+            .max()
+            .unwrap_or(Severity::Info);
+
+        let incident = StoredIncident {
+            id: incident_id.to_string(),
+            opened_at: ranked.iter().map(|m| m.ts).min().unwrap_or_default(),
+            updated_at: ranked.iter().map(|m| m.ts).max().unwrap_or_default(),
+            status: existing.map(|i| i.status).unwrap_or_else(|| "open".to_string()),
+            severity,
+            entities,
+            root_cause: ranked.first().map(|m| m.event_id.clone()),
+        };
+
+        self.storage.upsert_incident(&incident).await?;
+        let members: Vec<(String, i64)> = ranked.iter()
+            .enumerate()
+            .map(|(rank, m)| (m.event_id.clone(), rank as i64))
+            .collect();
+        self.storage.set_incident_members(incident_id, &members).await?;
+
+        info!(
+            "Incident {} now groups {} events (root cause candidate: {})",
+            incident_id,
+            members.len(),
+            incident.root_cause.as_deref().unwrap_or("?")
+        );
+
+        Ok(())
+    }
+}
+
+/// `key=value` pairs for the entity's correlation keys. CPU and memory events
+/// also count as being about their top process and the metric they threshold,
+/// so they group with each other and with anomalies on the same metric.
+fn entity_keys(event: &Event) -> HashSet<String> {
+    let entity = &event.entity;
+    let mut keys: HashSet<String> = CORRELATION_KEYS.iter()
+        .filter_map(|k| key_value(k, &entity[*k]))
+        .collect();
+
+    let (top_pid, metric) = match event.r#type {
+        EventKind::CpuHigh => (&entity["top_process"]["pid"], CPU_METRIC),
+        EventKind::MemoryHigh => (&entity["top_processes"][0]["pid"], MEMORY_METRIC),
+        _ => return keys,
+    };
+    keys.extend(key_value("pid", top_pid));
+    keys.insert(format!("metric={}", metric));
+    keys
+}
+
+fn key_value(key: &str, value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(format!("{}={}", key, s)),
+        serde_json::Value::Number(n) => Some(format!("{}={}", key, n)),
+        _ => None,
+    }
+}
+
+/// Lower ranks are more likely to be the cause: resource pressure leads to
+/// kernel actions, which lead to service failures.
+fn cause_rank(kind: &EventKind) -> u8 {
+    match kind {
+        EventKind::CpuHigh | EventKind::MemoryHigh | EventKind::DiskHigh | EventKind::Anomaly => 0,
+        EventKind::OomKill => 1,
+        EventKind::UnitFailed => 2,
+        EventKind::AgentMemoryHigh | EventKind::AgentDegraded | EventKind::Other(_) => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredEvent;
+    use serde_json::json;
+
+    async fn test_correlator(name: &str) -> Correlator {
+        let path = std::env::temp_dir().join(format!("sia-correlator-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        std::fs::File::create(&path).unwrap();
+        Correlator {
+            storage: Storage::new(path.to_str().unwrap()).await.unwrap(),
+            window_secs: 300,
+            recent: VecDeque::new(),
+            governor: MemoryGovernor::new(1024),
+        }
+    }
+
+    fn test_event(id: &str, kind: EventKind, ts: DateTime<Utc>, entity: serde_json::Value) -> Event {
+        Event {
+            event_id: id.to_string(),
+            ts: ts.to_rfc3339(),
+            severity: Severity::Warning,
+            r#type: kind,
+            entity,
+            evidence: json!({}),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        }
+    }
+
+    /// Store a WARNING event raised `ago` seconds ago and correlate it
+    async fn observe(correlator: &mut Correlator, id: &str, kind: EventKind, ago: i64, entity: serde_json::Value) {
+        let ts = Utc::now() - chrono::Duration::seconds(ago);
+        let event = test_event(id, kind, ts, entity);
+        correlator.storage.insert_event(&StoredEvent {
+            event_id: event.event_id.clone(),
+            ts: ts.timestamp(),
+            severity: event.severity,
+            type_: event.r#type.clone(),
+            service_id: "system".to_string(),
+            fingerprint: None,
+            snapshot: serde_json::to_vec(&json!({"entity": event.entity})).unwrap(),
+            status: "open".to_string(),
+            silenced_by: None,
+            suggestion: None,
+        }).await.unwrap();
+        correlator.correlate(&event).await.unwrap();
+    }
+
+    fn incident_of<'a>(correlator: &'a Correlator, id: &str) -> Option<&'a str> {
+        correlator.recent.iter().find(|m| m.event_id == id).and_then(|m| m.incident.as_deref())
+    }
+
+    #[tokio::test]
+    async fn groups_host_pressure_with_anomalies() {
+        let mut correlator = test_correlator("host").await;
+        observe(&mut correlator, "cpu_1", EventKind::CpuHigh, 30, json!({
+            "type": "system_cpu",
+            "top_process": {"name": "java", "pid": 4321, "cpu": 93.0},
+        })).await;
+        observe(&mut correlator, "mem_1", EventKind::MemoryHigh, 20, json!({
+            "type": "system_memory",
+            "top_processes": [{"name": "java", "pid": 4321}, {"name": "sshd", "pid": 812}],
+        })).await;
+        observe(&mut correlator, "anomaly_1", EventKind::Anomaly, 10, json!({
+            "type": "metric",
+            "metric": CPU_METRIC,
+        })).await;
+        observe(&mut correlator, "disk_1", EventKind::DiskHigh, 5, json!({"type": "filesystem", "mount": "/var"})).await;
+
+        let incident = incident_of(&correlator, "cpu_1").expect("cpu event joined an incident");
+        assert_eq!(incident_of(&correlator, "mem_1"), Some(incident));
+        assert_eq!(incident_of(&correlator, "anomaly_1"), Some(incident));
+        assert_eq!(incident_of(&correlator, "disk_1"), None);
+
+        // Processes further down the list don't tie events together
+        let keys = entity_keys(&test_event("mem_2", EventKind::MemoryHigh, Utc::now(), json!({
+            "top_processes": [{"pid": 4321}, {"pid": 812}],
+        })));
+        assert!(keys.contains("pid=4321"));
+        assert!(!keys.contains("pid=812"));
+    }
+
+    #[tokio::test]
+    async fn ranks_resource_pressure_as_root_cause() {
+        let mut correlator = test_correlator("rank").await;
+        // Effects arrive before the pressure that caused them
+        observe(&mut correlator, "unit_1", EventKind::UnitFailed, 30, json!({"unit": "app.service", "pid": 7})).await;
+        observe(&mut correlator, "oom_1", EventKind::OomKill, 20, json!({"pid": 7})).await;
+        observe(&mut correlator, "agent_1", EventKind::AgentDegraded, 15, json!({"pid": 7})).await;
+        observe(&mut correlator, "mem_1", EventKind::MemoryHigh, 10, json!({
+            "type": "system_memory",
+            "top_processes": [{"name": "app", "pid": 7}],
+        })).await;
+
+        let incident_id = incident_of(&correlator, "mem_1").unwrap().to_string();
+        let incident = correlator.storage.get_incident_by_id(&incident_id).await.unwrap().unwrap();
+        assert_eq!(incident.root_cause.as_deref(), Some("mem_1"));
+
+        let members: Vec<String> = correlator.storage.get_incident_members(&incident_id).await.unwrap()
+            .into_iter()
+            .map(|m| m.event_id)
+            .collect();
+        assert_eq!(members, vec!["mem_1", "oom_1", "unit_1", "agent_1"]);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use anyhow::Result;
+use chrono::Utc;
+use log::{info, error};
+use common::{DiscoveryConfig, Event, ServiceDiscovery};
+use crate::storage::Storage;
+
+/// Services recognised by process name when no systemd unit claims them:
+/// (service id, display name, process names)
+const WELL_KNOWN: &[(&str, &str, &[&str])] = &[
+    ("postgres", "PostgreSQL", &["postgres", "postmaster"]),
+    ("nginx", "nginx", &["nginx"]),
+    ("redis", "Redis", &["redis-server"]),
+    ("docker", "Docker", &["dockerd"]),
+];
+
+/// pids kept in a service's stored metadata; the map keeps all of them
+const MAX_STORED_PIDS: usize = 16;
+
+/// TCP_LISTEN in /proc/net/tcp
+const TCP_LISTEN: &str = "0A";
+
+/// Which service a process or unit belongs to, as of the last scan
+#[derive(Clone, Default)]
+pub struct ServiceMap {
+    index: Arc<RwLock<Index>>,
+}
+
+#[derive(Default)]
+struct Index {
+    by_pid: HashMap<u32, String>,
+    /// Keyed by unit name without the `.service` suffix
+    by_unit: HashMap<String, String>,
+}
+
+impl ServiceMap {
+    /// The service an event's entity points at: its unit, its pid, or for
+    /// host-level events the top process
+    pub fn service_for(&self, event: &Event) -> Option<String> {
+        let entity = &event.entity;
+        let index = self.index.read().unwrap();
+        if let Some(id) = entity["unit"].as_str().and_then(|u| index.by_unit.get(u.trim_end_matches(".service"))) {
+            return Some(id.clone());
+        }
+        let pid = entity["pid"].as_u64()
+            .or_else(|| entity["top_process"]["pid"].as_u64())
+            .or_else(|| entity["top_processes"][0]["pid"].as_u64())? as u32;
+        if let Some(id) = index.by_pid.get(&pid) {
+            return Some(id.clone());
+        }
+        // Started since the last scan; its unit may already be known
+        index.by_unit.get(&unit_of(pid)?).cloned()
+    }
+
+    fn replace(&self, found: &[Found]) {
+        let mut index = Index::default();
+        for service in found {
+            for pid in &service.pids {
+                index.by_pid.insert(*pid, service.id.clone());
+            }
+            if let Some(unit) = &service.discovery.unit {
+                index.by_unit.insert(unit.trim_end_matches(".service").to_string(), service.id.clone());
+            }
+        }
+        *self.index.write().unwrap() = index;
+    }
+}
+
+/// A service found by one scan
+struct Found {
+    id: String,
+    name: String,
+    /// Every pid, unlike `discovery.pids`
+    pids: Vec<u32>,
+    discovery: ServiceDiscovery,
+}
+
+pub fn start_discovery(config: DiscoveryConfig, storage: Storage, services: ServiceMap) -> Result<()> {
+    if !config.enabled {
+        info!("Service discovery disabled");
+        return Ok(());
+    }
+    info!("Starting service discovery (every {}s)", config.interval_secs);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs.max(10)));
+        loop {
+            ticker.tick().await;
+            let scan_config = config.clone();
+            let found = match tokio::task::spawn_blocking(move || scan(&scan_config)).await {
+                Ok(found) => found,
+                Err(e) => {
+                    error!("Service discovery scan failed: {}", e);
+                    continue;
+                }
+            };
+            services.replace(&found);
+            if let Err(e) = record(&storage, found).await {
+                error!("Failed to store discovered services: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Store this scan's services and mark the ones no longer found as stopped
+async fn record(storage: &Storage, found: Vec<Found>) -> Result<()> {
+    let now = Utc::now().timestamp();
+    let known: HashMap<String, Option<ServiceDiscovery>> = storage.get_services().await?
+        .into_iter()
+        .map(|s| (s.id, s.discovery))
+        .collect();
+
+    let running: BTreeSet<String> = found.iter().map(|s| s.id.clone()).collect();
+    let mut new = 0;
+    for mut service in found {
+        match known.get(&service.id).and_then(|d| d.as_ref()) {
+            Some(previous) => service.discovery.first_seen = previous.first_seen,
+            None => {
+                info!("Discovered service {} ({})", service.id, service.discovery.sources.join(", "));
+                new += 1;
+            }
+        }
+        storage.upsert_discovered_service(&service.id, &service.name, &service.discovery, now).await?;
+    }
+
+    let mut stopped = 0;
+    for (id, discovery) in known {
+        let Some(mut discovery) = discovery else { continue };
+        if discovery.running && !running.contains(&id) {
+            info!("Service {} is no longer running", id);
+            discovery.running = false;
+            discovery.pids.clear();
+            discovery.process_count = 0;
+            storage.upsert_discovered_service(&id, &id, &discovery, now).await?;
+            stopped += 1;
+        }
+    }
+    if new + stopped > 0 {
+        info!("Service discovery: {} new, {} stopped", new, stopped);
+    }
+    Ok(())
+}
+
+/// One pass over /proc
+fn scan(config: &DiscoveryConfig) -> Vec<Found> {
+    let now = Utc::now().timestamp();
+    let listeners = if config.sockets { listening_sockets() } else { HashMap::new() };
+
+    let mut well_known: Vec<(String, String, Vec<String>)> = WELL_KNOWN.iter()
+        .map(|(id, name, procs)| (id.to_string(), name.to_string(), procs.iter().map(|p| p.to_string()).collect()))
+        .collect();
+    well_known.extend(config.processes.iter().map(|(id, procs)| (id.clone(), id.clone(), procs.clone())));
+
+    let mut pids: Vec<u32> = std::fs::read_dir("/proc")
+        .map(|dir| dir.flatten().filter_map(|e| e.file_name().to_str()?.parse().ok()).collect())
+        .unwrap_or_default();
+    pids.sort_unstable();
+
+    let mut found: BTreeMap<String, Found> = BTreeMap::new();
+    for pid in pids {
+        let Ok(comm) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) else { continue };
+        let comm = comm.trim().to_string();
+        let unit = if config.systemd { unit_of(pid) } else { None };
+        let kind = well_known.iter().find(|(_, _, procs)| procs.contains(&comm));
+        let listen = if listeners.is_empty() { Vec::new() } else { listening_on(pid, &listeners) };
+
+        let (id, name, source) = match (&unit, kind) {
+            (Some(unit), _) => (unit.trim_end_matches(".service").to_string(), unit.clone(), "systemd"),
+            (None, Some((id, name, _))) => (id.clone(), name.clone(), "process"),
+            (None, None) if !listen.is_empty() => (comm.clone(), comm.clone(), "socket"),
+            (None, None) => continue,
+        };
+
+        let service = found.entry(id.clone()).or_insert_with(|| Found {
+            id,
+            name,
+            pids: Vec::new(),
+            discovery: ServiceDiscovery {
+                unit: unit.clone(),
+                exe: std::fs::read_link(format!("/proc/{}/exe", pid)).ok().map(|p| p.display().to_string()),
+                uid: proc_uid(pid),
+                running: true,
+                first_seen: now,
+                last_seen: now,
+                ..Default::default()
+            },
+        });
+        let discovery = &mut service.discovery;
+        let mut sources: BTreeSet<String> = discovery.sources.drain(..).collect();
+        sources.insert(source.to_string());
+        if let Some((kind, _, _)) = kind {
+            sources.insert("process".to_string());
+            discovery.kind.get_or_insert_with(|| kind.clone());
+        }
+        if !listen.is_empty() {
+            sources.insert("socket".to_string());
+        }
+        discovery.sources = sources.into_iter().collect();
+        for addr in listen {
+            if !discovery.listen.contains(&addr) {
+                discovery.listen.push(addr);
+            }
+        }
+        if discovery.pids.len() < MAX_STORED_PIDS {
+            discovery.pids.push(pid);
+        }
+        discovery.process_count += 1;
+        service.pids.push(pid);
+    }
+
+    found.into_values().collect()
+}
+
+/// The system service unit a process runs under, from its cgroup path
+fn unit_of(pid: u32) -> Option<String> {
+    unit_from_cgroup(&std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?)
+}
+
+fn unit_from_cgroup(cgroups: &str) -> Option<String> {
+    // cgroup v2 ("0::/system.slice/nginx.service") or the v1 systemd hierarchy
+    let path = cgroups.lines()
+        .find(|l| l.starts_with("0::") || l.contains(":name=systemd:"))
+        .and_then(|l| l.rsplit_once(':'))
+        .map(|(_, path)| path)?;
+    let rest = path.strip_prefix("/system.slice/")?;
+    rest.split('/')
+        .take_while(|part| !part.is_empty())
+        .filter(|part| part.ends_with(".service"))
+        .last()
+        .map(str::to_string)
+}
+
+fn proc_uid(pid: u32) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(format!("/proc/{}", pid)).ok().map(|m| m.uid())
+}
+
+/// Socket inode -> local address of every listening TCP socket
+fn listening_sockets() -> HashMap<u64, String> {
+    let mut sockets = HashMap::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = std::fs::read_to_string(table) else { continue };
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != TCP_LISTEN {
+                continue;
+            }
+            if let (Some(addr), Ok(inode)) = (parse_address(fields[1]), fields[9].parse()) {
+                sockets.insert(inode, addr);
+            }
+        }
+    }
+    sockets
+}
+
+/// Listening addresses among a process's open files; needs permission to read its fds
+fn listening_on(pid: u32, listeners: &HashMap<u64, String>) -> Vec<String> {
+    let Ok(fds) = std::fs::read_dir(format!("/proc/{}/fd", pid)) else { return Vec::new() };
+    let mut addrs: Vec<String> = fds.flatten()
+        .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+        .filter_map(|target| {
+            let target = target.to_str()?;
+            let inode = target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()?;
+            listeners.get(&inode).cloned()
+        })
+        .collect();
+    addrs.sort();
+    addrs.dedup();
+    addrs
+}
+
+/// `0100007F:1F90` -> `127.0.0.1:8080`. The kernel prints the address as
+/// 32-bit words in host byte order
+fn parse_address(s: &str) -> Option<String> {
+    let (ip, port) = s.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let bytes = (0..ip.len() / 8)
+        .map(|i| u32::from_str_radix(ip.get(i * 8..i * 8 + 8)?, 16).ok().map(u32::to_ne_bytes))
+        .collect::<Option<Vec<[u8; 4]>>>()?
+        .concat();
+    let ip = match bytes.len() {
+        4 => Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?).into(),
+        16 => Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?).into(),
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_net_addresses() {
+        if cfg!(target_endian = "little") {
+            assert_eq!(parse_address("0100007F:1F90").as_deref(), Some("127.0.0.1:8080"));
+            assert_eq!(parse_address("00000000000000000000000001000000:1538").as_deref(), Some("[::1]:5432"));
+        }
+        assert_eq!(parse_address("00000000:0050").as_deref(), Some("0.0.0.0:80"));
+        assert_eq!(parse_address("zz:0050"), None);
+    }
+
+    #[test]
+    fn links_events_by_unit_and_pid() {
+        let services = ServiceMap::default();
+        services.replace(&[Found {
+            id: "nginx".to_string(),
+            name: "nginx.service".to_string(),
+            pids: vec![100, 101],
+            discovery: ServiceDiscovery { unit: Some("nginx.service".to_string()), ..Default::default() },
+        }]);
+        let event = |entity: serde_json::Value| Event {
+            event_id: "e1".to_string(),
+            ts: Utc::now().to_rfc3339(),
+            severity: common::Severity::Warning,
+            r#type: common::EventKind::CpuHigh,
+            entity,
+            evidence: serde_json::json!({}),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        };
+        let service = |entity| services.service_for(&event(entity));
+        assert_eq!(service(serde_json::json!({"unit": "nginx.service"})).as_deref(), Some("nginx"));
+        assert_eq!(service(serde_json::json!({"pid": 101})).as_deref(), Some("nginx"));
+        assert_eq!(service(serde_json::json!({"top_process": {"pid": 100}})).as_deref(), Some("nginx"));
+        assert_eq!(service(serde_json::json!({"type": "system_cpu"})), None);
+    }
+
+    #[test]
+    fn finds_system_service_units() {
+        assert_eq!(unit_from_cgroup("0::/system.slice/nginx.service\n").as_deref(), Some("nginx.service"));
+        assert_eq!(
+            unit_from_cgroup("12:pids:/\n1:name=systemd:/system.slice/postgresql@14-main.service\n").as_deref(),
+            Some("postgresql@14-main.service")
+        );
+        // Containers and user sessions aren't system services
+        assert_eq!(unit_from_cgroup("0::/system.slice/docker-0123abcd.scope\n"), None);
+        assert_eq!(unit_from_cgroup("0::/user.slice/user-1000.slice/user@1000.service/app.slice/foo.service\n"), None);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use sysinfo::{ProcessExt, System, SystemExt};
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration};
+use common::{Event, EventKind, MemoryConfig, Severity};
+use crate::storage::Storage;
+use serde_json::json;
+use log::{info, warn};
+
+/// Share of the budget that queued items in a channel may take
+const CHANNEL_BUDGET_DIVISOR: u64 = 4;
+
+/// Rough heap footprint of a queued event (entity/evidence JSON included)
+pub const EVENT_BYTES: usize = 4096;
+
+/// Rough heap footprint of a queued metric sample
+pub const SAMPLE_BYTES: usize = 128;
+
+/// Load shedding stages, in the order they kick in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Shedding {
+    None,
+    /// Metric samples and DEBUG events are dropped
+    DropDebug,
+    /// Non-critical collectors skip their cycles
+    PauseCollectors,
+    /// Caches are trimmed to their minimum
+    ShrinkCaches,
+}
+
+impl Shedding {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Shedding::DropDebug,
+            2 => Shedding::PauseCollectors,
+            3 => Shedding::ShrinkCaches,
+            _ => Shedding::None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Shedding::None => "none",
+            Shedding::DropDebug => "drop_debug",
+            Shedding::PauseCollectors => "pause_collectors",
+            Shedding::ShrinkCaches => "shrink_caches",
+        }
+    }
+}
+
+/// Keeps the agent under `agent.memory_budget`. Cheap to clone; every stage
+/// checks the current shedding level at its own pace.
+#[derive(Clone)]
+pub struct MemoryGovernor {
+    budget_bytes: u64,
+    shedding: Arc<AtomicU8>,
+    rss_bytes: Arc<AtomicU64>,
+}
+
+impl MemoryGovernor {
+    pub fn new(budget_mb: usize) -> Self {
+        Self {
+            budget_bytes: budget_mb as u64 * 1024 * 1024,
+            shedding: Arc::new(AtomicU8::new(Shedding::None as u8)),
+            rss_bytes: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn shedding(&self) -> Shedding {
+        Shedding::from_u8(self.shedding.load(Ordering::Relaxed))
+    }
+
+    pub fn rss_bytes(&self) -> u64 {
+        self.rss_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn drops_debug(&self) -> bool {
+        self.shedding() >= Shedding::DropDebug
+    }
+
+    pub fn pauses_collectors(&self) -> bool {
+        self.shedding() >= Shedding::PauseCollectors
+    }
+
+    pub fn shrinks_caches(&self) -> bool {
+        self.shedding() >= Shedding::ShrinkCaches
+    }
+
+    /// `requested` capped so a full channel of `item_bytes` items stays
+    /// within a quarter of the budget
+    pub fn channel_capacity(&self, name: &str, requested: usize, item_bytes: usize) -> usize {
+        let fits = (self.budget_bytes / CHANNEL_BUDGET_DIVISOR / item_bytes.max(1) as u64).max(1) as usize;
+        if requested > fits {
+            warn!("Capping {} channel at {} entries (requested {}) to fit the memory budget", name, fits, requested);
+            return fits;
+        }
+        requested
+    }
+}
+
+/// Measure the agent's RSS every `check_interval_secs` and move between
+/// shedding stages. Entering a stage raises an `agent_memory_high` event.
+pub fn start_memory_governor(governor: MemoryGovernor, config: MemoryConfig, storage: Storage, tx: mpsc::Sender<Event>) {
+    let pid = match sysinfo::get_current_pid() {
+        Ok(pid) => pid,
+        Err(e) => {
+            warn!("Cannot find own pid ({}), memory budget not enforced", e);
+            return;
+        }
+    };
+    info!(
+        "Starting memory governor ({} MB budget, {}s interval)",
+        governor.budget_bytes / 1024 / 1024,
+        config.check_interval_secs
+    );
+
+    tokio::spawn(async move {
+        let mut sys = System::new();
+        let mut ticker = interval(Duration::from_secs(config.check_interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            if !sys.refresh_process(pid) {
+                continue;
+            }
+            let Some(rss) = sys.process(pid).map(|p| p.memory()) else { continue };
+            governor.rss_bytes.store(rss, Ordering::Relaxed);
+
+            let pct = rss * 100 / governor.budget_bytes.max(1);
+            let next = if pct >= config.shrink_caches_pct {
+                Shedding::ShrinkCaches
+            } else if pct >= config.pause_collectors_pct {
+                Shedding::PauseCollectors
+            } else if pct >= config.drop_debug_pct {
+                Shedding::DropDebug
+            } else {
+                Shedding::None
+            };
+
+            let previous = governor.shedding();
+            if next == previous {
+                continue;
+            }
+            governor.shedding.store(next as u8, Ordering::Relaxed);
+
+            if next < previous {
+                info!("Agent memory at {}% of budget, load shedding now {}", pct, next.as_str());
+                continue;
+            }
+            warn!("Agent memory at {}% of budget ({} MB), shedding load: {}", pct, rss / 1024 / 1024, next.as_str());
+            if next == Shedding::ShrinkCaches {
+                if let Err(e) = storage.shrink_memory().await {
+                    warn!("Failed to release SQLite cache: {}", e);
+                }
+            }
+            // The analyzer may be what's backed up, so don't wait on it
+            if tx.try_send(create_memory_event(&governor, pct, next)).is_err() {
+                warn!("Event channel full, agent_memory_high event dropped");
+            }
+        }
+    });
+}
+
+fn create_memory_event(governor: &MemoryGovernor, pct: u64, shedding: Shedding) -> Event {
+    use chrono::Utc;
+
+    let event_id = format!("agentmem_{}", Utc::now().timestamp_millis());
+    let ts = Utc::now().to_rfc3339();
+
+    let entity = json!({
+        "type": "agent",
+        "pid": std::process::id(),
+        "rss_mb": governor.rss_bytes() / 1024 / 1024,
+        "budget_mb": governor.budget_bytes / 1024 / 1024,
+    });
+
+    let evidence = json!({
+        "usage_percent": pct,
+        "shedding": shedding.as_str(),
+        "timestamp": ts.clone()
+    });
+
+    Event {
+        event_id,
+        ts,
+        severity: if shedding == Shedding::ShrinkCaches { Severity::Error } else { Severity::Warning },
+        r#type: EventKind::AgentMemoryHigh,
+        entity,
+        evidence,
+        suggestion: None,
+        status: "open".to_string(),
+        silenced_by: None,
+    }
+}
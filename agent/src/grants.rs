@@ -0,0 +1,188 @@
+use std::io::Write;
+use std::sync::Arc;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use log::info;
+use common::grants::{is_known_scope, sign_token, token_hash, verify_token, TokenClaims};
+use common::{Grant, GrantsConfig};
+use crate::ids::{hex, random_bytes};
+use crate::storage::{GrantRecord, Storage};
+
+/// Shortest signing key accepted from `key_file`
+const MIN_KEY_BYTES: usize = 32;
+
+/// Issues, refreshes, revokes and verifies grant tokens for registered services
+#[derive(Clone)]
+pub struct Grants {
+    storage: Storage,
+    key: Arc<Vec<u8>>,
+    default_ttl_secs: i64,
+    max_ttl_secs: i64,
+}
+
+impl Grants {
+    /// Load (or create) the signing key and register the configured services
+    pub async fn new(config: GrantsConfig, storage: Storage) -> Result<Self> {
+        let now = Utc::now().timestamp();
+        for service in &config.services {
+            let scopes = service.default_scopes.iter().chain(&service.requested_scopes);
+            if let Some(unknown) = scopes.into_iter().find(|s| !is_known_scope(s)) {
+                bail!("grants service '{}': unknown scope '{}'", service.id, unknown);
+            }
+            storage.upsert_service(service, now).await?;
+        }
+        if !config.services.is_empty() {
+            info!("Registered {} services for grants", config.services.len());
+        }
+
+        Ok(Self {
+            storage,
+            key: Arc::new(load_key(&config.key_file)?),
+            default_ttl_secs: config.default_ttl_secs,
+            max_ttl_secs: config.max_ttl_secs,
+        })
+    }
+
+    /// Grant `scopes` (the service's default scopes if none) to a registered service.
+    /// Returns the only copy of the token.
+    pub async fn create(&self, service_id: &str, scopes: Option<Vec<String>>, ttl_secs: Option<i64>, actor: &str) -> Result<Grant> {
+        let service = self.storage.get_service(service_id).await?
+            .ok_or_else(|| anyhow!("unknown service '{}'", service_id))?;
+        let scopes = match scopes.filter(|s| !s.is_empty()) {
+            Some(scopes) => {
+                let allowed = |s: &String| service.default_scopes.contains(s) || service.requested_scopes.contains(s);
+                if let Some(denied) = scopes.iter().find(|s| !allowed(s)) {
+                    bail!("service '{}' may not be granted '{}'", service_id, denied);
+                }
+                scopes
+            }
+            None if service.default_scopes.is_empty() => bail!("service '{}' has no default scopes; name them", service_id),
+            None => service.default_scopes.clone(),
+        };
+
+        let now = Utc::now().timestamp();
+        let claims = TokenClaims {
+            gid: format!("grant_{}", hex(&random_bytes(6)?)),
+            sid: service.id.clone(),
+            scp: scopes,
+            iat: now,
+            exp: now + self.ttl(ttl_secs)?,
+        };
+        let token = sign_token(&self.key, &claims)?;
+        self.storage.insert_grant(&GrantRecord {
+            id: claims.gid.clone(),
+            service_id: claims.sid.clone(),
+            scopes: claims.scp.clone(),
+            created_at: now,
+            expires_at: claims.exp,
+            revoked_at: None,
+            token_hash: token_hash(&token),
+        }).await?;
+        self.audit("grant.created", &claims, actor).await?;
+        Ok(grant(claims, token))
+    }
+
+    /// Issue a new token for the grant with a fresh expiry; the previous token stops working
+    pub async fn refresh(&self, grant_id: &str, ttl_secs: Option<i64>, actor: &str) -> Result<Grant> {
+        let record = self.storage.get_grant(grant_id).await?
+            .filter(|g| g.revoked_at.is_none())
+            .ok_or_else(|| anyhow!("no active grant '{}'", grant_id))?;
+
+        let now = Utc::now().timestamp();
+        let claims = TokenClaims {
+            gid: record.id,
+            sid: record.service_id,
+            scp: record.scopes,
+            iat: now,
+            exp: now + self.ttl(ttl_secs)?,
+        };
+        let token = sign_token(&self.key, &claims)?;
+        if !self.storage.refresh_grant(&claims.gid, claims.exp, &token_hash(&token)).await? {
+            bail!("grant '{}' was revoked", claims.gid);
+        }
+        self.audit("grant.refreshed", &claims, actor).await?;
+        Ok(grant(claims, token))
+    }
+
+    pub async fn revoke(&self, grant_id: &str, actor: &str) -> Result<bool> {
+        let revoked = self.storage.revoke_grant(grant_id, Utc::now().timestamp()).await?;
+        if revoked {
+            self.storage.insert_audit("grant.revoked", &serde_json::json!({
+                "grant_id": grant_id,
+                "by": actor,
+            })).await?;
+        }
+        Ok(revoked)
+    }
+
+    pub async fn list(&self, include_inactive: bool) -> Result<Vec<GrantRecord>> {
+        self.storage.get_grants(include_inactive, Utc::now().timestamp()).await
+    }
+
+    /// Claims of a token that is correctly signed, unexpired, and still the
+    /// current token of a grant that hasn't been revoked
+    pub async fn verify(&self, token: &str) -> Result<TokenClaims> {
+        let claims = verify_token(&self.key, token, Utc::now().timestamp())?;
+        let record = self.storage.get_grant(&claims.gid).await?
+            .ok_or_else(|| anyhow!("unknown grant"))?;
+        if record.revoked_at.is_some() {
+            bail!("grant revoked");
+        }
+        if record.token_hash != token_hash(token) {
+            bail!("token superseded by a refresh");
+        }
+        Ok(claims)
+    }
+
+    fn ttl(&self, ttl_secs: Option<i64>) -> Result<i64> {
+        let ttl = ttl_secs.unwrap_or(self.default_ttl_secs);
+        if ttl <= 0 || ttl > self.max_ttl_secs {
+            bail!("ttl must be between 1 and {} seconds", self.max_ttl_secs);
+        }
+        Ok(ttl)
+    }
+
+    async fn audit(&self, kind: &str, claims: &TokenClaims, actor: &str) -> Result<()> {
+        self.storage.insert_audit(kind, &serde_json::json!({
+            "grant_id": claims.gid,
+            "service_id": claims.sid,
+            "scopes": claims.scp,
+            "expires_at": claims.exp,
+            "by": actor,
+        })).await
+    }
+}
+
+fn grant(claims: TokenClaims, token: String) -> Grant {
+    Grant {
+        id: claims.gid,
+        service_id: claims.sid,
+        scopes: claims.scp,
+        expires_at: DateTime::<Utc>::from_timestamp(claims.exp, 0).map(|t| t.to_rfc3339()).unwrap_or_default(),
+        token,
+    }
+}
+
+/// Read the signing key, generating one readable only by the agent's user if there is none
+fn load_key(path: &str) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(key) if key.len() < MIN_KEY_BYTES => {
+            bail!("grants key {} is shorter than {} bytes", path, MIN_KEY_BYTES)
+        }
+        Ok(key) => Ok(key),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            use std::os::unix::fs::OpenOptionsExt;
+            let key = random_bytes(MIN_KEY_BYTES)?;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)
+                .and_then(|mut file| file.write_all(&key))
+                .with_context(|| format!("cannot create grants key {}", path))?;
+            info!("Generated grants signing key {}", path);
+            Ok(key)
+        }
+        Err(e) => Err(e).with_context(|| format!("cannot read grants key {}", path)),
+    }
+}
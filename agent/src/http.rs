@@ -0,0 +1,239 @@
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use serde_json::{json, Value};
+use common::HttpConfig;
+use crate::storage::Storage;
+use crate::llm::{LlmClient, LlmState};
+use crate::selfmon::metrics;
+use log::{info, error};
+
+/// A collector that hasn't ticked for this many intervals counts as stalled
+const STALLED_INTERVALS: i64 = 3;
+
+#[derive(Clone)]
+struct HttpState {
+    storage: Storage,
+    llm: Option<LlmClient>,
+    collector_interval: u64,
+}
+
+/// Serve `/metrics` (Prometheus text format), `/healthz` and `/readyz`
+pub async fn start_http_server(config: HttpConfig, storage: Storage, llm: Option<LlmClient>, collector_interval: u64) -> anyhow::Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let addr: SocketAddr = config.listen.parse()?;
+    let state = HttpState { storage, llm, collector_interval };
+    let make_svc = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(route(req, &state).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_svc);
+    info!("HTTP endpoint listening on {}", addr);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("HTTP server failed: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+async fn route(req: Request<Body>, state: &HttpState) -> Response<Body> {
+    if req.method() != Method::GET {
+        return text(StatusCode::METHOD_NOT_ALLOWED, "method not allowed\n");
+    }
+    match req.uri().path() {
+        "/metrics" => match render_metrics(state).await {
+            Ok(body) => Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(body))
+                .unwrap(),
+            Err(e) => text(StatusCode::INTERNAL_SERVER_ERROR, &format!("{}\n", e)),
+        },
+        "/healthz" => health(state, false).await,
+        "/readyz" => health(state, true).await,
+        _ => text(StatusCode::NOT_FOUND, "not found\n"),
+    }
+}
+
+fn text(status: StatusCode, body: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Liveness needs storage and running collectors; readiness also needs the
+/// LLM, when one is configured, to be serving its model
+async fn health(state: &HttpState, ready: bool) -> Response<Body> {
+    let storage = match state.storage.get_event_counts().await {
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let stalled_after = STALLED_INTERVALS * state.collector_interval.max(1) as i64;
+    let snapshot = metrics().snapshot();
+    let collectors: serde_json::Map<String, Value> = snapshot["collector_ticks"].as_object()
+        .map(|ticks| ticks.iter()
+            .map(|(name, ts)| {
+                let ok = now - ts.as_i64().unwrap_or(0) <= stalled_after;
+                (name.clone(), json!(if ok { "ok" } else { "stalled" }))
+            })
+            .collect())
+        .unwrap_or_default();
+
+    let llm = state.llm.as_ref().map(|client| client.health().status().state);
+
+    let mut healthy = storage == "ok" && !collectors.is_empty() && collectors.values().all(|s| s == "ok");
+    if ready {
+        healthy &= llm.is_none_or(|state| state == LlmState::Healthy);
+    }
+
+    let body = json!({
+        "status": if healthy { "ok" } else { "fail" },
+        "checks": {
+            "storage": storage,
+            "collectors": collectors,
+            "llm": llm.map(|s| json!(s)).unwrap_or(json!("disabled")),
+            "load_shedding": snapshot["process"]["shedding"],
+        },
+    });
+    Response::builder()
+        .status(if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE })
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Prometheus text exposition of host metrics, open events and agent internals
+async fn render_metrics(state: &HttpState) -> anyhow::Result<String> {
+    let snapshot = metrics().snapshot();
+    let mut out = Exposition::default();
+
+    if let Some(host) = snapshot["host"].as_object() {
+        for (name, value) in host {
+            let metric = format!("sia_host_{}", name.replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+            out.family(&metric, "Latest sample from the host collectors", "gauge");
+            out.sample(&metric, &[], value.as_f64().unwrap_or(0.0));
+        }
+    }
+
+    out.family("sia_open_events", "Open events by severity and type", "gauge");
+    for (severity, kind, count) in state.storage.get_open_event_counts_by_type().await? {
+        out.sample("sia_open_events", &[("severity", &severity), ("type", &kind)], count as f64);
+    }
+
+    out.family("sia_llm_jobs", "LLM analysis jobs by status", "gauge");
+    for (status, count) in state.storage.get_llm_job_counts().await? {
+        out.sample("sia_llm_jobs", &[("status", &status)], count as f64);
+    }
+
+    if let Some(ref client) = state.llm {
+        let current = client.health().status().state;
+        out.family("sia_llm_state", "1 for the current LLM provider state", "gauge");
+        for s in [LlmState::Unknown, LlmState::Healthy, LlmState::Unreachable, LlmState::ModelMissing] {
+            let name = json!(s);
+            out.sample("sia_llm_state", &[("state", name.as_str().unwrap_or("?"))], (s == current) as u8 as f64);
+        }
+    }
+
+    let process = &snapshot["process"];
+    out.gauge("sia_agent_rss_bytes", "Resident memory of the agent", process["rss_bytes"].as_f64().unwrap_or(0.0));
+    out.gauge("sia_agent_cpu_percent", "CPU used by the agent", process["cpu_percent"].as_f64().unwrap_or(0.0));
+    if let Some(fds) = process["fds"].as_f64() {
+        out.gauge("sia_agent_open_fds", "File descriptors held by the agent", fds);
+    }
+    out.family("sia_agent_load_shedding", "1 for the current load shedding stage", "gauge");
+    for stage in ["none", "drop_debug", "pause_collectors", "shrink_caches"] {
+        out.sample("sia_agent_load_shedding", &[("stage", stage)], (process["shedding"] == stage) as u8 as f64);
+    }
+    out.gauge("sia_agent_event_queue_depth", "Events waiting for the analyzer", snapshot["event_queue"]["depth"].as_f64().unwrap_or(0.0));
+    out.gauge("sia_agent_event_queue_capacity", "Capacity of the event channel", snapshot["event_queue"]["capacity"].as_f64().unwrap_or(0.0));
+    out.gauge("sia_agent_degraded", "1 while the agent reports itself degraded", snapshot["degraded"].as_array().is_some_and(|r| !r.is_empty()) as u8 as f64);
+
+    if let Some(sources) = snapshot["sources"].as_object() {
+        for (field, metric, help) in [
+            ("received", "sia_agent_events_received_total", "Events that reached the analyzer"),
+            ("stored", "sia_agent_events_stored_total", "Events written to the store"),
+            ("dropped", "sia_agent_events_dropped_total", "Events lost before being stored"),
+            ("blocked", "sia_agent_event_sends_blocked_total", "Event sends that waited on a full channel"),
+            ("samples_dropped", "sia_agent_samples_dropped_total", "Metric samples lost"),
+        ] {
+            out.family(metric, help, "counter");
+            for (source, counters) in sources {
+                out.sample(metric, &[("source", source)], counters[field].as_f64().unwrap_or(0.0));
+            }
+        }
+    }
+
+    let writes = &snapshot["storage_writes"];
+    out.counter("sia_agent_storage_writes_total", "Event writes", writes["count"].as_f64().unwrap_or(0.0));
+    out.counter("sia_agent_storage_write_errors_total", "Failed event writes", writes["errors"].as_f64().unwrap_or(0.0));
+    out.counter("sia_agent_storage_write_seconds_total", "Time spent writing events", writes["total_ms"].as_f64().unwrap_or(0.0) / 1000.0);
+
+    if let Some(llm) = snapshot["llm"].as_object() {
+        for (field, metric, help, scale) in [
+            ("count", "sia_agent_llm_requests_total", "Requests sent to the LLM", 1.0),
+            ("errors", "sia_agent_llm_errors_total", "LLM requests that failed", 1.0),
+            ("total_ms", "sia_agent_llm_request_seconds_total", "Time spent waiting on the LLM", 1000.0),
+        ] {
+            out.family(metric, help, "counter");
+            for (purpose, stats) in llm {
+                out.sample(metric, &[("purpose", purpose)], stats[field].as_f64().unwrap_or(0.0) / scale);
+            }
+        }
+    }
+
+    if let Some(ipc) = snapshot["ipc"].as_object() {
+        out.family("sia_agent_ipc_requests_total", "IPC requests by method", "counter");
+        for (method, count) in ipc {
+            out.sample("sia_agent_ipc_requests_total", &[("method", method)], count.as_f64().unwrap_or(0.0));
+        }
+    }
+
+    Ok(out.0)
+}
+
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        if labels.is_empty() {
+            let _ = writeln!(self.0, "{} {}", name, value);
+            return;
+        }
+        let labels: Vec<String> = labels.iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+            .collect();
+        let _ = writeln!(self.0, "{}{{{}}} {}", name, labels.join(","), value);
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.family(name, help, "gauge");
+        self.sample(name, &[], value);
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: f64) {
+        self.family(name, help, "counter");
+        self.sample(name, &[], value);
+    }
+}
@@ -0,0 +1,27 @@
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use anyhow::Result;
+use chrono::Utc;
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// `<prefix>_<millis>_<seq>`: sorts by creation time and stays unique when
+/// several ids are made in the same millisecond
+pub fn unique_id(prefix: &str) -> String {
+    format!("{}_{}_{}", prefix, Utc::now().timestamp_millis(), SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+/// `<prefix>_<32 hex digits>`, for ids that must not be guessable
+pub fn random_id(prefix: &str) -> Result<String> {
+    Ok(format!("{}_{}", prefix, hex(&random_bytes(16)?)))
+}
+
+pub fn random_bytes(n: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; n];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,13 +1,33 @@
 use crate::storage::Storage;
+use crate::llm::{LlmClient, TimeRange};
+use crate::selfmon::metrics;
+use crate::grants::Grants;
+use crate::ids::unique_id;
 use anyhow::Result;
 use tokio::net::{UnixListener, UnixStream};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
 use serde::{Deserialize, Serialize};
 use log::{info, error, warn};
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use common::{AskConfig, IpcAuthConfig, IpcConfig, IpcRole, Severity, Silence, SilenceMatchers};
+use common::grants::TokenClaims;
 
 static START_TIME: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
 
+/// Denials of one (uid, method) within this many seconds share one audit row
+const DENIAL_WINDOW_SECS: i64 = 60;
+
+/// The audit row still being counted into for each (uid, method)
+static DENIALS: std::sync::OnceLock<tokio::sync::Mutex<HashMap<(u32, String), OpenDenial>>> = std::sync::OnceLock::new();
+
+struct OpenDenial {
+    since: i64,
+    audit_id: i64,
+    payload: serde_json::Value,
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "method")]
 enum IpcRequest {
@@ -17,6 +37,187 @@ enum IpcRequest {
     List { limit: Option<i32> },
     #[serde(rename = "show")]
     Show { event_id: String },
+    /// Streams `{"chunk": ...}` lines before the final response
+    #[serde(rename = "explain")]
+    Explain { event_id: String, question: Option<String> },
+    /// Grounded chat; streams like `explain`. `from`/`to` override the range found in the question
+    #[serde(rename = "ask")]
+    Ask {
+        question: String,
+        session_id: Option<String>,
+        from: Option<i64>,
+        to: Option<i64>,
+    },
+    /// The redacted prompt an analysis of this event would send; nothing is sent
+    #[serde(rename = "llm.preview")]
+    LlmPreview { event_id: String },
+    /// Audit records of one kind, e.g. `llm.request`
+    #[serde(rename = "audit.list")]
+    AuditList { kind: String, since: Option<i64>, limit: Option<i64> },
+    /// Counters the agent keeps about itself
+    #[serde(rename = "agent.metrics")]
+    AgentMetrics,
+    /// Database size against the disk quota, table row counts and the last retention run
+    #[serde(rename = "storage.stats")]
+    StorageStats,
+    #[serde(rename = "incidents")]
+    Incidents { limit: Option<i32> },
+    #[serde(rename = "incident.show")]
+    IncidentShow { incident_id: String },
+    /// Registered and discovered services with their open event counts
+    #[serde(rename = "services")]
+    Services,
+    #[serde(rename = "service.show")]
+    ServiceShow { service_id: String },
+    #[serde(rename = "silence.add")]
+    SilenceAdd {
+        #[serde(default)]
+        matchers: SilenceMatchers,
+        starts_at: Option<i64>,
+        ends_at: i64,
+        comment: Option<String>,
+    },
+    #[serde(rename = "silence.list")]
+    SilenceList { all: Option<bool> },
+    #[serde(rename = "silence.expire")]
+    SilenceExpire { silence_id: String },
+    /// The response holds the token; it is not stored anywhere
+    #[serde(rename = "grants.create")]
+    GrantsCreate { service_id: String, scopes: Option<Vec<String>>, ttl_secs: Option<i64> },
+    #[serde(rename = "grants.list")]
+    GrantsList { all: Option<bool> },
+    #[serde(rename = "grants.revoke")]
+    GrantsRevoke { grant_id: String },
+    /// Also allowed with the grant's own token, so services can renew before expiry
+    #[serde(rename = "grants.refresh")]
+    GrantsRefresh { grant_id: String, ttl_secs: Option<i64> },
+}
+
+impl IpcRequest {
+    /// Least role allowed to make this request
+    fn required_role(&self) -> IpcRole {
+        match self {
+            IpcRequest::Status
+            | IpcRequest::List { .. }
+            | IpcRequest::Show { .. }
+            | IpcRequest::AgentMetrics
+            | IpcRequest::StorageStats
+            | IpcRequest::Incidents { .. }
+            | IpcRequest::IncidentShow { .. }
+            | IpcRequest::Services
+            | IpcRequest::ServiceShow { .. }
+            | IpcRequest::SilenceList { .. } => IpcRole::Viewer,
+            // These send host data to the LLM or change what gets notified
+            IpcRequest::Explain { .. }
+            | IpcRequest::Ask { .. }
+            | IpcRequest::LlmPreview { .. }
+            | IpcRequest::SilenceAdd { .. }
+            | IpcRequest::SilenceExpire { .. } => IpcRole::Operator,
+            IpcRequest::AuditList { .. }
+            | IpcRequest::GrantsCreate { .. }
+            | IpcRequest::GrantsList { .. }
+            | IpcRequest::GrantsRevoke { .. }
+            | IpcRequest::GrantsRefresh { .. } => IpcRole::Admin,
+        }
+    }
+    
+    /// Grant scope that allows this request regardless of role; grants can't manage grants
+    fn required_scope(&self) -> Option<&'static str> {
+        match self {
+            IpcRequest::Status | IpcRequest::AgentMetrics | IpcRequest::StorageStats => Some("status:read"),
+            IpcRequest::List { .. } | IpcRequest::Show { .. } => Some("events:read"),
+            IpcRequest::Incidents { .. } | IpcRequest::IncidentShow { .. } => Some("incidents:read"),
+            IpcRequest::Services | IpcRequest::ServiceShow { .. } => Some("services:read"),
+            IpcRequest::SilenceList { .. } => Some("silences:read"),
+            IpcRequest::SilenceAdd { .. } | IpcRequest::SilenceExpire { .. } => Some("silences:write"),
+            IpcRequest::Explain { .. } | IpcRequest::Ask { .. } | IpcRequest::LlmPreview { .. } => Some("llm:query"),
+            IpcRequest::AuditList { .. } => Some("audit:read"),
+            IpcRequest::GrantsCreate { .. }
+            | IpcRequest::GrantsList { .. }
+            | IpcRequest::GrantsRevoke { .. }
+            | IpcRequest::GrantsRefresh { .. } => None,
+        }
+    }
+    
+    /// Whether the peer's role, or else the presented grant, allows this request
+    fn is_allowed(&self, peer: &Peer, grant: Option<&TokenClaims>) -> bool {
+        if peer.role >= Some(self.required_role()) {
+            return true;
+        }
+        let Some(claims) = grant else { return false };
+        match self {
+            IpcRequest::GrantsRefresh { grant_id, .. } => *grant_id == claims.gid,
+            _ => self.required_scope().is_some_and(|scope| claims.scp.iter().any(|s| s == scope)),
+        }
+    }
+}
+
+/// The process on the other end of the socket, from `SO_PEERCRED`
+struct Peer {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+    role: Option<IpcRole>,
+}
+
+impl Peer {
+    fn identify(stream: &UnixStream, auth: &IpcAuthConfig) -> Result<Self> {
+        let cred = stream.peer_cred()?;
+        let (uid, gid, pid) = (cred.uid(), cred.gid(), cred.pid());
+        let role = if auth.enabled {
+            resolve_role(auth, uid, gid, &pid.map(supplementary_groups).unwrap_or_default())
+        } else {
+            Some(IpcRole::Admin)
+        };
+        Ok(Self { uid, gid, pid, role })
+    }
+    
+    /// How the peer is recorded in audits, e.g. `alice (uid 1000)`
+    fn name(&self) -> String {
+        match username(self.uid) {
+            Some(user) => format!("{} (uid {})", user, self.uid),
+            None => format!("uid {}", self.uid),
+        }
+    }
+}
+
+fn resolve_role(auth: &IpcAuthConfig, uid: u32, gid: u32, groups: &[u32]) -> Option<IpcRole> {
+    if uid == 0 || Some(uid) == agent_uid() {
+        return Some(IpcRole::Admin);
+    }
+    auth.roles.iter()
+        .filter(|b| b.uid.is_some() || b.gid.is_some())
+        .filter(|b| b.uid.is_none_or(|u| u == uid))
+        .filter(|b| b.gid.is_none_or(|g| g == gid || groups.contains(&g)))
+        .map(|b| b.role)
+        .max()
+        .or(auth.default_role)
+}
+
+/// `SO_PEERCRED` only carries the primary group
+fn supplementary_groups(pid: i32) -> Vec<u32> {
+    std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()
+        .and_then(|status| status.lines()
+            .find_map(|l| l.strip_prefix("Groups:"))
+            .map(|groups| groups.split_whitespace().filter_map(|g| g.parse().ok()).collect()))
+        .unwrap_or_default()
+}
+
+fn username(uid: u32) -> Option<String> {
+    std::fs::read_to_string("/etc/passwd")
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            (fields.nth(1)?.parse() == Ok(uid)).then(|| name.to_string())
+        })
+}
+
+fn agent_uid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata("/proc/self").ok().map(|m| m.uid())
 }
 
 #[derive(Serialize)]
@@ -25,13 +226,27 @@ struct IpcResponse {
     data: serde_json::Value,
 }
 
-pub async fn start_ipc_server(storage: Storage, socket_path: String) -> Result<()> {
+pub async fn start_ipc_server(
+    storage: Storage,
+    llm: Option<LlmClient>,
+    ask_config: AskConfig,
+    disk_quota_mb: usize,
+    grants: Grants,
+    config: IpcConfig,
+) -> Result<()> {
+    let socket_path = config.socket_path;
+    let auth = config.auth;
+    if !auth.enabled {
+        warn!("IPC authorization disabled, every local user has admin access");
+    }
+    
     // Remove old socket if exists
     let _ = std::fs::remove_file(&socket_path);
     
     let listener = UnixListener::bind(&socket_path)?;
     
-    // Set socket permissions to 0666 (read/write for all users)
+    // Any local user may connect; what they may do is decided per request
+    // from their peer credentials
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
@@ -54,8 +269,12 @@ pub async fn start_ipc_server(storage: Storage, socket_path: String) -> Result<(
             match listener.accept().await {
                 Ok((stream, _)) => {
                     let storage = storage.clone();
+                    let llm = llm.clone();
+                    let ask_config = ask_config.clone();
+                    let grants = grants.clone();
+                    let auth = auth.clone();
                     tokio::spawn(async move {
-                        if let Err(e) = handle_client(stream, storage).await {
+                        if let Err(e) = handle_client(stream, storage, llm, ask_config, disk_quota_mb, grants, &auth).await {
                             error!("Client error: {}", e);
                         }
                     });
@@ -70,7 +289,16 @@ pub async fn start_ipc_server(storage: Storage, socket_path: String) -> Result<(
     Ok(())
 }
 
-async fn handle_client(mut stream: UnixStream, storage: Storage) -> Result<()> {
+async fn handle_client(
+    mut stream: UnixStream,
+    storage: Storage,
+    llm: Option<LlmClient>,
+    ask_config: AskConfig,
+    disk_quota_mb: usize,
+    grants: Grants,
+    auth: &IpcAuthConfig,
+) -> Result<()> {
+    let peer = Peer::identify(&stream, auth)?;
     let mut buffer = vec![0u8; 8192];
     let n = stream.read(&mut buffer).await?;
     
@@ -79,10 +307,77 @@ async fn handle_client(mut stream: UnixStream, storage: Storage) -> Result<()> {
     }
     
     let request_str = String::from_utf8_lossy(&buffer[..n]);
-    info!("IPC request: {}", request_str.trim());
     
-    let response = match serde_json::from_str::<IpcRequest>(&request_str) {
-        Ok(req) => handle_request(req, &storage).await,
+    let value = serde_json::from_str::<serde_json::Value>(&request_str);
+    let token = value.as_ref().ok().and_then(|v| v["token"].as_str()).map(str::to_string);
+    let request = value
+        .and_then(|value| {
+            let method = value["method"].as_str().unwrap_or_default().to_string();
+            serde_json::from_value::<IpcRequest>(value).map(|req| (method, req))
+        })
+        .map(|(method, req)| {
+            // Only known methods are counted, so clients can't grow the map
+            metrics().ipc_request(&method);
+            (method, req)
+        });
+    
+    let method = request.as_ref().map(|(method, _)| method.as_str()).unwrap_or_default();
+    // Not the raw request: it may carry a token
+    match &request {
+        Ok(_) => info!("IPC request from uid {}: {}", peer.uid, method),
+        Err(e) => info!("Unparsable IPC request from uid {}: {}", peer.uid, e),
+    }
+    
+    // A bad token is refused even if the peer's role would have sufficed
+    let grant = match &token {
+        Some(token) => match grants.verify(token).await {
+            Ok(claims) => Some(claims),
+            Err(e) => {
+                let error = format!("Permission denied: invalid token ({})", e);
+                return deny(&mut stream, &storage, &peer, None, method, None, error).await;
+            }
+        },
+        None => None,
+    };
+    
+    match &request {
+        Ok((method, req)) if !req.is_allowed(&peer, grant.as_ref()) => {
+            let role = req.required_role();
+            let error = match req.required_scope() {
+                Some(scope) => format!("Permission denied: {} requires the {} role or the {} scope", method, role.as_str(), scope),
+                None => format!("Permission denied: {} requires the {} role", method, role.as_str()),
+            };
+            return deny(&mut stream, &storage, &peer, grant.as_ref(), method, Some(role), error).await;
+        }
+        // Unknown peers learn nothing, not even whether their request parsed
+        Err(_) if peer.role.is_none() && grant.is_none() => {
+            return deny(&mut stream, &storage, &peer, None, "", None, "Permission denied".to_string()).await;
+        }
+        _ => {}
+    }
+    
+    // Who to record as having made a change: the grant if the request needed
+    // it, otherwise the peer
+    let actor = match (&grant, &request) {
+        (Some(claims), Ok((_, req))) if peer.role < Some(req.required_role()) => format!("grant {}", claims.gid),
+        _ => peer.name(),
+    };
+    
+    let response = match request.map(|(_, req)| req) {
+        Ok(IpcRequest::Explain { event_id, question }) => {
+            handle_explain(&mut stream, &storage, llm, &event_id, question).await?
+        }
+        Ok(IpcRequest::Ask { question, session_id, from, to }) => {
+            let range = TimeRange::resolve(&question, from, to, &ask_config, chrono::Local::now());
+            handle_ask(&mut stream, &storage, llm, ask_config, &actor, question, session_id, range).await?
+        }
+        Ok(IpcRequest::LlmPreview { event_id }) => handle_llm_preview(&storage, llm, &event_id).await,
+        Ok(IpcRequest::StorageStats) => handle_storage_stats(&storage, disk_quota_mb).await,
+        Ok(req @ (IpcRequest::GrantsCreate { .. }
+            | IpcRequest::GrantsList { .. }
+            | IpcRequest::GrantsRevoke { .. }
+            | IpcRequest::GrantsRefresh { .. })) => handle_grants(req, &grants, &actor).await,
+        Ok(req) => handle_request(req, &storage, llm.as_ref(), &actor).await,
         Err(e) => {
             warn!("Invalid request: {}", e);
             IpcResponse {
@@ -99,21 +394,126 @@ async fn handle_client(mut stream: UnixStream, storage: Storage) -> Result<()> {
     Ok(())
 }
 
-async fn handle_request(req: IpcRequest, storage: &Storage) -> IpcResponse {
+/// Refuse the request and record the attempt in the audits table
+async fn deny(
+    stream: &mut UnixStream,
+    storage: &Storage,
+    peer: &Peer,
+    grant: Option<&TokenClaims>,
+    method: &str,
+    required: Option<IpcRole>,
+    error: String,
+) -> Result<()> {
+    warn!(
+        "IPC request {} denied for uid {} (role {}): {}",
+        if method.is_empty() { "?" } else { method },
+        peer.uid,
+        peer.role.map(|r| r.as_str()).unwrap_or("none"),
+        error
+    );
+    let payload = serde_json::json!({
+        "uid": peer.uid,
+        "gid": peer.gid,
+        "pid": peer.pid,
+        "method": method,
+        "role": peer.role,
+        "required": required,
+        "grant_id": grant.map(|g| &g.gid),
+        "service_id": grant.map(|g| &g.sid),
+        "reason": error,
+    });
+    if let Err(e) = audit_denial(storage, peer.uid, method, payload, chrono::Utc::now().timestamp()).await {
+        error!("Failed to audit denied IPC request: {}", e);
+    }
+    
+    let response = IpcResponse { success: false, data: serde_json::json!({"error": error}) };
+    stream.write_all(serde_json::to_string(&response)?.as_bytes()).await?;
+    stream.write_all(b"\n").await?;
+    Ok(())
+}
+
+/// Record a denial, counting repeats of the same (uid, method) within
+/// `DENIAL_WINDOW_SECS` into the first one's row instead of adding rows. The
+/// row keeps the latest details plus `count` and `last_ts`
+async fn audit_denial(storage: &Storage, uid: u32, method: &str, mut payload: serde_json::Value, now: i64) -> Result<()> {
+    let mut denials = DENIALS.get_or_init(Default::default).lock().await;
+    // `method` is empty or a known method name, so keys are bounded per uid
+    denials.retain(|_, open| now - open.since < DENIAL_WINDOW_SECS);
+    
+    payload["last_ts"] = serde_json::json!(now);
+    match denials.get_mut(&(uid, method.to_string())) {
+        Some(open) => {
+            payload["count"] = serde_json::json!(open.payload["count"].as_u64().unwrap_or(1) + 1);
+            storage.update_audit_payload(open.audit_id, &payload).await?;
+            open.payload = payload;
+        }
+        None => {
+            payload["count"] = serde_json::json!(1);
+            let audit_id = storage.insert_audit_returning_id("ipc.denied", &payload).await?;
+            denials.insert((uid, method.to_string()), OpenDenial { since: now, audit_id, payload });
+        }
+    }
+    Ok(())
+}
+
+async fn handle_request(req: IpcRequest, storage: &Storage, llm: Option<&LlmClient>, actor: &str) -> IpcResponse {
     match req {
-        IpcRequest::Status => handle_status(storage).await,
+        IpcRequest::Status => handle_status(storage, llm).await,
+        IpcRequest::AgentMetrics => IpcResponse { success: true, data: metrics().snapshot() },
         IpcRequest::List { limit } => handle_list(storage, limit.unwrap_or(20)).await,
         IpcRequest::Show { event_id } => handle_show(storage, &event_id).await,
+        IpcRequest::Explain { .. } | IpcRequest::Ask { .. } => unreachable!("streamed by handle_client"),
+        IpcRequest::LlmPreview { .. } | IpcRequest::StorageStats => unreachable!("handled by handle_client"),
+        IpcRequest::GrantsCreate { .. }
+        | IpcRequest::GrantsList { .. }
+        | IpcRequest::GrantsRevoke { .. }
+        | IpcRequest::GrantsRefresh { .. } => unreachable!("handled by handle_client"),
+        IpcRequest::Incidents { limit } => handle_incidents(storage, limit.unwrap_or(20)).await,
+        IpcRequest::IncidentShow { incident_id } => handle_incident_show(storage, &incident_id).await,
+        IpcRequest::Services => handle_services(storage).await,
+        IpcRequest::ServiceShow { service_id } => handle_service_show(storage, &service_id).await,
+        IpcRequest::SilenceAdd { matchers, starts_at, ends_at, comment } => {
+            let now = chrono::Utc::now().timestamp();
+            let silence = Silence {
+                id: unique_id("sil"),
+                matchers,
+                starts_at: starts_at.unwrap_or(now),
+                ends_at,
+                created_by: actor.to_string(),
+                comment: comment.unwrap_or_default(),
+                created_at: now,
+            };
+            handle_silence_add(storage, silence).await
+        }
+        IpcRequest::SilenceList { all } => handle_silence_list(storage, all.unwrap_or(false)).await,
+        IpcRequest::SilenceExpire { silence_id } => handle_silence_expire(storage, &silence_id).await,
+        IpcRequest::AuditList { kind, since, limit } => {
+            handle_audit_list(storage, &kind, since.unwrap_or(0), limit.unwrap_or(100)).await
+        }
     }
 }
 
-async fn handle_status(storage: &Storage) -> IpcResponse {
+async fn handle_status(storage: &Storage, llm: Option<&LlmClient>) -> IpcResponse {
     let uptime_secs = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs() - START_TIME.get().unwrap_or(&0);
     
-    let (critical, warning, info) = storage.get_event_counts().await.unwrap_or((0, 0, 0));
+    let counts = storage.get_event_counts().await.unwrap_or_default();
+    let events: serde_json::Map<String, serde_json::Value> = Severity::ALL.iter()
+        .map(|s| (s.as_str().to_lowercase(), counts.get(s).copied().unwrap_or(0).into()))
+        .collect();
+    let llm_jobs = storage.get_llm_job_counts().await.unwrap_or_default();
+    let llm_state = match llm {
+        Some(client) => {
+            let mut state = serde_json::to_value(client.health().status()).unwrap_or_default();
+            state["enabled"] = true.into();
+            state["provider"] = client.provider_name().into();
+            state["model"] = client.model().into();
+            state
+        }
+        None => serde_json::json!({"enabled": false}),
+    };
     
     IpcResponse {
         success: true,
@@ -124,11 +524,9 @@ async fn handle_status(storage: &Storage) -> IpcResponse {
                 "cpu": "active",
                 "memory": "active"
             },
-            "events": {
-                "critical": critical,
-                "warning": warning,
-                "info": info
-            }
+            "events": events,
+            "llm": llm_state,
+            "llm_jobs": llm_jobs
         }),
     }
 }
@@ -143,6 +541,7 @@ async fn handle_list(storage: &Storage, limit: i32) -> IpcResponse {
                     "severity": e.severity,
                     "type": e.type_,
                     "status": e.status,
+                    "silenced": e.silenced_by.is_some(),
                 })
             }).collect();
             
@@ -166,6 +565,15 @@ async fn handle_show(storage: &Storage, event_id: &str) -> IpcResponse {
             // Try to parse snapshot as JSON
             let snapshot: serde_json::Value = serde_json::from_slice(&event.snapshot)
                 .unwrap_or_else(|_| serde_json::json!({}));
+            let llm_job = storage.get_llm_job_for_event(&event.event_id).await.ok().flatten().map(|job| {
+                serde_json::json!({
+                    "status": job.status,
+                    "attempts": job.attempts,
+                    "next_attempt_at": job.next_attempt_at,
+                    "last_error": job.last_error,
+                })
+            });
+            let notifications = storage.get_notifications_for_event(&event.event_id).await.unwrap_or_default();
             
             IpcResponse {
                 success: true,
@@ -176,7 +584,11 @@ async fn handle_show(storage: &Storage, event_id: &str) -> IpcResponse {
                     "type": event.type_,
                     "service_id": event.service_id,
                     "status": event.status,
+                    "silenced_by": event.silenced_by,
                     "snapshot": snapshot,
+                    "suggestion": event.suggestion,
+                    "llm_job": llm_job,
+                    "notifications": notifications,
                 }),
             }
         }
@@ -194,3 +606,526 @@ async fn handle_show(storage: &Storage, event_id: &str) -> IpcResponse {
         }
     }
 }
+
+/// Run an on-demand analysis, forwarding generated text as it arrives, and
+/// save the result as the event's suggestion.
+async fn handle_explain(
+    stream: &mut UnixStream,
+    storage: &Storage,
+    llm: Option<LlmClient>,
+    event_id: &str,
+    question: Option<String>,
+) -> Result<IpcResponse> {
+    let error = |msg: String| Ok(IpcResponse { success: false, data: serde_json::json!({"error": msg}) });
+    
+    let Some(client) = llm else {
+        return error("No LLM provider is configured".to_string());
+    };
+    let event = match storage.get_event_by_id(event_id).await {
+        Ok(Some(event)) => event.to_event(),
+        Ok(None) => return error(format!("Event {} not found", event_id)),
+        Err(e) => return error(format!("Database error: {}", e)),
+    };
+    
+    let (tx, rx) = mpsc::unbounded_channel();
+    let analysis = tokio::spawn(async move {
+        client.explain_event(&event, question.as_deref(), tx).await
+    });
+    
+    forward_chunks(stream, rx).await?;
+    
+    match analysis.await? {
+        Ok(suggestion) => {
+            if let Err(e) = storage.set_event_suggestion(event_id, &suggestion).await {
+                return error(format!("Database error: {}", e));
+            }
+            Ok(IpcResponse { success: true, data: suggestion })
+        }
+        Err(e) => error(format!("LLM analysis failed: {}", e)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_ask(
+    stream: &mut UnixStream,
+    storage: &Storage,
+    llm: Option<LlmClient>,
+    config: AskConfig,
+    owner: &str,
+    question: String,
+    session_id: Option<String>,
+    range: TimeRange,
+) -> Result<IpcResponse> {
+    let Some(client) = llm else {
+        return Ok(IpcResponse { success: false, data: serde_json::json!({"error": "No LLM provider is configured"}) });
+    };
+    
+    let (tx, rx) = mpsc::unbounded_channel();
+    let task_storage = storage.clone();
+    let owner = owner.to_string();
+    let answer = tokio::spawn(async move {
+        client.ask(&task_storage, &config, &owner, session_id, &question, range, tx).await
+    });
+    
+    forward_chunks(stream, rx).await?;
+    
+    Ok(match answer.await? {
+        Ok(data) => IpcResponse { success: true, data },
+        Err(e) => IpcResponse { success: false, data: serde_json::json!({"error": format!("LLM request failed: {}", e)}) },
+    })
+}
+
+async fn handle_audit_list(storage: &Storage, kind: &str, since: i64, limit: i64) -> IpcResponse {
+    match storage.get_audits(kind, since, limit).await {
+        Ok(records) => {
+            let records_json: Vec<_> = records.into_iter()
+                .map(|(ts, payload)| serde_json::json!({"ts": ts, "payload": payload}))
+                .collect();
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({ "kind": kind, "records": records_json }),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to fetch audits: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_storage_stats(storage: &Storage, disk_quota_mb: usize) -> IpcResponse {
+    let stats = match storage.get_storage_stats().await {
+        Ok(stats) => stats,
+        Err(e) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to read storage stats: {}", e)}),
+            }
+        }
+    };
+    let quota_bytes = disk_quota_mb as u64 * 1024 * 1024;
+    let last_retention = storage.get_audits(crate::retention::AUDIT_KIND, 0, 1).await
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .map(|(ts, payload)| serde_json::json!({"ts": ts, "payload": payload}));
+    
+    IpcResponse {
+        success: true,
+        data: serde_json::json!({
+            "db_bytes": stats.db_bytes,
+            "wal_bytes": stats.wal_bytes,
+            "free_bytes": stats.free_bytes,
+            "total_bytes": stats.total_bytes(),
+            "quota_bytes": quota_bytes,
+            "usage_pct": if quota_bytes > 0 { stats.total_bytes() as f64 * 100.0 / quota_bytes as f64 } else { 0.0 },
+            "tables": stats.tables,
+            "last_retention": last_retention,
+        }),
+    }
+}
+
+async fn handle_llm_preview(storage: &Storage, llm: Option<LlmClient>, event_id: &str) -> IpcResponse {
+    let error = |msg: String| IpcResponse { success: false, data: serde_json::json!({"error": msg}) };
+    
+    let Some(client) = llm else {
+        return error("No LLM provider is configured".to_string());
+    };
+    match storage.get_event_by_id(event_id).await {
+        Ok(Some(event)) => IpcResponse { success: true, data: client.preview_event(&event.to_event()).await },
+        Ok(None) => error(format!("Event {} not found", event_id)),
+        Err(e) => error(format!("Database error: {}", e)),
+    }
+}
+
+/// Write `{"chunk": ...}` lines until the producer drops its sender
+async fn forward_chunks(stream: &mut UnixStream, mut rx: mpsc::UnboundedReceiver<String>) -> Result<()> {
+    while let Some(chunk) = rx.recv().await {
+        let line = serde_json::to_string(&serde_json::json!({"chunk": chunk}))?;
+        stream.write_all(line.as_bytes()).await?;
+        stream.write_all(b"\n").await?;
+    }
+    Ok(())
+}
+
+async fn handle_incidents(storage: &Storage, limit: i32) -> IpcResponse {
+    match storage.get_recent_incidents(limit).await {
+        Ok(incidents) => {
+            let mut incidents_json = Vec::with_capacity(incidents.len());
+            for i in &incidents {
+                let event_count = storage.get_incident_members(&i.id).await
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                incidents_json.push(serde_json::json!({
+                    "incident_id": i.id,
+                    "opened_at": i.opened_at,
+                    "updated_at": i.updated_at,
+                    "severity": i.severity,
+                    "status": i.status,
+                    "root_cause": i.root_cause,
+                    "event_count": event_count,
+                }));
+            }
+            
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({ "incidents": incidents_json }),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to fetch incidents: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_services(storage: &Storage) -> IpcResponse {
+    let (services, counts) = match tokio::try_join!(storage.get_services(), storage.get_open_event_counts_by_service()) {
+        Ok(result) => result,
+        Err(e) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to fetch services: {}", e)}),
+            };
+        }
+    };
+    let counts: std::collections::HashMap<String, i64> = counts.into_iter().collect();
+    
+    let services_json: Vec<_> = services.iter().map(|s| {
+        serde_json::json!({
+            "service_id": s.id,
+            "name": s.name,
+            "discovery": s.discovery,
+            "open_events": counts.get(&s.id).copied().unwrap_or(0),
+        })
+    }).collect();
+    // Open events no service claims, host-level or from processes not seen as services
+    let unassigned: i64 = counts.iter()
+        .filter(|(id, _)| !services.iter().any(|s| &s.id == *id))
+        .map(|(_, n)| n)
+        .sum();
+    
+    IpcResponse {
+        success: true,
+        data: serde_json::json!({ "services": services_json, "unassigned_open_events": unassigned }),
+    }
+}
+
+async fn handle_service_show(storage: &Storage, service_id: &str) -> IpcResponse {
+    let service = match storage.get_service(service_id).await {
+        Ok(Some(service)) => service,
+        Ok(None) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Service {} not found", service_id)}),
+            };
+        }
+        Err(e) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Database error: {}", e)}),
+            };
+        }
+    };
+    
+    let result = tokio::try_join!(
+        storage.get_service_open_event_counts(service_id),
+        storage.get_recent_events_for_service(service_id, 10),
+    );
+    match result {
+        Ok((counts, events)) => {
+            let open_events: serde_json::Map<String, serde_json::Value> = counts.into_iter()
+                .map(|(severity, n)| (severity, n.into()))
+                .collect();
+            let events_json: Vec<_> = events.iter().map(|e| {
+                serde_json::json!({
+                    "event_id": e.event_id,
+                    "ts": e.ts,
+                    "severity": e.severity,
+                    "type": e.type_,
+                    "status": e.status,
+                })
+            }).collect();
+            
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({
+                    "service_id": service.id,
+                    "name": service.name,
+                    "discovery": service.discovery,
+                    "default_scopes": service.default_scopes,
+                    "requested_scopes": service.requested_scopes,
+                    "open_events": open_events,
+                    "recent_events": events_json,
+                }),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Database error: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_incident_show(storage: &Storage, incident_id: &str) -> IpcResponse {
+    let incident = match storage.get_incident_by_id(incident_id).await {
+        Ok(Some(incident)) => incident,
+        Ok(None) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Incident {} not found", incident_id)}),
+            };
+        }
+        Err(e) => {
+            return IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Database error: {}", e)}),
+            };
+        }
+    };
+    
+    match storage.get_incident_members(incident_id).await {
+        Ok(members) => {
+            // Members are already ordered by root-cause rank
+            let events_json: Vec<_> = members.iter().enumerate().map(|(rank, m)| {
+                serde_json::json!({
+                    "rank": rank + 1,
+                    "event_id": m.event_id,
+                    "ts": m.ts,
+                    "severity": m.severity,
+                    "type": m.kind,
+                })
+            }).collect();
+            
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({
+                    "incident_id": incident.id,
+                    "opened_at": incident.opened_at,
+                    "updated_at": incident.updated_at,
+                    "severity": incident.severity,
+                    "status": incident.status,
+                    "entities": incident.entities,
+                    "root_cause": incident.root_cause,
+                    "events": events_json,
+                }),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Database error: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_silence_add(storage: &Storage, silence: Silence) -> IpcResponse {
+    if silence.ends_at <= silence.starts_at {
+        return IpcResponse {
+            success: false,
+            data: serde_json::json!({"error": "Silence must end after it starts"}),
+        };
+    }
+    
+    match storage.insert_silence(&silence).await {
+        Ok(()) => {
+            info!("Silence {} created by {}", silence.id, silence.created_by);
+            IpcResponse {
+                success: true,
+                data: serde_json::to_value(&silence).unwrap_or_default(),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to create silence: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_silence_list(storage: &Storage, all: bool) -> IpcResponse {
+    let now = chrono::Utc::now().timestamp();
+    match storage.get_silences(all, now).await {
+        Ok(silences) => {
+            let silences_json: Vec<_> = silences.iter().map(|s| {
+                let state = if s.is_active(now) {
+                    "active"
+                } else if s.starts_at > now {
+                    "pending"
+                } else {
+                    "expired"
+                };
+                let mut value = serde_json::to_value(s).unwrap_or_default();
+                value["state"] = serde_json::json!(state);
+                value
+            }).collect();
+            
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({ "silences": silences_json }),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Failed to fetch silences: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_silence_expire(storage: &Storage, silence_id: &str) -> IpcResponse {
+    match storage.expire_silence(silence_id, chrono::Utc::now().timestamp()).await {
+        Ok(true) => {
+            info!("Silence {} expired", silence_id);
+            IpcResponse {
+                success: true,
+                data: serde_json::json!({ "silence_id": silence_id, "expired": true }),
+            }
+        }
+        Ok(false) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Silence {} not found or already expired", silence_id)}),
+            }
+        }
+        Err(e) => {
+            IpcResponse {
+                success: false,
+                data: serde_json::json!({"error": format!("Database error: {}", e)}),
+            }
+        }
+    }
+}
+
+async fn handle_grants(req: IpcRequest, grants: &Grants, actor: &str) -> IpcResponse {
+    let result = match req {
+        IpcRequest::GrantsCreate { service_id, scopes, ttl_secs } => {
+            grants.create(&service_id, scopes, ttl_secs, actor).await.map(|grant| {
+                info!("Grant {} created for {} by {}", grant.id, grant.service_id, actor);
+                serde_json::json!({ "grant": grant })
+            })
+        }
+        IpcRequest::GrantsRefresh { grant_id, ttl_secs } => {
+            grants.refresh(&grant_id, ttl_secs, actor).await.map(|grant| {
+                info!("Grant {} refreshed by {}", grant.id, actor);
+                serde_json::json!({ "grant": grant })
+            })
+        }
+        IpcRequest::GrantsRevoke { grant_id } => match grants.revoke(&grant_id, actor).await {
+            Ok(true) => {
+                info!("Grant {} revoked by {}", grant_id, actor);
+                Ok(serde_json::json!({ "grant_id": grant_id, "revoked": true }))
+            }
+            Ok(false) => Err(anyhow::anyhow!("Grant {} not found or already revoked", grant_id)),
+            Err(e) => Err(e),
+        },
+        IpcRequest::GrantsList { all } => grants.list(all.unwrap_or(false)).await.map(|grants| {
+            serde_json::json!({ "grants": grants, "now": chrono::Utc::now().timestamp() })
+        }),
+        _ => unreachable!("not a grants request"),
+    };
+    match result {
+        Ok(data) => IpcResponse { success: true, data },
+        Err(e) => IpcResponse { success: false, data: serde_json::json!({"error": e.to_string()}) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::IpcRoleBinding;
+
+    fn binding(uid: Option<u32>, gid: Option<u32>, role: IpcRole) -> IpcRoleBinding {
+        IpcRoleBinding { uid, gid, role }
+    }
+
+    async fn test_storage(name: &str) -> Storage {
+        let path = std::env::temp_dir().join(format!("sia-ipc-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        std::fs::File::create(&path).unwrap();
+        Storage::new(path.to_str().unwrap()).await.unwrap()
+    }
+
+    #[test]
+    fn resolves_highest_matching_role() {
+        let mut auth = IpcAuthConfig {
+            enabled: true,
+            default_role: None,
+            roles: vec![
+                binding(Some(1000), None, IpcRole::Viewer),
+                binding(None, Some(27), IpcRole::Operator),
+                binding(Some(1000), Some(4), IpcRole::Admin),
+                // Matches everyone; ignored
+                binding(None, None, IpcRole::Admin),
+            ],
+        };
+        assert_eq!(resolve_role(&auth, 1000, 1000, &[]), Some(IpcRole::Viewer));
+        assert_eq!(resolve_role(&auth, 1000, 1000, &[27]), Some(IpcRole::Operator));
+        assert_eq!(resolve_role(&auth, 1000, 1000, &[4, 27]), Some(IpcRole::Admin));
+        assert_eq!(resolve_role(&auth, 1001, 4, &[]), None);
+        assert_eq!(resolve_role(&auth, 0, 0, &[]), Some(IpcRole::Admin));
+
+        auth.default_role = Some(IpcRole::Viewer);
+        assert_eq!(resolve_role(&auth, 1001, 4, &[]), Some(IpcRole::Viewer));
+    }
+
+    #[test]
+    fn audit_reads_need_admin() {
+        let audit: IpcRequest = serde_json::from_str(r#"{"method":"audit.list","kind":"ipc.denied","since":0}"#).unwrap();
+        let silence: IpcRequest = serde_json::from_str(r#"{"method":"silence.list"}"#).unwrap();
+        assert_eq!(audit.required_role(), IpcRole::Admin);
+        assert_eq!(silence.required_role(), IpcRole::Viewer);
+        assert!(Some(IpcRole::Operator) < Some(audit.required_role()));
+        assert!(None < Some(silence.required_role()));
+    }
+
+    #[test]
+    fn grants_allow_only_their_scopes() {
+        let peer = Peer { uid: 1234, gid: 1234, pid: None, role: None };
+        let claims = TokenClaims {
+            gid: "grant_1".to_string(),
+            sid: "backup".to_string(),
+            scp: vec!["events:read".to_string()],
+            iat: 0,
+            exp: i64::MAX,
+        };
+        let request = |json: &str| serde_json::from_str::<IpcRequest>(json).unwrap();
+        assert!(request(r#"{"method":"list"}"#).is_allowed(&peer, Some(&claims)));
+        assert!(!request(r#"{"method":"list"}"#).is_allowed(&peer, None));
+        assert!(!request(r#"{"method":"silence.list"}"#).is_allowed(&peer, Some(&claims)));
+        // A grant may renew itself but not others, and never create grants
+        assert!(request(r#"{"method":"grants.refresh","grant_id":"grant_1"}"#).is_allowed(&peer, Some(&claims)));
+        assert!(!request(r#"{"method":"grants.refresh","grant_id":"grant_2"}"#).is_allowed(&peer, Some(&claims)));
+        assert!(!request(r#"{"method":"grants.create","service_id":"backup"}"#).is_allowed(&peer, Some(&claims)));
+    }
+
+    #[tokio::test]
+    async fn coalesces_repeated_denials() {
+        let storage = test_storage("denials").await;
+        let denial = |method: &str| serde_json::json!({"uid": 4242, "method": method});
+        let now = chrono::Utc::now().timestamp();
+        
+        for i in 0..5 {
+            audit_denial(&storage, 4242, "silence.add", denial("silence.add"), now + i).await.unwrap();
+        }
+        audit_denial(&storage, 4242, "grant.create", denial("grant.create"), now + 5).await.unwrap();
+        // A new window starts a new row
+        audit_denial(&storage, 4242, "silence.add", denial("silence.add"), now + DENIAL_WINDOW_SECS).await.unwrap();
+        
+        let records = storage.get_audits("ipc.denied", 0, 10).await.unwrap();
+        let counts: Vec<(&str, u64)> = records.iter()
+            .map(|(_, p)| (p["method"].as_str().unwrap(), p["count"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(counts, vec![("silence.add", 1), ("grant.create", 1), ("silence.add", 5)]);
+        assert_eq!(records[2].1["last_ts"], serde_json::json!(now + 4));
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use serde_json::{json, Value};
+use super::{ChatMessage, ChatRequest, ChatResponse, LlmProvider, ModelCheck, Role, ToolCall};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic Messages API
+pub struct AnthropicProvider {
+    client: Client,
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+    // tool_use blocks
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    input: Value,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl AnthropicProvider {
+    pub fn new(client: Client, base_url: String, model: String, api_key: String) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+        }
+    }
+}
+
+/// Non-system turns as content blocks; consecutive tool results are sent
+/// together in one user turn, as the Messages API requires.
+fn wire_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    let mut wire: Vec<Value> = Vec::new();
+    for message in messages.iter().filter(|m| m.role != Role::System) {
+        match message.role {
+            Role::Tool => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                });
+                let previous_is_results = wire.last().is_some_and(|m| {
+                    m["role"] == "user" && m["content"][0]["type"] == "tool_result"
+                });
+                match wire.last_mut() {
+                    Some(last) if previous_is_results => {
+                        if let Some(blocks) = last["content"].as_array_mut() {
+                            blocks.push(block);
+                        }
+                    }
+                    _ => wire.push(json!({"role": "user", "content": [block]})),
+                }
+            }
+            Role::Assistant if !message.tool_calls.is_empty() => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({"type": "text", "text": message.content}));
+                }
+                blocks.extend(message.tool_calls.iter().map(|c| {
+                    json!({"type": "tool_use", "id": c.id, "name": c.name, "input": c.arguments})
+                }));
+                wire.push(json!({"role": "assistant", "content": blocks}));
+            }
+            _ => wire.push(json!({"role": message.role, "content": message.content})),
+        }
+    }
+    wire
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        // The Messages API takes the system prompt as a top-level field
+        let system: Vec<&str> = request.messages.iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        let body = MessagesRequest {
+            model: &self.model,
+            max_tokens: request.max_tokens,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages: wire_messages(&request.messages),
+            tools: request.tools.iter()
+                .map(|t| json!({"name": t.name, "description": t.description, "input_schema": t.parameters}))
+                .collect(),
+        };
+
+        let url = format!("{}/v1/messages", self.base_url);
+        let response = self.client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Anthropic request failed with status {}", response.status());
+        }
+
+        let response: MessagesResponse = response.json().await?;
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block.kind.as_str() {
+                "text" => content.push_str(&block.text),
+                "tool_use" => tool_calls.push(ToolCall { id: block.id, name: block.name, arguments: block.input }),
+                _ => {}
+            }
+        }
+
+        Ok(ChatResponse {
+            content,
+            tool_calls,
+            input_tokens: response.usage.as_ref().and_then(|u| u.input_tokens),
+            output_tokens: response.usage.as_ref().and_then(|u| u.output_tokens),
+        })
+    }
+
+    /// Model aliases aren't listed by `/v1/models`, so only reachability is checked
+    async fn health_check(&self) -> Result<ModelCheck> {
+        let url = format!("{}/v1/models", self.base_url);
+        let response = self.client
+            .get(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Anthropic returned status {}", response.status());
+        }
+        Ok(ModelCheck::Unknown)
+    }
+}
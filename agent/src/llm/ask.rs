@@ -0,0 +1,381 @@
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use common::AskConfig;
+use crate::ids::random_id;
+use crate::storage::Storage;
+use super::{ChatMessage, ChatRequest, LlmClient, Redactions};
+use anyhow::Result;
+
+/// Longest rendering of a single event in the prompt
+const MAX_EVENT_CHARS: usize = 400;
+
+/// Time range a question is about, as unix seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from: i64,
+    pub to: i64,
+}
+
+impl TimeRange {
+    /// Explicit bounds win; otherwise look for a time named in the question
+    /// ("at 3am", "14:30 yesterday", "last 2 hours") and fall back to the
+    /// last `default_window_mins`.
+    pub fn resolve(question: &str, from: Option<i64>, to: Option<i64>, config: &AskConfig, now: DateTime<Local>) -> Self {
+        let now_ts = now.timestamp();
+        if from.is_some() || to.is_some() {
+            let to = to.unwrap_or(now_ts);
+            return Self { from: from.unwrap_or(to - config.default_window_mins * 60), to };
+        }
+
+        let question = question.to_lowercase();
+        let words: Vec<&str> = question
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        // "last 2 hours", "past 30 minutes", "last hour"
+        for (i, word) in words.iter().enumerate() {
+            if *word != "last" && *word != "past" {
+                continue;
+            }
+            let (count, unit) = match words.get(i + 1).and_then(|w| w.parse::<i64>().ok()) {
+                Some(n) => (n, words.get(i + 2)),
+                None => (1, words.get(i + 1)),
+            };
+            let secs = match unit.map(|u| u.trim_end_matches('s')) {
+                Some("minute") | Some("min") => 60,
+                Some("hour") | Some("hr") => 3600,
+                Some("day") => 86400,
+                _ => continue,
+            };
+            return Self { from: now_ts - count * secs, to: now_ts };
+        }
+
+        let yesterday = words.contains(&"yesterday");
+        let around = config.around_window_mins * 60;
+        if let Some(time) = find_clock_time(&words) {
+            let mut day = now.date_naive();
+            if yesterday {
+                day -= Duration::days(1);
+            }
+            let mut at = Local.from_local_datetime(&day.and_time(time)).earliest();
+            // "at 3am" asked at 10:00 means today; asked at 02:00 it means yesterday
+            if !yesterday && at.is_some_and(|t| t > now) {
+                at = Local.from_local_datetime(&(day - Duration::days(1)).and_time(time)).earliest();
+            }
+            if let Some(at) = at {
+                let at = at.timestamp();
+                return Self { from: at - around, to: (at + around).min(now_ts) };
+            }
+        }
+
+        if yesterday {
+            let today = now.date_naive().and_time(NaiveTime::MIN);
+            if let Some(midnight) = Local.from_local_datetime(&today).earliest() {
+                let midnight = midnight.timestamp();
+                return Self { from: midnight - 86400, to: midnight };
+            }
+        }
+
+        Self { from: now_ts - config.default_window_mins * 60, to: now_ts }
+    }
+}
+
+/// "3am", "3 pm", "03:15", "3:15pm"; bare numbers are not times
+fn find_clock_time(words: &[&str]) -> Option<NaiveTime> {
+    for (i, word) in words.iter().enumerate() {
+        let (digits, mut meridiem) = match word.strip_suffix("am").or_else(|| word.strip_suffix("pm")) {
+            Some(rest) => (rest, Some(&word[rest.len()..])),
+            None => (*word, None),
+        };
+        if meridiem.is_none() {
+            meridiem = words.get(i + 1).copied().filter(|w| *w == "am" || *w == "pm");
+        }
+        if meridiem.is_none() && !digits.contains(':') {
+            continue;
+        }
+
+        let (hour, minute) = match digits.split_once(':') {
+            Some((h, m)) => (h.parse::<u32>().ok(), m.parse::<u32>().ok()),
+            None => (digits.parse::<u32>().ok(), Some(0)),
+        };
+        let (Some(mut hour), Some(minute)) = (hour, minute) else { continue };
+        match meridiem {
+            Some("am") if hour == 12 => hour = 0,
+            Some("pm") if hour < 12 => hour += 12,
+            _ => {}
+        }
+        if let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) {
+            return Some(time);
+        }
+    }
+    None
+}
+
+/// A piece of retrieved context the model can cite by `id`
+struct Evidence {
+    id: String,
+    kind: &'static str,
+    reference: String,
+    text: String,
+}
+
+impl LlmClient {
+    /// Answer a question about the host from data stored for `range`,
+    /// continuing `session_id` when given, which only its `owner` may do.
+    /// The answer is streamed to `chunks`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn ask(
+        &self,
+        storage: &Storage,
+        config: &AskConfig,
+        owner: &str,
+        session_id: Option<String>,
+        question: &str,
+        range: TimeRange,
+        chunks: mpsc::UnboundedSender<String>,
+    ) -> Result<Value> {
+        let session_id = match session_id {
+            // Someone else's session looks the same as one that doesn't exist
+            Some(id) if storage.get_chat_session_owner(&id).await?.as_deref() != Some(owner) => {
+                anyhow::bail!("unknown chat session {}", id)
+            }
+            Some(id) => id,
+            None => random_id("chat")?,
+        };
+
+        let mut redactions = Redactions::default();
+        let evidence = gather_evidence(storage, config, range, &mut |v| self.redact_json(v, &mut redactions)).await?;
+        let evidence = select_within_budget(evidence, config.context_tokens);
+        let context: String = evidence.iter().map(|e| format!("[{}] {}\n", e.id, e.text)).collect();
+
+        let mut messages = vec![ChatMessage::system(format!(
+            "You are a system administrator AI assistant answering questions about this host. \
+             Use only the evidence below, collected between {} and {} (local time). \
+             Cite every piece of evidence you rely on by its tag, e.g. [E2]. \
+             If the evidence does not answer the question, say so.\n\nEvidence:\n{}",
+            format_local(range.from),
+            format_local(range.to),
+            if context.is_empty() { "(nothing recorded in this range)\n".to_string() } else { context },
+        ))];
+        messages.extend(history_within_budget(storage.get_chat_messages(&session_id).await?, config.history_tokens));
+        messages.push(ChatMessage::user(question));
+
+        let request = ChatRequest { messages, max_tokens: 768, json: false, tools: Vec::new() };
+        let response = self.send_stream(&request, &mut redactions, "ask", None, chunks).await?;
+        let answer = response.content.trim().to_string();
+
+        let citations: Vec<Value> = evidence.iter()
+            .filter(|e| answer.contains(&format!("[{}]", e.id)))
+            .map(|e| json!({"id": e.id, "kind": e.kind, "ref": e.reference, "text": self.restore(&e.text, &redactions)}))
+            .collect();
+        let citations = Value::Array(citations);
+
+        storage.insert_chat_message(&session_id, owner, "user", question, None).await?;
+        storage.insert_chat_message(&session_id, owner, "assistant", &answer, Some(&citations)).await?;
+
+        Ok(json!({
+            "session_id": session_id,
+            "answer": answer,
+            "citations": citations,
+            "range": {"from": range.from, "to": range.to},
+            "evidence_count": evidence.len(),
+            "source": self.provider.name(),
+            "model": self.provider.model(),
+            "usage": {
+                "input_tokens": response.input_tokens,
+                "output_tokens": response.output_tokens
+            },
+        }))
+    }
+}
+
+/// Metric summaries, then incidents, then events (most severe, then newest first)
+/// `redact` is applied to event data so deny/allow field rules hold here too
+async fn gather_evidence(
+    storage: &Storage,
+    config: &AskConfig,
+    range: TimeRange,
+    redact: &mut (dyn FnMut(&Value) -> Value + Send),
+) -> Result<Vec<Evidence>> {
+    let mut evidence = Vec::new();
+
+    for (i, m) in storage.get_metric_summaries(range.from, range.to).await?.into_iter().enumerate() {
+        evidence.push(Evidence {
+            id: format!("M{}", i + 1),
+            kind: "metric",
+            text: format!(
+                "{}: min {:.1}, avg {:.1}, max {:.1} over {} samples ({} to {})",
+                m.name, m.min, m.avg, m.max, m.samples, format_local(m.first_ts), format_local(m.last_ts)
+            ),
+            reference: m.name,
+        });
+    }
+
+    for (i, inc) in storage.get_incidents_between(range.from, range.to).await?.into_iter().enumerate() {
+        evidence.push(Evidence {
+            id: format!("I{}", i + 1),
+            kind: "incident",
+            text: format!(
+                "incident {} ({}, {}) from {} to {}, entities: {}, root cause event: {}",
+                inc.id, inc.severity, inc.status, format_local(inc.opened_at), format_local(inc.updated_at),
+                inc.entities.join(", "), inc.root_cause.as_deref().unwrap_or("unknown")
+            ),
+            reference: inc.id,
+        });
+    }
+
+    let mut events = storage.get_events_between(range.from, range.to, config.max_events).await?;
+    events.sort_by_key(|e| (std::cmp::Reverse(e.severity), std::cmp::Reverse(e.ts)));
+    for (i, event) in events.into_iter().enumerate() {
+        let stored_ts = event.ts;
+        let event = event.to_event();
+        // Cap each event so one noisy snapshot can't eat the whole budget
+        let text: String = format!(
+            "{} {} {} entity={} evidence={}",
+            format_local(stored_ts),
+            event.severity, event.r#type, redact(&event.entity), redact(&event.evidence)
+        ).chars().take(MAX_EVENT_CHARS).collect();
+        evidence.push(Evidence {
+            id: format!("E{}", i + 1),
+            kind: "event",
+            reference: event.event_id,
+            text,
+        });
+    }
+
+    Ok(evidence)
+}
+
+/// Rough token estimate; good enough for budgeting prompts
+fn estimate_tokens(text: &str) -> usize {
+    text.len() / 4 + 1
+}
+
+fn select_within_budget(evidence: Vec<Evidence>, budget: usize) -> Vec<Evidence> {
+    let mut used = 0;
+    evidence.into_iter()
+        .take_while(|e| {
+            used += estimate_tokens(&e.text);
+            used <= budget
+        })
+        .collect()
+}
+
+/// Most recent turns that fit the budget, oldest first
+fn history_within_budget(turns: Vec<(String, String)>, budget: usize) -> Vec<ChatMessage> {
+    let mut used = 0;
+    let mut kept: Vec<ChatMessage> = turns.into_iter().rev()
+        .take_while(|(_, content)| {
+            used += estimate_tokens(content);
+            used <= budget
+        })
+        .map(|(role, content)| match role.as_str() {
+            "assistant" => ChatMessage::assistant(content),
+            _ => ChatMessage::user(content),
+        })
+        .collect();
+    kept.reverse();
+    kept
+}
+
+fn format_local(ts: i64) -> String {
+    Local.timestamp_opt(ts, 0).single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Role;
+
+    /// Wednesday 2026-01-14, local time
+    fn at(day: u32, hour: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, day, hour, min, 0).unwrap()
+    }
+
+    #[test]
+    fn resolves_times_named_in_questions() {
+        let config = AskConfig::default();
+        let now = at(14, 10, 0);
+        let around = |t: DateTime<Local>| (t.timestamp() - 1800, (t.timestamp() + 1800).min(now.timestamp()));
+        let back = |secs: i64| (now.timestamp() - secs, now.timestamp());
+
+        let cases = [
+            ("what happened at 3am?", around(at(14, 3, 0))),
+            ("what broke at 14:30 yesterday", around(at(13, 14, 30))),
+            ("why was it slow around 9:45", around(at(14, 9, 45))),
+            ("anything at 12am", around(at(14, 0, 0))),
+            // Later than now today, so yesterday
+            ("what ran at 12pm", around(at(13, 12, 0))),
+            ("errors at 11 pm", around(at(13, 23, 0))),
+            ("errors in the last 2 hours", back(7200)),
+            ("past 30 minutes", back(1800)),
+            ("the last hour", back(3600)),
+            ("what happened yesterday", (at(13, 0, 0).timestamp(), at(14, 0, 0).timestamp())),
+            ("is the disk ok", back(3600)),
+        ];
+        for (question, (from, to)) in cases {
+            assert_eq!(TimeRange::resolve(question, None, None, &config, now), TimeRange { from, to }, "{}", question);
+        }
+
+        // Explicit bounds win over the question
+        let range = TimeRange::resolve("at 3am", Some(100), None, &config, now);
+        assert_eq!(range, TimeRange { from: 100, to: now.timestamp() });
+        let range = TimeRange::resolve("at 3am", None, Some(10_000), &config, now);
+        assert_eq!(range, TimeRange { from: 10_000 - 3600, to: 10_000 });
+    }
+
+    #[test]
+    fn finds_clock_times() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+        let cases = [
+            ("at 3am", time(3, 0)),
+            ("at 3 pm", time(15, 0)),
+            ("03:15 today", time(3, 15)),
+            ("3:15pm", time(15, 15)),
+            ("12am", time(0, 0)),
+            ("12pm", time(12, 0)),
+            ("over 3 servers", None),
+            ("25:00", None),
+        ];
+        for (question, expected) in cases {
+            let words: Vec<&str> = question.split_whitespace().collect();
+            assert_eq!(find_clock_time(&words), expected, "{}", question);
+        }
+    }
+
+    /// Text estimated at `tokens` tokens
+    fn text(tokens: usize) -> String {
+        "x".repeat((tokens - 1) * 4)
+    }
+
+    #[test]
+    fn keeps_evidence_in_order_until_the_budget_runs_out() {
+        let evidence = |id: &str, tokens| Evidence {
+            id: id.to_string(),
+            kind: "event",
+            reference: id.to_string(),
+            text: text(tokens),
+        };
+        let selected = select_within_budget(vec![evidence("e1", 10), evidence("e2", 10), evidence("e3", 10), evidence("e4", 1)], 25);
+        let ids: Vec<&str> = selected.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["e1", "e2"]);
+        assert!(select_within_budget(vec![evidence("e1", 10)], 9).is_empty());
+    }
+
+    #[test]
+    fn keeps_the_most_recent_history_that_fits() {
+        let turns = vec![
+            ("user".to_string(), text(10)),
+            ("assistant".to_string(), text(10)),
+            ("user".to_string(), text(10)),
+        ];
+        let kept = history_within_budget(turns.clone(), 25);
+        let roles: Vec<Role> = kept.iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![Role::Assistant, Role::User]);
+        assert_eq!(history_within_budget(turns, 30).len(), 3);
+    }
+}
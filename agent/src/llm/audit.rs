@@ -0,0 +1,121 @@
+use sha2::{Digest, Sha256};
+use serde_json::json;
+use tokio::time::{interval, Duration};
+use crate::storage::Storage;
+use super::{ChatRequest, ChatResponse};
+use log::{info, error};
+
+/// `audits.kind` of LLM exchange records
+pub const AUDIT_KIND: &str = "llm.request";
+
+/// Records every request sent to the model and what came back.
+///
+/// Prompts are recorded after redaction, exactly as they left the host.
+/// By default only their SHA-256 is kept so the audit table doesn't become a
+/// second copy of the data; `full_text` stores the text as well.
+pub struct LlmAuditor {
+    storage: Storage,
+    full_text: bool,
+}
+
+/// One exchange with the provider
+pub struct Exchange<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    /// analyze, explain or ask
+    pub purpose: &'a str,
+    pub event_id: Option<&'a str>,
+    pub request: &'a ChatRequest,
+    pub response: Result<&'a ChatResponse, String>,
+    pub latency: Duration,
+    pub redactions: usize,
+}
+
+impl LlmAuditor {
+    pub fn new(storage: Storage, full_text: bool) -> Self {
+        Self { storage, full_text }
+    }
+
+    /// Failures are logged; a broken audit table doesn't stop analysis
+    pub async fn record(&self, exchange: Exchange<'_>) {
+        let prompt = prompt_text(exchange.request);
+        let mut payload = json!({
+            "provider": exchange.provider,
+            "model": exchange.model,
+            "purpose": exchange.purpose,
+            "event_id": exchange.event_id,
+            "prompt_sha256": sha256(&prompt),
+            "tools": exchange.request.tools.iter().map(|t| t.name).collect::<Vec<_>>(),
+            "redactions": exchange.redactions,
+            "latency_ms": exchange.latency.as_millis() as u64,
+        });
+        if self.full_text {
+            payload["prompt"] = json!(prompt);
+        }
+
+        match exchange.response {
+            Ok(response) => {
+                let text = response_text(response);
+                payload["outcome"] = json!("ok");
+                payload["input_tokens"] = json!(response.input_tokens);
+                payload["output_tokens"] = json!(response.output_tokens);
+                payload["response_sha256"] = json!(sha256(&text));
+                if self.full_text {
+                    payload["response"] = json!(text);
+                }
+            }
+            Err(e) => {
+                payload["outcome"] = json!("error");
+                payload["error"] = json!(e);
+            }
+        }
+
+        if let Err(e) = self.storage.insert_audit(AUDIT_KIND, &payload).await {
+            error!("Failed to record LLM audit: {}", e);
+        }
+    }
+
+    /// Delete LLM audit records older than `retention_days`, hourly
+    pub fn start_retention(&self, retention_days: u64) {
+        let storage = self.storage.clone();
+        let max_age = retention_days.max(1) as i64 * 86400;
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(3600));
+            loop {
+                ticker.tick().await;
+                let cutoff = chrono::Utc::now().timestamp() - max_age;
+                match storage.delete_audits_before(AUDIT_KIND, cutoff).await {
+                    Ok(0) => {}
+                    Ok(n) => info!("Deleted {} LLM audit records past retention", n),
+                    Err(e) => error!("LLM audit retention failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+/// The request as sent: one block per message, tool calls included
+fn prompt_text(request: &ChatRequest) -> String {
+    request.messages.iter()
+        .map(|m| {
+            let mut block = format!("[{}]\n{}", json!(m.role).as_str().unwrap_or("?"), m.content);
+            for call in &m.tool_calls {
+                block.push_str(&format!("\n<tool call {}: {}>", call.name, call.arguments));
+            }
+            block
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn response_text(response: &ChatResponse) -> String {
+    let mut text = response.content.clone();
+    for call in &response.tool_calls {
+        text.push_str(&format!("\n<tool call {}: {}>", call.name, call.arguments));
+    }
+    text
+}
+
+fn sha256(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
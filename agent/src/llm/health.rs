@@ -0,0 +1,125 @@
+use std::sync::{Arc, RwLock};
+use serde::Serialize;
+use tokio::time::{interval, Duration};
+use common::LlmHealthConfig;
+use crate::storage::Storage;
+use super::{LlmClient, LlmQueue, ModelCheck};
+use log::{info, warn, error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmState {
+    /// Not probed yet
+    Unknown,
+    Healthy,
+    Unreachable,
+    /// The provider answers but doesn't serve the configured model
+    ModelMissing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmHealthStatus {
+    pub state: LlmState,
+    /// When the current state began
+    pub since: i64,
+    pub last_check: Option<i64>,
+    pub last_error: Option<String>,
+    /// Events queued by the last recovery backfill
+    pub backfilled: usize,
+}
+
+/// Current provider health, shared by the checker, the job queue and `status`
+#[derive(Clone)]
+pub struct LlmHealth(Arc<RwLock<LlmHealthStatus>>);
+
+impl Default for LlmHealth {
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(LlmHealthStatus {
+            state: LlmState::Unknown,
+            since: chrono::Utc::now().timestamp(),
+            last_check: None,
+            last_error: None,
+            backfilled: 0,
+        })))
+    }
+}
+
+impl LlmHealth {
+    /// Background analysis only runs while the provider is known to be healthy
+    pub fn is_available(&self) -> bool {
+        self.0.read().unwrap().state == LlmState::Healthy
+    }
+
+    pub fn status(&self) -> LlmHealthStatus {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Record a probe result; returns the previous state
+    fn update(&self, state: LlmState, error: Option<String>) -> LlmState {
+        let mut status = self.0.write().unwrap();
+        let previous = status.state;
+        let now = chrono::Utc::now().timestamp();
+        if state != previous {
+            status.since = now;
+        }
+        status.state = state;
+        status.last_check = Some(now);
+        status.last_error = error;
+        previous
+    }
+
+    fn set_backfilled(&self, count: usize) {
+        self.0.write().unwrap().backfilled = count;
+    }
+}
+
+/// Probe the provider every `interval_secs`, pausing background analysis
+/// while it is down and queueing CRITICAL events it missed once it recovers.
+pub fn start_health_checker(client: LlmClient, queue: LlmQueue, storage: Storage, config: LlmHealthConfig) {
+    info!("Starting LLM health checker ({}s interval)", config.interval_secs.max(1));
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+
+            let (state, error) = match client.health_check().await {
+                Ok(ModelCheck::Available) | Ok(ModelCheck::Unknown) => (LlmState::Healthy, None),
+                Ok(ModelCheck::Missing(served)) => {
+                    let served = if served.is_empty() { "none".to_string() } else { served.join(", ") };
+                    (LlmState::ModelMissing, Some(format!("model {} is not available (have: {})", client.model(), served)))
+                }
+                Err(e) => (LlmState::Unreachable, Some(e.to_string())),
+            };
+
+            let previous = client.health().update(state, error.clone());
+            if state == previous {
+                continue;
+            }
+            match state {
+                LlmState::Healthy => {
+                    info!("LLM available ({}), analysis enabled", client.describe());
+                    match backfill(&queue, &storage, config.backfill_hours).await {
+                        Ok(count) => {
+                            client.health().set_backfilled(count);
+                            if count > 0 {
+                                info!("Queued {} CRITICAL events missed while the LLM was unavailable", count);
+                            }
+                        }
+                        Err(e) => error!("LLM backfill failed: {}", e),
+                    }
+                }
+                _ => warn!("LLM unavailable ({}), analysis paused: {}", client.describe(), error.unwrap_or_default()),
+            }
+        }
+    });
+}
+
+async fn backfill(queue: &LlmQueue, storage: &Storage, hours: u64) -> anyhow::Result<usize> {
+    let since = chrono::Utc::now().timestamp() - hours as i64 * 3600;
+    let events = storage.get_unanalyzed_critical_events(since).await?;
+    for event in &events {
+        queue.enqueue(&event.to_event()).await?;
+    }
+    Ok(events.len())
+}
@@ -0,0 +1,885 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use common::{Event, LlmConfig, LlmProviderConfig, LlmProviderKind};
+use anyhow::Result;
+use log::warn;
+
+mod ollama;
+mod openai;
+mod anthropic;
+mod suggestion;
+mod queue;
+mod ask;
+mod tools;
+mod redact;
+mod audit;
+mod prompts;
+mod health;
+
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiProvider;
+pub use anthropic::AnthropicProvider;
+pub use suggestion::{Suggestion, SUGGESTION_SCHEMA};
+pub use queue::{start_llm_queue, LlmQueue};
+pub use ask::TimeRange;
+pub use tools::Toolbox;
+pub use redact::{Redactions, Redactor};
+pub use audit::{LlmAuditor, AUDIT_KIND};
+pub use prompts::PromptTemplates;
+pub use health::{start_health_checker, LlmHealth, LlmState};
+use audit::Exchange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// Result of a tool call requested by the assistant
+    Tool,
+}
+
+/// Provider-neutral chat turn; each backend maps it to its wire format
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    /// Tools the assistant asked to run (assistant turns only)
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a tool turn answers
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: Role, content: impl Into<String>) -> Self {
+        Self { role, content: content.into(), tool_calls: Vec::new(), tool_call_id: None }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    pub fn tool_result(call: &ToolCall, content: impl Into<String>) -> Self {
+        Self { tool_call_id: Some(call.id.clone()), ..Self::new(Role::Tool, content) }
+    }
+}
+
+/// A function the model may call, described by a JSON schema
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+    /// Ask the backend to constrain output to a JSON object where supported
+    pub json: bool,
+    /// Tools offered to the model; empty disables tool calling
+    pub tools: Vec<ToolSpec>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+/// A chat-capable model backend
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Short provider name recorded with suggestions ("ollama", "openai", ...)
+    fn name(&self) -> &'static str;
+    fn model(&self) -> &str;
+    fn url(&self) -> &str;
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse>;
+    /// Like `chat`, sending text to `chunks` as it is generated. Backends
+    /// without streaming support send the whole reply as one chunk.
+    async fn chat_stream(&self, request: &ChatRequest, chunks: mpsc::UnboundedSender<String>) -> Result<ChatResponse> {
+        let response = self.chat(request).await?;
+        let _ = chunks.send(response.content.clone());
+        Ok(response)
+    }
+    /// Cheap probe: errors when the backend is unreachable, otherwise
+    /// reports whether the configured model is there when it can tell
+    async fn health_check(&self) -> Result<ModelCheck>;
+}
+
+/// What a health probe learned about the configured model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelCheck {
+    Available,
+    /// Not served; holds the models that are
+    Missing(Vec<String>),
+    /// The backend can't list its models
+    Unknown,
+}
+
+/// Build the provider selected by `[llm] provider`
+pub fn build_provider(config: &LlmConfig) -> Result<Arc<dyn LlmProvider>> {
+    let settings = config.active()?;
+    let client = Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_secs))
+        .build()?;
+    let api_key = read_api_key(&settings)?;
+
+    let provider: Arc<dyn LlmProvider> = match config.provider {
+        LlmProviderKind::Ollama => Arc::new(OllamaProvider::new(client, settings.url, settings.model)),
+        LlmProviderKind::OpenAi => Arc::new(OpenAiProvider::new(client, settings.url, settings.model, api_key)),
+        LlmProviderKind::Anthropic => {
+            let api_key = api_key.ok_or_else(|| anyhow::anyhow!("[llm.anthropic] requires api_key_file"))?;
+            Arc::new(AnthropicProvider::new(client, settings.url, settings.model, api_key))
+        }
+    };
+    Ok(provider)
+}
+
+fn read_api_key(settings: &LlmProviderConfig) -> Result<Option<String>> {
+    match settings.api_key_file {
+        Some(ref path) => {
+            let key = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("cannot read API key file {}: {}", path, e))?;
+            Ok(Some(key.trim().to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Clone)]
+pub struct LlmClient {
+    provider: Arc<dyn LlmProvider>,
+    toolbox: Option<Arc<Toolbox>>,
+    redactor: Option<Arc<Redactor>>,
+    auditor: Option<Arc<LlmAuditor>>,
+    prompts: Arc<PromptTemplates>,
+    health: LlmHealth,
+}
+
+impl LlmClient {
+    pub fn new(provider: Arc<dyn LlmProvider>) -> Self {
+        Self {
+            provider,
+            toolbox: None,
+            redactor: None,
+            auditor: None,
+            prompts: Arc::new(PromptTemplates::builtin()),
+            health: LlmHealth::default(),
+        }
+    }
+
+    /// Let the model call read-only diagnostics while analyzing events
+    pub fn with_tools(self, toolbox: Toolbox) -> Self {
+        Self { toolbox: Some(Arc::new(toolbox)), ..self }
+    }
+
+    /// Mask secrets and identifying data in everything sent to the provider
+    pub fn with_redactor(self, redactor: Redactor) -> Self {
+        Self { redactor: Some(Arc::new(redactor)), ..self }
+    }
+
+    /// Render event prompts from these templates instead of the built-in ones
+    pub fn with_prompts(self, prompts: PromptTemplates) -> Self {
+        Self { prompts: Arc::new(prompts), ..self }
+    }
+
+    /// Record every request and response in the audit log
+    pub fn with_auditor(self, auditor: LlmAuditor) -> Self {
+        Self { auditor: Some(Arc::new(auditor)), ..self }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {} at {}", self.provider.name(), self.provider.model(), self.provider.url())
+    }
+
+    /// Shared state kept current by the health checker
+    pub fn health(&self) -> &LlmHealth {
+        &self.health
+    }
+
+    pub async fn health_check(&self) -> Result<ModelCheck> {
+        self.provider.health_check().await
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    pub fn model(&self) -> &str {
+        self.provider.model()
+    }
+
+    pub async fn analyze_event(&self, event: &Event) -> Result<Value> {
+        let mut redactions = Redactions::default();
+        let mut request = self.analysis_request(event, &mut redactions).await;
+
+        let mut transcript = Vec::new();
+        // Shared with the repair attempt, so it can't start a fresh budget
+        let mut tool_rounds = 0;
+        let mut response = self.chat_with_tools(&mut request, &mut redactions, &event.event_id, &mut transcript, &mut tool_rounds).await?;
+        let mut usage = (response.input_tokens, response.output_tokens);
+
+        let mut parsed = Suggestion::parse(&response.content);
+        if let Err(ref e) = parsed {
+            // One repair attempt: show the model its reply and what was wrong with it
+            warn!("Malformed suggestion for {} ({}), asking model to repair it", event.event_id, e);
+            request.messages.push(ChatMessage::assistant(response.content.clone()));
+            request.messages.push(ChatMessage::user(format!(
+                "That reply was not valid: {}. Reply again with only the corrected JSON object.",
+                e
+            )));
+            response = self.chat_with_tools(&mut request, &mut redactions, &event.event_id, &mut transcript, &mut tool_rounds).await?;
+            usage = (add_tokens(usage.0, response.input_tokens), add_tokens(usage.1, response.output_tokens));
+            parsed = Suggestion::parse(&response.content);
+        }
+
+        let mut suggestion = match parsed {
+            Ok(structured) => {
+                let mut value = serde_json::to_value(&structured)?;
+                value["format"] = json!("structured");
+                value
+            }
+            Err(e) => {
+                // Keep whatever the model said rather than losing the analysis
+                warn!("Falling back to text suggestion for {}: {}", event.event_id, e);
+                json!({
+                    "format": "text",
+                    "analysis": response.content.trim(),
+                })
+            }
+        };
+
+        suggestion["source"] = json!(self.provider.name());
+        suggestion["model"] = json!(self.provider.model());
+        suggestion["usage"] = json!({
+            "input_tokens": usage.0,
+            "output_tokens": usage.1
+        });
+        suggestion["timestamp"] = json!(chrono::Utc::now().to_rfc3339());
+        if self.toolbox.is_some() {
+            suggestion["tool_transcript"] = Value::Array(transcript);
+        }
+        suggestion["redactions"] = json!(redactions.entries().count());
+
+        Ok(suggestion)
+    }
+
+    /// Exactly what `analyze_event` would send first, after redaction, without sending it
+    pub async fn preview_event(&self, event: &Event) -> Value {
+        let mut redactions = Redactions::default();
+        let mut request = self.analysis_request(event, &mut redactions).await;
+        if let Some(toolbox) = &self.toolbox {
+            request.tools = toolbox.specs().await;
+        }
+        let request = self.redact_request(&request, &mut redactions);
+
+        json!({
+            "provider": self.provider.name(),
+            "model": self.provider.model(),
+            "redaction_enabled": self.redactor.is_some(),
+            "messages": request.messages.iter()
+                .map(|m| json!({"role": m.role, "content": m.content}))
+                .collect::<Vec<_>>(),
+            "tools": request.tools.iter().map(|t| t.name).collect::<Vec<_>>(),
+            "redactions": redactions.entries()
+                .map(|(placeholder, kind, original)| json!({"placeholder": placeholder, "kind": kind, "original": original}))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    async fn analysis_request(&self, event: &Event, redactions: &mut Redactions) -> ChatRequest {
+        let tools_note = if self.toolbox.is_some() {
+            "You may call the provided read-only tools to gather more information first. "
+        } else {
+            ""
+        };
+        let prompt = self.create_prompt(event, redactions).await;
+        ChatRequest {
+            messages: vec![
+                ChatMessage::system(format!(
+                    "You are a system administrator AI assistant analyzing a system event. {}\
+                     Reply with a single JSON object matching this schema and nothing else:\n{}",
+                    tools_note,
+                    SUGGESTION_SCHEMA
+                )),
+                ChatMessage::user(prompt),
+            ],
+            max_tokens: 768,
+            // Constrained JSON output keeps some backends from emitting tool calls
+            json: self.toolbox.is_none(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Chat, running any tools the model calls and feeding the results back
+    /// until it answers or `max_iterations` tool rounds have been spent, counting
+    /// the ones already in `tool_rounds`. Every call is appended to `transcript`;
+    /// usage covers all round-trips.
+    async fn chat_with_tools(
+        &self,
+        request: &mut ChatRequest,
+        redactions: &mut Redactions,
+        event_id: &str,
+        transcript: &mut Vec<Value>,
+        tool_rounds: &mut usize,
+    ) -> Result<ChatResponse> {
+        let Some(ref toolbox) = self.toolbox else {
+            return self.send(request, redactions, "analyze", Some(event_id)).await;
+        };
+        request.tools = toolbox.specs().await;
+
+        let mut usage = (None, None);
+        loop {
+            let final_turn = *tool_rounds >= toolbox.max_iterations;
+            if final_turn {
+                // Tool definitions stay in the request (some APIs require them
+                // alongside earlier tool turns); just demand the answer now.
+                request.messages.push(ChatMessage::user(
+                    "Tool call limit reached. Do not call any more tools; give your final answer now."
+                ));
+            }
+
+            let mut response = self.send(request, redactions, "analyze", Some(event_id)).await?;
+            usage = (add_tokens(usage.0, response.input_tokens), add_tokens(usage.1, response.output_tokens));
+            if response.tool_calls.is_empty() || final_turn {
+                response.input_tokens = usage.0;
+                response.output_tokens = usage.1;
+                return Ok(response);
+            }
+
+            *tool_rounds += 1;
+            let mut turn = ChatMessage::assistant(response.content.clone());
+            turn.tool_calls = response.tool_calls.clone();
+            request.messages.push(turn);
+            for call in &response.tool_calls {
+                let output = toolbox.run(call).await;
+                transcript.push(json!({
+                    "iteration": *tool_rounds,
+                    "tool": call.name,
+                    "arguments": call.arguments,
+                    "output": output,
+                }));
+                request.messages.push(ChatMessage::tool_result(call, output));
+            }
+        }
+    }
+
+    /// Free-text analysis of any event on request, streamed to `chunks`
+    pub async fn explain_event(&self, event: &Event, question: Option<&str>, chunks: mpsc::UnboundedSender<String>) -> Result<Value> {
+        let mut redactions = Redactions::default();
+        let mut prompt = self.create_prompt(event, &mut redactions).await;
+        if let Some(question) = question.filter(|q| !q.trim().is_empty()) {
+            prompt.push_str(&format!("\n\nThe operator also asks: {}", question.trim()));
+        }
+
+        let request = ChatRequest {
+            messages: vec![
+                ChatMessage::system(
+                    "You are a system administrator AI assistant analyzing a system event. \
+                     Answer in plain text for an operator reading a terminal; be concise."
+                ),
+                ChatMessage::user(prompt),
+            ],
+            max_tokens: 1024,
+            json: false,
+            tools: Vec::new(),
+        };
+
+        let response = self.send_stream(&request, &mut redactions, "explain", Some(&event.event_id), chunks).await?;
+
+        Ok(json!({
+            "format": "text",
+            "analysis": response.content.trim(),
+            "question": question,
+            "source": self.provider.name(),
+            "model": self.provider.model(),
+            "usage": {
+                "input_tokens": response.input_tokens,
+                "output_tokens": response.output_tokens
+            },
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "redactions": redactions.entries().count(),
+        }))
+    }
+
+    /// Redact the request, send it, record it, and map placeholders in the reply back
+    async fn send(&self, request: &ChatRequest, redactions: &mut Redactions, purpose: &str, event_id: Option<&str>) -> Result<ChatResponse> {
+        let outgoing = self.redact_request(request, redactions);
+        let started = Instant::now();
+        let response = self.provider.chat(&outgoing).await;
+        self.audit(&outgoing, &response, started, redactions, purpose, event_id).await;
+        Ok(self.restore_response(response?, redactions))
+    }
+
+    /// Streaming `send`; chunks are restored before they reach `chunks`
+    async fn send_stream(
+        &self,
+        request: &ChatRequest,
+        redactions: &mut Redactions,
+        purpose: &str,
+        event_id: Option<&str>,
+        chunks: mpsc::UnboundedSender<String>,
+    ) -> Result<ChatResponse> {
+        let outgoing = self.redact_request(request, redactions);
+        let started = Instant::now();
+        let Some(ref redactor) = self.redactor else {
+            let response = self.provider.chat_stream(&outgoing, chunks).await;
+            self.audit(&outgoing, &response, started, redactions, purpose, event_id).await;
+            return response;
+        };
+
+        let (raw_tx, mut raw_rx) = mpsc::unbounded_channel::<String>();
+        let relay_redactor = redactor.clone();
+        let relay_redactions = redactions.clone();
+        let relay = tokio::spawn(async move {
+            // A placeholder can be split across chunks; hold back from an unclosed '<'
+            let mut pending = String::new();
+            while let Some(chunk) = raw_rx.recv().await {
+                pending.push_str(&chunk);
+                let split = match pending.rfind('<') {
+                    Some(pos) if !pending[pos..].contains('>') => pos,
+                    _ => pending.len(),
+                };
+                let ready: String = pending.drain(..split).collect();
+                if !ready.is_empty() {
+                    let _ = chunks.send(relay_redactor.restore_text(&ready, &relay_redactions));
+                }
+            }
+            if !pending.is_empty() {
+                let _ = chunks.send(relay_redactor.restore_text(&pending, &relay_redactions));
+            }
+        });
+
+        let response = self.provider.chat_stream(&outgoing, raw_tx).await;
+        relay.await?;
+        self.audit(&outgoing, &response, started, redactions, purpose, event_id).await;
+        Ok(self.restore_response(response?, redactions))
+    }
+
+    /// Audit what actually went over the wire: the redacted request and the raw reply.
+    /// Also counted in the agent metrics, audited or not
+    async fn audit(
+        &self,
+        outgoing: &ChatRequest,
+        response: &Result<ChatResponse>,
+        started: Instant,
+        redactions: &Redactions,
+        purpose: &str,
+        event_id: Option<&str>,
+    ) {
+        crate::selfmon::metrics().llm_request(purpose, started.elapsed(), response.is_ok());
+        let Some(ref auditor) = self.auditor else { return };
+        auditor.record(Exchange {
+            provider: self.provider.name(),
+            model: self.provider.model(),
+            purpose,
+            event_id,
+            request: outgoing,
+            response: response.as_ref().map_err(|e| e.to_string()),
+            latency: started.elapsed(),
+            redactions: redactions.entries().count(),
+        }).await;
+    }
+
+    fn redact_request(&self, request: &ChatRequest, redactions: &mut Redactions) -> ChatRequest {
+        let Some(ref redactor) = self.redactor else {
+            return request.clone();
+        };
+        let mut outgoing = request.clone();
+        for message in &mut outgoing.messages {
+            message.content = redactor.redact_text(&message.content, redactions);
+            for call in &mut message.tool_calls {
+                call.arguments = redactor.redact_value(&call.arguments, redactions);
+            }
+        }
+        outgoing
+    }
+
+    fn restore_response(&self, mut response: ChatResponse, redactions: &Redactions) -> ChatResponse {
+        if let Some(ref redactor) = self.redactor {
+            response.content = redactor.restore_text(&response.content, redactions);
+            for call in &mut response.tool_calls {
+                call.arguments = redactor.restore_value(&call.arguments, redactions);
+            }
+        }
+        response
+    }
+
+    fn restore(&self, text: &str, redactions: &Redactions) -> String {
+        match self.redactor {
+            Some(ref redactor) => redactor.restore_text(text, redactions),
+            None => text.to_string(),
+        }
+    }
+
+    /// Structural redaction of event data (deny/allow fields); text detectors run again on send
+    fn redact_json(&self, value: &Value, redactions: &mut Redactions) -> Value {
+        match self.redactor {
+            Some(ref redactor) => redactor.redact_value(value, redactions),
+            None => value.clone(),
+        }
+    }
+
+    /// Event prompt from the template for its type, with entity and evidence redacted
+    async fn create_prompt(&self, event: &Event, redactions: &mut Redactions) -> String {
+        let entity = self.redact_json(&event.entity, redactions);
+        let evidence = self.redact_json(&event.evidence, redactions);
+        self.prompts.render(event, &entity, &evidence).await
+    }
+}
+
+fn add_tokens(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{EventKind, Severity};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn test_event(kind: &str, severity: Severity) -> Event {
+        Event {
+            event_id: format!("{}_1", kind),
+            ts: "2025-01-01T00:00:00Z".to_string(),
+            severity,
+            r#type: EventKind::from(kind),
+            entity: json!({}),
+            evidence: json!({}),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        }
+    }
+
+    /// Request captured by the mock server
+    struct Captured {
+        request_line: String,
+        headers: String,
+        body: Value,
+    }
+
+    /// Serve one HTTP request per entry in `responses`, in order, and hand back what was received
+    async fn mock_server(responses: Vec<Value>) -> (String, mpsc::UnboundedReceiver<Captured>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let header_end = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head.lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let body = serde_json::to_vec(&response).unwrap();
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+
+                let (request_line, headers) = head.split_once("\r\n").unwrap();
+                let _ = tx.send(Captured {
+                    request_line: request_line.to_string(),
+                    headers: headers.to_ascii_lowercase(),
+                    body: serde_json::from_slice(&buf[header_end..]).unwrap_or(Value::Null),
+                });
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            messages: vec![ChatMessage::system("be brief"), ChatMessage::user("why is cpu high?")],
+            max_tokens: 64,
+            json: false,
+            tools: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn ollama_uses_chat_endpoint() {
+        let (url, mut captured) = mock_server(vec![json!({
+            "message": {"role": "assistant", "content": "a runaway process"},
+            "prompt_eval_count": 12,
+            "eval_count": 4
+        })]).await;
+        let provider = OllamaProvider::new(Client::new(), url, "llama3.2".to_string());
+
+        let response = provider.chat(&request()).await.unwrap();
+        let captured = captured.recv().await.unwrap();
+
+        assert_eq!(response.content, "a runaway process");
+        assert_eq!(response.input_tokens, Some(12));
+        assert_eq!(captured.request_line, "POST /api/chat HTTP/1.1");
+        assert_eq!(captured.body["model"], "llama3.2");
+        assert_eq!(captured.body["stream"], false);
+        assert_eq!(captured.body["messages"][0]["role"], "system");
+    }
+
+    #[tokio::test]
+    async fn openai_compatible_sends_bearer_token() {
+        let (url, mut captured) = mock_server(vec![json!({
+            "choices": [{"message": {"role": "assistant", "content": "restart the service"}}],
+            "usage": {"prompt_tokens": 20, "completion_tokens": 5}
+        })]).await;
+        let provider = OpenAiProvider::new(Client::new(), url, "qwen".to_string(), Some("sk-test".to_string()));
+
+        let response = provider.chat(&request()).await.unwrap();
+        let captured = captured.recv().await.unwrap();
+
+        assert_eq!(response.content, "restart the service");
+        assert_eq!(response.output_tokens, Some(5));
+        assert_eq!(captured.request_line, "POST /v1/chat/completions HTTP/1.1");
+        assert!(captured.headers.contains("authorization: bearer sk-test"));
+        assert_eq!(captured.body["max_tokens"], 64);
+        assert_eq!(captured.body["messages"][1]["content"], "why is cpu high?");
+    }
+
+    #[tokio::test]
+    async fn anthropic_moves_system_prompt_out_of_messages() {
+        let (url, mut captured) = mock_server(vec![json!({
+            "content": [{"type": "text", "text": "check swap usage"}],
+            "usage": {"input_tokens": 30, "output_tokens": 6}
+        })]).await;
+        let provider = AnthropicProvider::new(Client::new(), url, "claude".to_string(), "key".to_string());
+
+        let response = provider.chat(&request()).await.unwrap();
+        let captured = captured.recv().await.unwrap();
+
+        assert_eq!(response.content, "check swap usage");
+        assert_eq!(captured.request_line, "POST /v1/messages HTTP/1.1");
+        assert!(captured.headers.contains("x-api-key: key"));
+        assert!(captured.headers.contains("anthropic-version:"));
+        assert_eq!(captured.body["system"], "be brief");
+        assert_eq!(captured.body["messages"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn http_errors_are_reported() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            stream.write_all(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await.unwrap();
+        });
+        let provider = OllamaProvider::new(Client::new(), url, "llama3.2".to_string());
+
+        let err = provider.chat(&request()).await.unwrap_err();
+        assert!(err.to_string().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn ollama_health_check_requires_pulled_model() {
+        let (url, mut captured) = mock_server(vec![
+            json!({"models": [{"name": "llama3.2:latest"}, {"name": "qwen2.5:7b"}]}),
+            json!({"models": [{"name": "qwen2.5:7b"}]}),
+        ]).await;
+        let provider = OllamaProvider::new(Client::new(), url, "llama3.2".to_string());
+
+        assert_eq!(provider.health_check().await.unwrap(), ModelCheck::Available);
+        assert_eq!(provider.health_check().await.unwrap(), ModelCheck::Missing(vec!["qwen2.5:7b".to_string()]));
+        assert_eq!(captured.recv().await.unwrap().request_line, "GET /api/tags HTTP/1.1");
+    }
+
+    #[tokio::test]
+    async fn ollama_streams_ndjson_chunks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            let lines = [
+                json!({"message": {"role": "assistant", "content": "disk "}, "done": false}),
+                json!({"message": {"role": "assistant", "content": "is full"}, "done": false}),
+                json!({"message": {"role": "assistant", "content": ""}, "done": true, "prompt_eval_count": 9, "eval_count": 2}),
+            ];
+            let body: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+            let head = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n", body.len());
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(body.as_bytes()).await.unwrap();
+        });
+        let provider = OllamaProvider::new(Client::new(), url, "llama3.2".to_string());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let response = provider.chat_stream(&request(), tx).await.unwrap();
+
+        assert_eq!(response.content, "disk is full");
+        assert_eq!(response.output_tokens, Some(2));
+        assert_eq!(rx.recv().await.unwrap(), "disk ");
+        assert_eq!(rx.recv().await.unwrap(), "is full");
+    }
+
+    #[tokio::test]
+    async fn malformed_suggestion_is_repaired_once() {
+        let valid = json!({
+            "summary": "memory pressure from a leaking worker",
+            "probable_causes": ["worker leaks memory"],
+            "immediate_actions": [{"description": "restart the worker", "command": "systemctl restart worker", "risk": "medium"}],
+            "prevention": ["set MemoryMax on the unit"],
+            "confidence": 0.7
+        });
+        let (url, mut captured) = mock_server(vec![
+            json!({"message": {"role": "assistant", "content": "{\"summary\": \"oops\"}"}}),
+            json!({"message": {"role": "assistant", "content": valid.to_string()}}),
+        ]).await;
+        let client = LlmClient::new(Arc::new(OllamaProvider::new(Client::new(), url, "llama3.2".to_string())));
+        let event = test_event("memory_high", Severity::Critical);
+
+        let suggestion = client.analyze_event(&event).await.unwrap();
+        let first = captured.recv().await.unwrap();
+        let repair = captured.recv().await.unwrap();
+
+        assert_eq!(first.body["format"], "json");
+        assert_eq!(repair.body["messages"].as_array().unwrap().len(), 4);
+        assert_eq!(suggestion["format"], "structured");
+        assert_eq!(suggestion["immediate_actions"][0]["risk"], "medium");
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_run_and_recorded() {
+        let valid = json!({
+            "summary": "the agent itself is busy",
+            "probable_causes": ["test load"],
+            "immediate_actions": [{"description": "wait", "command": null, "risk": "low"}],
+            "prevention": [],
+            "confidence": 0.5
+        });
+        let pid = std::process::id();
+        let (url, mut captured) = mock_server(vec![
+            json!({"message": {"role": "assistant", "content": "", "tool_calls": [
+                {"function": {"name": "read_proc", "arguments": {"pid": pid}}}
+            ]}}),
+            json!({"message": {"role": "assistant", "content": valid.to_string()}}),
+        ]).await;
+
+        let db = std::env::temp_dir().join(format!("sia-tools-test-{}.db", pid));
+        std::fs::write(&db, b"").unwrap();
+        let storage = crate::storage::Storage::new(db.to_str().unwrap()).await.unwrap();
+        let client = LlmClient::new(Arc::new(OllamaProvider::new(Client::new(), url, "llama3.2".to_string())))
+            .with_tools(Toolbox::new(storage, 3));
+        let mut event = test_event("cpu_high", Severity::Critical);
+        event.entity = json!({"top_process": {"pid": pid}});
+
+        let suggestion = client.analyze_event(&event).await.unwrap();
+        let first = captured.recv().await.unwrap();
+        let second = captured.recv().await.unwrap();
+        let _ = std::fs::remove_file(&db);
+
+        assert_eq!(first.body["tools"].as_array().unwrap().len(), 4);
+        let messages = second.body["messages"].as_array().unwrap();
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["name"], "read_proc");
+        assert_eq!(messages[3]["role"], "tool");
+        assert!(messages[3]["content"].as_str().unwrap().contains("cmdline"));
+        assert_eq!(suggestion["format"], "structured");
+        assert_eq!(suggestion["tool_transcript"][0]["tool"], "read_proc");
+    }
+
+    #[tokio::test]
+    async fn unrepairable_suggestion_falls_back_to_text() {
+        let (url, _captured) = mock_server(vec![
+            json!({"message": {"role": "assistant", "content": "just restart it"}}),
+            json!({"message": {"role": "assistant", "content": "seriously, restart it"}}),
+        ]).await;
+        let client = LlmClient::new(Arc::new(OllamaProvider::new(Client::new(), url, "llama3.2".to_string())));
+        let event = test_event("cpu_high", Severity::Critical);
+
+        let suggestion = client.analyze_event(&event).await.unwrap();
+
+        assert_eq!(suggestion["format"], "text");
+        assert_eq!(suggestion["analysis"], "seriously, restart it");
+    }
+
+    #[tokio::test]
+    async fn secrets_are_redacted_before_sending_and_restored_after() {
+        let (url, mut captured) = mock_server(vec![
+            json!({"message": {"role": "assistant", "content": "connections from <IP_1> are failing auth"}}),
+            json!({"message": {"role": "assistant", "content": "connections from <IP_1> are failing auth"}}),
+        ]).await;
+        let redactor = Redactor::new(&common::RedactionConfig::default()).unwrap();
+        let client = LlmClient::new(Arc::new(OllamaProvider::new(Client::new(), url, "llama3.2".to_string())))
+            .with_redactor(redactor);
+        let mut event = test_event("auth_failures", Severity::Critical);
+        event.entity = json!({"source": "10.1.2.3", "environ": "AWS_PROFILE=prod"});
+        event.evidence = json!({"cmdline": "deploy --password=hunter2 --notify ops@example.com"});
+
+        let suggestion = client.analyze_event(&event).await.unwrap();
+        let sent = captured.recv().await.unwrap().body.to_string();
+
+        for secret in ["10.1.2.3", "hunter2", "ops@example.com", "AWS_PROFILE"] {
+            assert!(!sent.contains(secret), "{} reached the provider", secret);
+        }
+        assert!(sent.contains("--password=<SECRET_1>"));
+        assert_eq!(suggestion["analysis"], "connections from 10.1.2.3 are failing auth");
+        assert_eq!(suggestion["redactions"], 4);
+    }
+
+    #[tokio::test]
+    async fn prompt_template_is_chosen_by_event_type() {
+        let prompts = PromptTemplates::builtin();
+        let event = |kind: &str| Event {
+            entity: json!({"cpu_usage": 97.5, "top_process": {"name": "stress", "pid": 42, "cpu": 390.0}}),
+            evidence: json!({"threshold": 95.0}),
+            ..test_event(kind, Severity::Critical)
+        };
+
+        let cpu = event("cpu_high");
+        let prompt = prompts.render(&cpu, &cpu.entity, &cpu.evidence).await;
+        assert!(prompt.starts_with("High CPU usage"));
+        assert!(prompt.contains("stress (pid 42)"));
+
+        let other = event("fan_failure");
+        let prompt = prompts.render(&other, &other.entity, &other.evidence).await;
+        assert!(prompt.contains("- Type: fan_failure"));
+    }
+}
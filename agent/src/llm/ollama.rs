@@ -0,0 +1,182 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use tokio::sync::mpsc;
+use serde_json::{json, Value};
+use super::{ChatMessage, ChatRequest, ChatResponse, LlmProvider, ModelCheck, Role, ToolCall};
+
+/// Ollama's native `/api/chat` endpoint
+pub struct OllamaProvider {
+    client: Client,
+    base_url: String,
+    model: String,
+}
+
+#[derive(Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    messages: Vec<Value>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'static str>,
+    options: OllamaOptions,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    num_predict: u32,
+}
+
+#[derive(Deserialize)]
+struct OllamaMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunction,
+}
+
+#[derive(Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: OllamaMessage,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+/// One line of a streamed (NDJSON) `/api/chat` reply
+#[derive(Deserialize)]
+struct OllamaStreamChunk {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+}
+
+impl OllamaProvider {
+    pub fn new(client: Client, base_url: String, model: String) -> Self {
+        Self { client, base_url, model }
+    }
+
+    fn body<'a>(&'a self, request: &'a ChatRequest, stream: bool) -> OllamaRequest<'a> {
+        OllamaRequest {
+            model: &self.model,
+            messages: request.messages.iter().map(wire_message).collect(),
+            stream,
+            format: request.json.then_some("json"),
+            options: OllamaOptions { num_predict: request.max_tokens },
+            tools: request.tools.iter()
+                .map(|t| json!({"type": "function", "function": t}))
+                .collect(),
+        }
+    }
+}
+
+fn wire_message(message: &ChatMessage) -> Value {
+    let mut wire = json!({"role": message.role, "content": message.content});
+    if message.role == Role::Assistant && !message.tool_calls.is_empty() {
+        wire["tool_calls"] = message.tool_calls.iter()
+            .map(|c| json!({"function": {"name": c.name, "arguments": c.arguments}}))
+            .collect();
+    }
+    wire
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/api/chat", self.base_url);
+        let response = self.client.post(&url).json(&self.body(request, false)).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama request failed with status {}", response.status());
+        }
+
+        let response: OllamaResponse = response.json().await?;
+        Ok(ChatResponse {
+            content: response.message.content,
+            // Ollama doesn't assign call ids; number them so results can be matched up
+            tool_calls: response.message.tool_calls.into_iter().enumerate()
+                .map(|(i, c)| ToolCall { id: format!("call_{}", i), name: c.function.name, arguments: c.function.arguments })
+                .collect(),
+            input_tokens: response.prompt_eval_count,
+            output_tokens: response.eval_count,
+        })
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest, chunks: mpsc::UnboundedSender<String>) -> Result<ChatResponse> {
+        let url = format!("{}/api/chat", self.base_url);
+        let mut response = self.client.post(&url).json(&self.body(request, true)).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama request failed with status {}", response.status());
+        }
+
+        let mut result = ChatResponse { content: String::new(), tool_calls: Vec::new(), input_tokens: None, output_tokens: None };
+        let mut pending = Vec::new();
+        while let Some(bytes) = response.chunk().await? {
+            pending.extend_from_slice(&bytes);
+            // HTTP chunks don't line up with NDJSON lines; only parse complete lines
+            while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                let chunk: OllamaStreamChunk = serde_json::from_slice(&line)?;
+                if let Some(message) = chunk.message.filter(|m| !m.content.is_empty()) {
+                    result.content.push_str(&message.content);
+                    let _ = chunks.send(message.content);
+                }
+                if chunk.done {
+                    result.input_tokens = chunk.prompt_eval_count;
+                    result.output_tokens = chunk.eval_count;
+                    return Ok(result);
+                }
+            }
+        }
+
+        anyhow::bail!("Ollama stream ended before completion")
+    }
+
+    async fn health_check(&self) -> Result<ModelCheck> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama returned status {}", response.status());
+        }
+
+        let tags: Value = response.json().await?;
+        let pulled: Vec<String> = tags["models"].as_array()
+            .map(|models| models.iter().filter_map(|m| m["name"].as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        // "llama3.2" is pulled as "llama3.2:latest"
+        let found = pulled.iter().any(|name| name == &self.model || name.strip_suffix(":latest") == Some(&self.model));
+        Ok(if found { ModelCheck::Available } else { ModelCheck::Missing(pulled) })
+    }
+}
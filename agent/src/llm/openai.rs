@@ -0,0 +1,171 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use serde_json::{json, Value};
+use super::{ChatMessage, ChatRequest, ChatResponse, LlmProvider, ModelCheck, Role, ToolCall};
+
+/// Any server implementing the OpenAI `/v1/chat/completions` API
+/// (vLLM, llama.cpp server, LM Studio, ...)
+pub struct OpenAiProvider {
+    client: Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<Value>,
+    max_tokens: u32,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<WireToolCall>,
+}
+
+#[derive(Deserialize)]
+struct WireToolCall {
+    id: String,
+    function: WireFunction,
+}
+
+#[derive(Deserialize)]
+struct WireFunction {
+    name: String,
+    /// JSON-encoded arguments
+    arguments: String,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
+}
+
+impl OpenAiProvider {
+    pub fn new(client: Client, base_url: String, model: String, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').trim_end_matches("/v1").to_string(),
+            model,
+            api_key,
+        }
+    }
+
+    fn authorized(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.api_key {
+            Some(ref key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+}
+
+fn wire_message(message: &ChatMessage) -> Value {
+    match message.role {
+        Role::Tool => json!({
+            "role": "tool",
+            "tool_call_id": message.tool_call_id,
+            "content": message.content,
+        }),
+        Role::Assistant if !message.tool_calls.is_empty() => json!({
+            "role": "assistant",
+            "content": (!message.content.is_empty()).then_some(&message.content),
+            "tool_calls": message.tool_calls.iter().map(|c| json!({
+                "id": c.id,
+                "type": "function",
+                "function": {"name": c.name, "arguments": c.arguments.to_string()},
+            })).collect::<Vec<_>>(),
+        }),
+        _ => json!({"role": message.role, "content": message.content}),
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let body = CompletionRequest {
+            model: &self.model,
+            messages: request.messages.iter().map(wire_message).collect(),
+            max_tokens: request.max_tokens,
+            stream: false,
+            response_format: request.json.then(|| json!({"type": "json_object"})),
+            tools: request.tools.iter()
+                .map(|t| json!({"type": "function", "function": t}))
+                .collect(),
+        };
+
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let response = self.authorized(self.client.post(&url)).json(&body).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Chat completion request failed with status {}", response.status());
+        }
+
+        let response: CompletionResponse = response.json().await?;
+        let message = response.choices.into_iter()
+            .next()
+            .map(|c| c.message)
+            .ok_or_else(|| anyhow::anyhow!("Chat completion returned no choices"))?;
+
+        Ok(ChatResponse {
+            content: message.content.unwrap_or_default(),
+            tool_calls: message.tool_calls.into_iter()
+                .map(|c| ToolCall {
+                    id: c.id,
+                    name: c.function.name,
+                    arguments: serde_json::from_str(&c.function.arguments).unwrap_or(Value::Null),
+                })
+                .collect(),
+            input_tokens: response.usage.as_ref().and_then(|u| u.prompt_tokens),
+            output_tokens: response.usage.as_ref().and_then(|u| u.completion_tokens),
+        })
+    }
+
+    async fn health_check(&self) -> Result<ModelCheck> {
+        let url = format!("{}/v1/models", self.base_url);
+        let response = self.authorized(self.client.get(&url)).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("OpenAI-compatible server returned status {}", response.status());
+        }
+
+        // Some compatible servers answer with something other than a model list
+        let Ok(models) = response.json::<Value>().await else { return Ok(ModelCheck::Unknown) };
+        let Some(data) = models["data"].as_array() else { return Ok(ModelCheck::Unknown) };
+        let served: Vec<String> = data.iter().filter_map(|m| m["id"].as_str().map(String::from)).collect();
+        Ok(if served.contains(&self.model) { ModelCheck::Available } else { ModelCheck::Missing(served) })
+    }
+}
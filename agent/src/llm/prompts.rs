@@ -0,0 +1,206 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use minijinja::{context, Environment};
+use serde_json::{json, Value};
+use sysinfo::{System, SystemExt};
+use common::Event;
+use crate::storage::Storage;
+use log::{info, warn};
+
+/// Templates shipped with the agent; files in the configured directory override them by name
+const BUILTIN: &[(&str, &str)] = &[
+    ("default.j2", include_str!("../../../config/prompts/default.j2")),
+    ("_context.j2", include_str!("../../../config/prompts/_context.j2")),
+    ("cpu_high.j2", include_str!("../../../config/prompts/cpu_high.j2")),
+    ("memory_high.j2", include_str!("../../../config/prompts/memory_high.j2")),
+    ("disk_high.j2", include_str!("../../../config/prompts/disk_high.j2")),
+    ("oom_kill.j2", include_str!("../../../config/prompts/oom_kill.j2")),
+    ("unit_failed.j2", include_str!("../../../config/prompts/unit_failed.j2")),
+];
+
+/// How far back `history` looks
+const HISTORY_WINDOW_SECS: i64 = 24 * 3600;
+
+/// Per-event-type prompt templates (`<type>.j2`, falling back to `default.j2`).
+///
+/// The directory is re-checked on every render and reloaded when a file is
+/// added, removed or modified, so prompts can be tuned on a running agent.
+pub struct PromptTemplates {
+    dir: Option<PathBuf>,
+    storage: Option<Storage>,
+    history_events: usize,
+    builtin: Environment<'static>,
+    loaded: Mutex<Loaded>,
+}
+
+struct Loaded {
+    env: Environment<'static>,
+    /// (file, mtime) for everything loaded from `dir`
+    stamp: Vec<(PathBuf, SystemTime)>,
+}
+
+impl PromptTemplates {
+    /// Built-in templates only, without history
+    pub fn builtin() -> Self {
+        Self {
+            dir: None,
+            storage: None,
+            history_events: 0,
+            builtin: builtin_env(),
+            loaded: Mutex::new(Loaded { env: builtin_env(), stamp: Vec::new() }),
+        }
+    }
+
+    pub fn new(dir: Option<&str>, storage: Storage, history_events: usize) -> Self {
+        let dir = dir.map(PathBuf::from);
+        if let Some(ref dir) = dir {
+            if !dir.is_dir() {
+                warn!("Prompt template directory {} not found, using built-in templates", dir.display());
+            }
+        }
+        let loaded = match dir {
+            Some(ref dir) => load(dir),
+            None => Loaded { env: builtin_env(), stamp: Vec::new() },
+        };
+        Self {
+            dir,
+            storage: Some(storage),
+            history_events,
+            builtin: builtin_env(),
+            loaded: Mutex::new(loaded),
+        }
+    }
+
+    /// User prompt for `event`; `entity` and `evidence` are already redacted
+    pub async fn render(&self, event: &Event, entity: &Value, evidence: &Value) -> String {
+        let history = self.history(event).await;
+        let ctx = context! {
+            event => json!({
+                "event_id": event.event_id,
+                "type": event.r#type,
+                "severity": event.severity,
+                "ts": event.ts,
+                "status": event.status,
+            }),
+            entity => entity,
+            evidence => evidence,
+            entity_json => serde_json::to_string_pretty(entity).unwrap_or_default(),
+            evidence_json => serde_json::to_string_pretty(evidence).unwrap_or_default(),
+            history => history,
+            host => host_facts(),
+        };
+
+        let name = format!("{}.j2", event.r#type.as_str());
+        let rendered = {
+            let mut loaded = self.loaded.lock().unwrap();
+            if let Some(ref dir) = self.dir {
+                if stamp(dir) != loaded.stamp {
+                    *loaded = load(dir);
+                    info!("Reloaded prompt templates from {}", dir.display());
+                }
+            }
+            let template = loaded.env.get_template(&name).or_else(|_| loaded.env.get_template("default.j2"));
+            template.and_then(|t| t.render(&ctx))
+        };
+
+        match rendered {
+            Ok(prompt) => prompt.trim().to_string(),
+            Err(e) => {
+                // A broken edit shouldn't stop analysis; the shipped templates always render
+                warn!("Prompt template for {} failed ({:#}), using the built-in one", event.r#type, e);
+                self.builtin.get_template(&name)
+                    .or_else(|_| self.builtin.get_template("default.j2"))
+                    .and_then(|t| t.render(&ctx))
+                    .map(|p| p.trim().to_string())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    async fn history(&self, event: &Event) -> Vec<Value> {
+        let Some(ref storage) = self.storage else { return Vec::new() };
+        if self.history_events == 0 {
+            return Vec::new();
+        }
+        let to = chrono::Utc::now().timestamp();
+        let events = match storage.get_events_between(to - HISTORY_WINDOW_SECS, to, 500).await {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("Failed to load event history for prompt: {}", e);
+                return Vec::new();
+            }
+        };
+        events.into_iter()
+            .filter(|e| e.type_ == event.r#type && e.event_id != event.event_id)
+            .take(self.history_events)
+            .map(|e| json!({
+                "event_id": e.event_id,
+                "ts": chrono::DateTime::from_timestamp(e.ts, 0).map(|t| t.to_rfc3339()).unwrap_or_default(),
+                "severity": e.severity,
+                "status": e.status,
+            }))
+            .collect()
+    }
+}
+
+fn builtin_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    for (name, source) in BUILTIN {
+        env.add_template(name, source).expect("built-in prompt templates are valid");
+    }
+    env
+}
+
+/// Built-ins overlaid with every `.j2` file in `dir`; files that don't parse are skipped
+fn load(dir: &Path) -> Loaded {
+    let mut env = builtin_env();
+    let stamp = stamp(dir);
+    for (path, _) in &stamp {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Cannot read prompt template {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if let Err(e) = env.add_template_owned(name.to_string(), source) {
+            warn!("Ignoring prompt template {}: {:#}", path.display(), e);
+            // A failed add drops the name, so put the shipped version back
+            if let Some((name, source)) = BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+                let _ = env.add_template(name, source);
+            }
+        }
+    }
+    Loaded { env, stamp }
+}
+
+fn stamp(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut stamp: Vec<(PathBuf, SystemTime)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "j2"))
+        .filter_map(|p| {
+            let modified = std::fs::metadata(&p).and_then(|m| m.modified()).ok()?;
+            Some((p, modified))
+        })
+        .collect();
+    stamp.sort();
+    stamp
+}
+
+fn host_facts() -> Value {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.refresh_cpu();
+    json!({
+        "hostname": sys.host_name().unwrap_or_default(),
+        "os": sys.long_os_version().unwrap_or_default(),
+        "kernel": sys.kernel_version().unwrap_or_default(),
+        "cpus": sys.cpus().len(),
+        "memory_total_mb": sys.total_memory() / 1024 / 1024,
+        "uptime_hours": sys.uptime() / 3600,
+    })
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use tokio::sync::{Notify, Semaphore};
+use tokio::time::{interval, Duration};
+use common::{Event, LlmQueueConfig};
+use crate::storage::{LlmJob, Storage};
+use super::LlmClient;
+use anyhow::Result;
+use log::{info, warn, error};
+
+/// Handle the analyzer uses to hand events to the background workers.
+///
+/// Jobs live in the `llm_jobs` table, so a slow or unreachable model never
+/// holds up event storage and queued work survives agent restarts.
+#[derive(Clone)]
+pub struct LlmQueue {
+    storage: Storage,
+    wake: Arc<Notify>,
+}
+
+impl LlmQueue {
+    pub async fn enqueue(&self, event: &Event) -> Result<()> {
+        let payload = serde_json::to_string(event)?;
+        self.storage.enqueue_llm_job(&event.event_id, &payload, chrono::Utc::now().timestamp()).await?;
+        self.wake.notify_one();
+        Ok(())
+    }
+}
+
+pub async fn start_llm_queue(storage: Storage, client: LlmClient, config: LlmQueueConfig) -> Result<LlmQueue> {
+    let requeued = storage.requeue_running_llm_jobs(chrono::Utc::now().timestamp()).await?;
+    if requeued > 0 {
+        info!("Requeued {} LLM jobs interrupted by a restart", requeued);
+    }
+
+    info!("Starting LLM job queue ({} concurrent)", config.concurrency.max(1));
+
+    let queue = LlmQueue { storage: storage.clone(), wake: Arc::new(Notify::new()) };
+    let wake = queue.wake.clone();
+
+    tokio::spawn(async move {
+        let slots = Arc::new(Semaphore::new(config.concurrency.max(1)));
+        let mut ticker = interval(Duration::from_secs(config.poll_interval_secs.max(1)));
+        loop {
+            // New jobs and finished jobs wake the loop; the ticker picks up due retries
+            tokio::select! {
+                _ = wake.notified() => {}
+                _ = ticker.tick() => {}
+            }
+
+            // Jobs stay pending while the provider is down instead of burning retries
+            let free = slots.available_permits();
+            if free == 0 || !client.health().is_available() {
+                continue;
+            }
+
+            let jobs = match storage.claim_llm_jobs(free, chrono::Utc::now().timestamp()).await {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    error!("Failed to claim LLM jobs: {}", e);
+                    continue;
+                }
+            };
+
+            for job in jobs {
+                let Ok(permit) = slots.clone().acquire_owned().await else { return };
+                let storage = storage.clone();
+                let client = client.clone();
+                let config = config.clone();
+                let wake = wake.clone();
+                tokio::spawn(async move {
+                    run_job(&storage, &client, &config, job).await;
+                    drop(permit);
+                    wake.notify_one();
+                });
+            }
+        }
+    });
+
+    Ok(queue)
+}
+
+async fn run_job(storage: &Storage, client: &LlmClient, config: &LlmQueueConfig, job: LlmJob) {
+    let result = match serde_json::from_str::<Event>(&job.payload) {
+        Ok(event) => client.analyze_event(&event).await,
+        Err(e) => Err(anyhow::anyhow!("unreadable job payload: {}", e)),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let outcome = match result {
+        Ok(suggestion) => {
+            info!("LLM suggestion added to event {}", job.event_id);
+            storage.complete_llm_job(job.id, &job.event_id, &suggestion, now).await
+        }
+        Err(e) if job.attempts < config.max_attempts as i64 => {
+            let delay = retry_delay(config, job.attempts);
+            warn!("LLM analysis of {} failed (attempt {}), retrying in {}s: {}", job.event_id, job.attempts, delay, e);
+            storage.fail_llm_job(job.id, &e.to_string(), Some(now + delay as i64), now).await
+        }
+        Err(e) => {
+            error!("LLM analysis of {} failed after {} attempts: {}", job.event_id, job.attempts, e);
+            storage.fail_llm_job(job.id, &e.to_string(), None, now).await
+        }
+    };
+
+    if let Err(e) = outcome {
+        error!("Failed to update LLM job {}: {}", job.id, e);
+    }
+}
+
+/// Exponential backoff: base, 2*base, 4*base, ... capped at backoff_max_secs
+fn retry_delay(config: &LlmQueueConfig, attempts: i64) -> u64 {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    config.backoff_base_secs.saturating_mul(1 << exponent).min(config.backoff_max_secs)
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use common::RedactionConfig;
+use anyhow::Result;
+
+/// Placeholders look like `<IP_3>`; nothing a detector matches has this shape
+const PLACEHOLDER: &str = r"<([A-Z]+)_(\d+)>";
+
+/// Masks secrets and identifying data in text sent to the LLM.
+///
+/// Each distinct value gets a stable placeholder (`<EMAIL_1>`) recorded in a
+/// per-conversation [`Redactions`] map, so replies can be mapped back locally.
+pub struct Redactor {
+    detectors: Vec<(&'static str, Regex)>,
+    custom: Vec<Regex>,
+    deny_fields: Vec<String>,
+    allow_fields: Vec<String>,
+    placeholder: Regex,
+}
+
+/// Placeholder mapping for one conversation
+#[derive(Debug, Clone, Default)]
+pub struct Redactions {
+    by_original: HashMap<String, String>,
+    by_placeholder: BTreeMap<String, (String, String)>,
+    counters: HashMap<String, usize>,
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self> {
+        let mut detectors = Vec::new();
+        for name in &config.detectors {
+            let (kind, pattern) = match name.as_str() {
+                // Order matters: more specific token formats run first
+                "jwt" => ("JWT", r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+".to_string()),
+                "aws_key" => ("AWSKEY", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b".to_string()),
+                "api_key" => ("SECRET", concat!(
+                    r"\b(?:sk-[A-Za-z0-9_-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|xox[abpr]-[A-Za-z0-9-]{10,})",
+                    r"|(?i:\b(?:api[_-]?key|token|secret|passw(?:or)?d|auth)\b[=:]\s*)[^\s,;&]+"
+                ).to_string()),
+                "email" => ("EMAIL", r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b".to_string()),
+                "ip" => ("IP", concat!(
+                    r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b",
+                    r"|\b(?:[0-9a-fA-F]{1,4}:){3,7}[0-9a-fA-F]{1,4}\b"
+                ).to_string()),
+                "home_path" => ("USER", r"/home/([^/\s'\x22<>]+)".to_string()),
+                "username" => match word_list(local_usernames()) {
+                    Some(pattern) => ("USER", pattern),
+                    None => continue,
+                },
+                "hostname" => match word_list(local_hostname().into_iter().collect()) {
+                    Some(pattern) => ("HOST", pattern),
+                    None => continue,
+                },
+                other => anyhow::bail!("unknown redaction detector {}", other),
+            };
+            detectors.push((kind, Regex::new(&pattern)?));
+        }
+
+        let custom = config.patterns.iter()
+            .map(|p| Regex::new(p).map_err(|e| anyhow::anyhow!("invalid redaction pattern {}: {}", p, e)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            detectors,
+            custom,
+            deny_fields: config.deny_fields.clone(),
+            allow_fields: config.allow_fields.clone(),
+            placeholder: Regex::new(PLACEHOLDER)?,
+        })
+    }
+
+    pub fn redact_text(&self, text: &str, redactions: &mut Redactions) -> String {
+        let mut text = text.to_string();
+        for (kind, regex) in &self.detectors {
+            text = regex.replace_all(&text, |caps: &Captures| {
+                match caps.get(1) {
+                    // home_path: keep the path shape, mask only the user
+                    Some(user) if *kind == "USER" => format!("/home/{}", redactions.placeholder(kind, user.as_str())),
+                    _ => redact_secret_value(kind, caps, redactions),
+                }
+            }).into_owned();
+        }
+        for regex in &self.custom {
+            text = regex.replace_all(&text, |caps: &Captures| redactions.placeholder("CUSTOM", &caps[0])).into_owned();
+        }
+        text
+    }
+
+    /// Structural pass over event data: denied fields are replaced whole,
+    /// allowed fields are left alone, other strings go through the detectors.
+    pub fn redact_value(&self, value: &Value, redactions: &mut Redactions) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.iter().map(|(key, v)| {
+                let v = if self.allow_fields.contains(key) {
+                    v.clone()
+                } else if self.deny_fields.contains(key) {
+                    Value::String(redactions.placeholder("FIELD", &v.to_string()))
+                } else {
+                    self.redact_value(v, redactions)
+                };
+                (key.clone(), v)
+            }).collect()),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.redact_value(v, redactions)).collect()),
+            Value::String(s) => Value::String(self.redact_text(s, redactions)),
+            other => other.clone(),
+        }
+    }
+
+    /// Put original values back into model output
+    pub fn restore_text(&self, text: &str, redactions: &Redactions) -> String {
+        self.placeholder.replace_all(text, |caps: &Captures| {
+            redactions.original(&caps[0]).unwrap_or(&caps[0]).to_string()
+        }).into_owned()
+    }
+
+    pub fn restore_value(&self, value: &Value, redactions: &Redactions) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.iter()
+                .map(|(k, v)| (k.clone(), self.restore_value(v, redactions)))
+                .collect()),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.restore_value(v, redactions)).collect()),
+            Value::String(s) => Value::String(self.restore_text(s, redactions)),
+            other => other.clone(),
+        }
+    }
+}
+
+impl Redactions {
+    fn placeholder(&mut self, kind: &str, original: &str) -> String {
+        // Text is redacted again on every turn; existing placeholders stay as they are
+        if self.by_placeholder.contains_key(original) {
+            return original.to_string();
+        }
+        if let Some(existing) = self.by_original.get(original) {
+            return existing.clone();
+        }
+        let counter = self.counters.entry(kind.to_string()).or_default();
+        *counter += 1;
+        let placeholder = format!("<{}_{}>", kind, counter);
+        self.by_original.insert(original.to_string(), placeholder.clone());
+        self.by_placeholder.insert(placeholder.clone(), (kind.to_string(), original.to_string()));
+        placeholder
+    }
+
+    fn original(&self, placeholder: &str) -> Option<&str> {
+        self.by_placeholder.get(placeholder).map(|(_, original)| original.as_str())
+    }
+
+    /// (placeholder, kind, original) for every value masked so far
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.by_placeholder.iter().map(|(p, (kind, original))| (p.as_str(), kind.as_str(), original.as_str()))
+    }
+}
+
+/// `password=hunter2` keeps its key so the model still knows what was there
+fn redact_secret_value(kind: &str, caps: &Captures, redactions: &mut Redactions) -> String {
+    let matched = &caps[0];
+    if kind == "SECRET" {
+        if let Some(split) = matched.find(['=', ':']) {
+            let (key, rest) = matched.split_at(split + 1);
+            let value = rest.trim_start();
+            let spacing = &rest[..rest.len() - value.len()];
+            return format!("{}{}{}", key, spacing, redactions.placeholder(kind, value));
+        }
+    }
+    redactions.placeholder(kind, matched)
+}
+
+/// `\b(?:alice|bob)\b`, or None when there is nothing to match
+fn word_list(words: Vec<String>) -> Option<String> {
+    let words: Vec<String> = words.iter()
+        .filter(|w| w.len() >= 3)
+        .map(|w| regex::escape(w))
+        .collect();
+    (!words.is_empty()).then(|| format!(r"\b(?:{})\b", words.join("|")))
+}
+
+/// Login accounts (uid >= 1000) from /etc/passwd
+fn local_usernames() -> Vec<String> {
+    std::fs::read_to_string("/etc/passwd")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let uid: u32 = fields.get(2)?.parse().ok()?;
+            (uid >= 1000 && uid != 65534).then(|| fields[0].to_string())
+        })
+        .collect()
+}
+
+fn local_hostname() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty() && h != "localhost")
+}
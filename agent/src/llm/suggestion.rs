@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+
+/// Shape the model is asked to reply with; embedded in the prompt verbatim
+pub const SUGGESTION_SCHEMA: &str = r#"{
+  "summary": "one or two sentences describing what is happening",
+  "probable_causes": ["most likely cause first"],
+  "immediate_actions": [
+    {"description": "what to do", "command": "shell command or null", "risk": "low|medium|high"}
+  ],
+  "prevention": ["how to avoid this in the future"],
+  "confidence": 0.0
+}"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub summary: String,
+    pub probable_causes: Vec<String>,
+    pub immediate_actions: Vec<Action>,
+    #[serde(default)]
+    pub prevention: Vec<String>,
+    /// 0.0 (guess) to 1.0 (certain)
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    pub description: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    pub risk: Risk,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    Low,
+    Medium,
+    High,
+}
+
+impl Suggestion {
+    /// Parse a model reply, tolerating code fences or text around the JSON object
+    pub fn parse(reply: &str) -> Result<Self> {
+        let start = reply.find('{').ok_or_else(|| anyhow::anyhow!("reply contains no JSON object"))?;
+        let end = reply.rfind('}').ok_or_else(|| anyhow::anyhow!("reply contains no JSON object"))?;
+        if end < start {
+            anyhow::bail!("reply contains no JSON object");
+        }
+
+        let suggestion: Suggestion = serde_json::from_str(&reply[start..=end])?;
+        suggestion.validate()?;
+        Ok(suggestion)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.summary.trim().is_empty() {
+            anyhow::bail!("summary must not be empty");
+        }
+        if self.probable_causes.is_empty() {
+            anyhow::bail!("probable_causes must list at least one cause");
+        }
+        if self.immediate_actions.is_empty() {
+            anyhow::bail!("immediate_actions must list at least one action");
+        }
+        if let Some(action) = self.immediate_actions.iter().find(|a| a.description.trim().is_empty()) {
+            anyhow::bail!("immediate action has an empty description (command: {:?})", action.command);
+        }
+        if !(0.0..=1.0).contains(&self.confidence) {
+            anyhow::bail!("confidence must be between 0 and 1, got {}", self.confidence);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use serde_json::{json, Value};
+use sysinfo::{System, SystemExt, ProcessExt, PidExt};
+use common::Severity;
+use crate::storage::Storage;
+use super::{ToolCall, ToolSpec};
+use anyhow::Result;
+
+/// Tool output is cut to this many characters before it reaches the model
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+/// Samples returned by `get_metrics`, evenly spread over the range
+const MAX_METRIC_POINTS: usize = 30;
+
+/// Metrics recorded this recently are offered to the model by name
+const METRIC_NAMES_WINDOW_SECS: i64 = 24 * 3600;
+
+/// `/proc/<pid>/status` fields worth showing the model
+const PROC_STATUS_FIELDS: &[&str] = &[
+    "Name", "State", "PPid", "Uid", "Threads", "VmPeak", "VmSize", "VmRSS", "VmSwap",
+    "voluntary_ctxt_switches", "nonvoluntary_ctxt_switches",
+];
+
+/// Read-only diagnostics the model may call while analyzing an event.
+///
+/// Every tool only reads the agent's own stores or `/proc`; arguments are
+/// validated and outputs capped, so a misbehaving model can't change the host
+/// or flood the prompt.
+pub struct Toolbox {
+    storage: Storage,
+    pub max_iterations: usize,
+}
+
+impl Toolbox {
+    pub fn new(storage: Storage, max_iterations: usize) -> Self {
+        Self { storage, max_iterations: max_iterations.max(1) }
+    }
+
+    /// Tool definitions; `get_metrics` names the metrics actually recorded
+    pub async fn specs(&self) -> Vec<ToolSpec> {
+        let now = chrono::Utc::now().timestamp();
+        let metric_names: Vec<String> = self.storage.get_metric_summaries(now - METRIC_NAMES_WINDOW_SECS, now).await
+            .map(|summaries| summaries.into_iter().map(|m| m.name).collect())
+            .unwrap_or_default();
+        let mut metric_name = json!({"type": "string", "description": "Metric name, e.g. cpu.usage_percent or memory.used_percent"});
+        if !metric_names.is_empty() {
+            metric_name["enum"] = json!(metric_names);
+        }
+
+        vec![
+            ToolSpec {
+                name: "get_metrics",
+                description: "Summary and recent samples of a recorded metric (e.g. cpu.usage_percent, memory.used_percent)",
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "name": metric_name,
+                        "minutes": {"type": "integer", "description": "How far back to look (default 60)"}
+                    },
+                    "required": ["name"]
+                }),
+            },
+            ToolSpec {
+                name: "list_events",
+                description: "Recent events recorded by the agent, newest first",
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "min_severity": {"type": "string", "enum": ["DEBUG", "INFO", "WARNING", "ERROR", "CRITICAL"]},
+                        "type": {"type": "string", "description": "Event type, e.g. cpu_high"},
+                        "minutes": {"type": "integer", "description": "How far back to look (default 60)"},
+                        "limit": {"type": "integer", "description": "Maximum events (default 20, at most 50)"}
+                    }
+                }),
+            },
+            ToolSpec {
+                name: "read_proc",
+                description: "Status and command line of a running process from /proc",
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "pid": {"type": "integer"}
+                    },
+                    "required": ["pid"]
+                }),
+            },
+            ToolSpec {
+                name: "top_processes",
+                description: "Processes using the most CPU or memory right now",
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "sort_by": {"type": "string", "enum": ["cpu", "memory"]},
+                        "limit": {"type": "integer", "description": "Maximum processes (default 10, at most 25)"}
+                    }
+                }),
+            },
+        ]
+    }
+
+    /// Run a tool call; failures are reported to the model rather than aborting the analysis
+    pub async fn run(&self, call: &ToolCall) -> String {
+        let result = match call.name.as_str() {
+            "get_metrics" => self.get_metrics(&call.arguments).await,
+            "list_events" => self.list_events(&call.arguments).await,
+            "read_proc" => read_proc(&call.arguments),
+            "top_processes" => top_processes(&call.arguments).await,
+            other => Err(anyhow::anyhow!("unknown tool {}", other)),
+        };
+        let output = match result {
+            Ok(value) => value.to_string(),
+            Err(e) => json!({"error": e.to_string()}).to_string(),
+        };
+        output.chars().take(MAX_OUTPUT_CHARS).collect()
+    }
+
+    async fn get_metrics(&self, args: &Value) -> Result<Value> {
+        let name = args["name"].as_str().ok_or_else(|| anyhow::anyhow!("name is required"))?;
+        let to = chrono::Utc::now().timestamp();
+        let from = to - minutes(args) * 60;
+
+        let samples = self.storage.get_metric_samples(name, from, to).await?;
+        if samples.is_empty() {
+            let available: Vec<String> = self.storage.get_metric_summaries(from, to).await?
+                .into_iter()
+                .map(|m| m.name)
+                .collect();
+            anyhow::bail!("no samples for {} in range; recorded metrics: {}", name, available.join(", "));
+        }
+
+        let values: Vec<f64> = samples.iter().map(|(_, v)| *v).collect();
+        let step = samples.len().div_ceil(MAX_METRIC_POINTS);
+        Ok(json!({
+            "name": name,
+            "from": from,
+            "to": to,
+            "min": values.iter().cloned().fold(f64::INFINITY, f64::min),
+            "max": values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            "avg": values.iter().sum::<f64>() / values.len() as f64,
+            "samples": samples.iter().step_by(step).map(|(ts, v)| json!([ts, v])).collect::<Vec<_>>(),
+        }))
+    }
+
+    async fn list_events(&self, args: &Value) -> Result<Value> {
+        let min_severity = match args["min_severity"].as_str() {
+            Some(s) => s.parse::<Severity>()?,
+            None => Severity::Debug,
+        };
+        let kind = args["type"].as_str();
+        let limit = args["limit"].as_u64().unwrap_or(20).min(50) as usize;
+        let to = chrono::Utc::now().timestamp();
+        let from = to - minutes(args) * 60;
+
+        let events: Vec<Value> = self.storage.get_events_between(from, to, 500).await?
+            .into_iter()
+            .filter(|e| e.severity >= min_severity && kind.is_none_or(|k| e.type_.as_str() == k))
+            .take(limit)
+            .map(|e| {
+                let event = e.to_event();
+                json!({
+                    "event_id": event.event_id,
+                    "ts": event.ts,
+                    "severity": event.severity,
+                    "type": event.r#type,
+                    "status": event.status,
+                    "entity": event.entity,
+                })
+            })
+            .collect();
+        Ok(json!({"events": events}))
+    }
+}
+
+fn minutes(args: &Value) -> i64 {
+    args["minutes"].as_i64().unwrap_or(60).clamp(1, 7 * 24 * 60)
+}
+
+fn read_proc(args: &Value) -> Result<Value> {
+    // Only a numeric pid ever reaches the path, so no other file can be read
+    let pid = args["pid"].as_u64().ok_or_else(|| anyhow::anyhow!("pid must be a positive integer"))?;
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .map_err(|e| anyhow::anyhow!("cannot read process {}: {}", pid, e))?;
+
+    let fields: serde_json::Map<String, Value> = status.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| PROC_STATUS_FIELDS.contains(key))
+        .map(|(key, value)| (key.to_string(), json!(value.split_whitespace().collect::<Vec<_>>().join(" "))))
+        .collect();
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|raw| String::from_utf8_lossy(&raw).replace('\0', " ").trim().to_string())
+        .unwrap_or_default();
+
+    Ok(json!({"pid": pid, "cmdline": cmdline, "status": fields}))
+}
+
+async fn top_processes(args: &Value) -> Result<Value> {
+    let by_memory = args["sort_by"].as_str() == Some("memory");
+    let limit = args["limit"].as_u64().unwrap_or(10).min(25) as usize;
+
+    let mut sys = System::new_all();
+    // CPU usage is measured between two refreshes
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    sys.refresh_processes();
+
+    let mut procs: Vec<_> = sys.processes().values().collect();
+    if by_memory {
+        procs.sort_by_key(|p| std::cmp::Reverse(p.memory()));
+    } else {
+        procs.sort_by(|a, b| b.cpu_usage().total_cmp(&a.cpu_usage()));
+    }
+
+    let processes: Vec<Value> = procs.into_iter().take(limit).map(|p| json!({
+        "pid": p.pid().as_u32(),
+        "name": p.name(),
+        "cpu": p.cpu_usage(),
+        "memory_mb": p.memory() / 1024 / 1024,
+    })).collect();
+    Ok(json!({"sort_by": if by_memory { "memory" } else { "cpu" }, "processes": processes}))
+}
@@ -1,19 +1,42 @@
 use anyhow::Result;
 use tokio::signal;
-use tokio::sync::mpsc;
-use log::info;
+use tokio::sync::{broadcast, mpsc};
+use log::{info, warn};
 
 mod collectors;
 mod analyzer;
 mod storage;
 mod ipc;
 mod llm;
+mod baseline;
+mod correlator;
+mod sweeper;
+mod silences;
+mod retention;
+mod governor;
+mod selfmon;
+mod http;
+mod otlp;
+mod notify;
+mod grants;
+mod discovery;
+mod ids;
 
 use collectors::start_collectors;
 use analyzer::start_analyzer;
+use correlator::start_correlator;
+use sweeper::start_sweeper;
+use retention::start_retention;
+use governor::{start_memory_governor, MemoryGovernor, EVENT_BYTES, SAMPLE_BYTES};
+use selfmon::start_self_monitor;
+use http::start_http_server;
+use otlp::start_otlp_exporter;
+use notify::start_notifier;
 use ipc::start_ipc_server;
+use grants::Grants;
+use discovery::{start_discovery, ServiceMap};
 use storage::Storage;
-use llm::LlmClient;
+use llm::{build_provider, start_health_checker, start_llm_queue, LlmAuditor, LlmClient, PromptTemplates, Redactor, Toolbox};
 use common::Config;
 
 #[tokio::main]
@@ -31,38 +54,100 @@ async fn main() -> Result<()> {
     let storage = Storage::new(&config.storage.db_path).await?;
     info!("Storage initialized at {}", config.storage.db_path);
     
-    // Initialize LLM client (optional)
-    let llm_client = LlmClient::new(
-        config.llm.ollama_url.clone(),
-        config.llm.model.clone(),
-    );
-    
-    let llm_available = if let Ok(connected) = llm_client.test_connection().await {
-        if connected {
-            info!("LLM client ready ({})", config.llm.ollama_url);
+    // Initialize LLM client (optional). An unreachable model is not fatal:
+    // the health checker pauses analysis until it is back.
+    let llm_client = match build_provider(&config.llm) {
+        Ok(provider) => {
+            let prompts = PromptTemplates::new(config.llm.prompts.dir.as_deref(), storage.clone(), config.llm.prompts.history_events);
+            let mut llm_client = LlmClient::new(provider).with_prompts(prompts);
+            if config.llm.tools.enabled {
+                llm_client = llm_client.with_tools(Toolbox::new(storage.clone(), config.llm.tools.max_iterations));
+            }
+            if config.llm.redaction.enabled {
+                llm_client = llm_client.with_redactor(Redactor::new(&config.llm.redaction)?);
+            }
+            if config.llm.audit.enabled {
+                let auditor = LlmAuditor::new(storage.clone(), config.llm.audit.full_text);
+                auditor.start_retention(config.llm.audit.retention_days);
+                llm_client = llm_client.with_auditor(auditor);
+            }
+            info!("LLM client configured ({})", llm_client.describe());
             Some(llm_client)
-        } else {
-            info!("LLM not available, continuing without AI suggestions");
+        }
+        Err(e) => {
+            warn!("LLM provider misconfigured ({}), continuing without AI suggestions", e);
             None
         }
-    } else {
-        info!("LLM connection test failed, continuing without AI suggestions");
-        None
     };
+    let llm_queue = match llm_client {
+        Some(ref client) => {
+            let queue = start_llm_queue(storage.clone(), client.clone(), config.llm.queue.clone()).await?;
+            start_health_checker(client.clone(), queue.clone(), storage.clone(), config.llm.health.clone());
+            Some(queue)
+        }
+        None => None,
+    };
+    
+    // Create event channels, bounded to fit the memory budget
+    let governor = MemoryGovernor::new(config.agent.memory_budget);
+    let (tx, rx) = mpsc::channel(governor.channel_capacity("event", config.agent.event_ring_capacity, EVENT_BYTES));
+    let (metrics_tx, metrics_rx) = mpsc::channel(governor.channel_capacity("metric", config.agent.event_ring_capacity, SAMPLE_BYTES));
+    let (stored_tx, _) = broadcast::channel(1024);
     
-    // Create event channel
-    let (tx, rx) = mpsc::channel(config.agent.event_ring_capacity);
+    // Watch our own memory use and shed load before the budget is exceeded
+    start_memory_governor(governor.clone(), config.agent.memory.clone(), storage.clone(), tx.clone());
+    start_self_monitor(config.self_monitor.clone(), governor.clone(), tx.clone());
     
     // Start collectors
-    start_collectors(tx, config.agent.cpu_interval).await?;
+    start_collectors(tx, metrics_tx, config.agent.cpu_interval, governor.clone()).await?;
     info!("Collectors started");
     
+    // Start correlation stage before the analyzer so no stored event is missed
+    start_correlator(stored_tx.subscribe(), storage.clone(), config.correlation.clone(), governor.clone()).await?;
+    
+    // Start OTLP export (optional), also subscribed before the analyzer runs
+    start_otlp_exporter(config.otlp.clone(), stored_tx.subscribe())?;
+    
+    // Start notifications (optional)
+    start_notifier(config.notify.clone(), storage.clone(), stored_tx.subscribe())?;
+    
+    // Start service discovery; the analyzer links events to what it finds
+    let services = ServiceMap::default();
+    start_discovery(config.discovery.clone(), storage.clone(), services.clone())?;
+    
     // Start analyzer
-    start_analyzer(rx, storage.clone(), llm_available).await?;
+    start_analyzer(
+        rx,
+        metrics_rx,
+        storage.clone(),
+        llm_queue.clone(),
+        config.anomaly.clone(),
+        config.maintenance.clone(),
+        stored_tx.clone(),
+        governor,
+        services,
+    ).await?;
     info!("Analyzer started");
     
+    // Start severity escalation/decay sweeper
+    start_sweeper(storage.clone(), config.escalation.clone(), llm_queue, stored_tx).await?;
+    
+    // Start disk quota enforcement
+    start_retention(storage.clone(), config.agent.disk_quota, config.retention.clone()).await?;
+    
+    // Start Prometheus/health endpoint (optional)
+    start_http_server(config.http.clone(), storage.clone(), llm_client.clone(), config.agent.cpu_interval).await?;
+    
     // Start IPC server
-    start_ipc_server(storage.clone(), config.ipc.socket_path.clone()).await?;
+    let grants = Grants::new(config.grants.clone(), storage.clone()).await?;
+    start_ipc_server(
+        storage.clone(),
+        llm_client,
+        config.llm.ask.clone(),
+        config.agent.disk_quota,
+        grants,
+        config.ipc.clone(),
+    ).await?;
     info!("IPC server started on {}", config.ipc.socket_path);
     
     info!("SIA agent is running");
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde_json::json;
+use tokio::sync::Notify;
+use common::{NotifyDigest, Severity};
+use crate::storage::{NewNotification, Storage};
+use super::Notification;
+use anyhow::{anyhow, Result};
+use log::{info, error};
+
+/// Resolutions looked at per digest; far more than a day's worth
+const MAX_RESOLUTIONS: i64 = 10_000;
+
+/// Event counts per type for one section of a digest
+type Counts = BTreeMap<String, i64>;
+
+/// Queue each digest on its schedule. The covered period runs from just after
+/// the previous digest (or one schedule interval back) up to now.
+pub fn start_digests(digests: Vec<NotifyDigest>, storage: Storage, wake: Arc<Notify>, host: String) -> Result<()> {
+    for digest in digests {
+        let schedule = cron::Schedule::from_str(&digest.schedule)
+            .map_err(|e| anyhow!("notify digest '{}': invalid schedule '{}': {}", digest.name, digest.schedule, e))?;
+        info!("Digest '{}' scheduled at '{}'", digest.name, digest.schedule);
+
+        let storage = storage.clone();
+        let wake = wake.clone();
+        let host = host.clone();
+        tokio::spawn(async move {
+            loop {
+                let Some(next) = schedule.upcoming(Local).next() else { return };
+                let wait = (next - Local::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match queue_digest(&storage, &digest, &schedule, &host).await {
+                    Ok(true) => wake.notify_one(),
+                    Ok(false) => info!("Digest '{}' skipped, nothing to report", digest.name),
+                    Err(e) => error!("Failed to build digest '{}': {}", digest.name, e),
+                }
+                // Don't fire twice within the same second
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+    }
+    Ok(())
+}
+
+pub(super) async fn queue_digest(storage: &Storage, digest: &NotifyDigest, schedule: &cron::Schedule, host: &str) -> Result<bool> {
+    let now = Utc::now();
+    let from = match storage.last_digest_at(&digest.name).await? {
+        Some(last) => last,
+        None => now.timestamp() - schedule_period(schedule),
+    };
+    let to = now.timestamp();
+
+    let keep = |severity: &str| Severity::from_str(severity).is_ok_and(|s| s >= digest.min_severity);
+    let group = |rows: Vec<(String, String, i64)>| -> Counts {
+        let mut counts = Counts::new();
+        for (severity, kind, count) in rows {
+            if keep(&severity) {
+                *counts.entry(kind).or_default() += count;
+            }
+        }
+        counts
+    };
+    let new = group(storage.get_new_event_counts(from, to).await?);
+    let open = group(storage.get_open_event_counts_before(from).await?);
+
+    let mut resolved = Counts::new();
+    for (ts, audit) in storage.get_audits("event.resolved", from, MAX_RESOLUTIONS).await? {
+        if ts <= from || ts > to {
+            continue;
+        }
+        for id in audit["event_ids"].as_array().into_iter().flatten().filter_map(|id| id.as_str()) {
+            // Events since removed by retention no longer count
+            let Some(event) = storage.get_event_by_id(id).await? else { continue };
+            if event.severity >= digest.min_severity {
+                *resolved.entry(event.type_.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    let total = |counts: &Counts| counts.values().sum::<i64>();
+    if digest.skip_empty && total(&new) + total(&open) + total(&resolved) == 0 {
+        return Ok(false);
+    }
+
+    let notification = build(digest, host, from, to, &new, &open, &resolved);
+    let payload = serde_json::to_string(&notification)?;
+    for sink in &digest.sinks {
+        storage.enqueue_notification(&NewNotification {
+            event_id: None,
+            event_type: None,
+            kind: "digest".to_string(),
+            route: digest.name.clone(),
+            sink: sink.clone(),
+            payload: payload.clone(),
+            throttled: false,
+        }, to).await?;
+    }
+    Ok(true)
+}
+
+fn build(digest: &NotifyDigest, host: &str, from: i64, to: i64, new: &Counts, open: &Counts, resolved: &Counts) -> Notification {
+    let total = |counts: &Counts| counts.values().sum::<i64>();
+    let local = |ts: i64| Local.timestamp_opt(ts, 0).single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+
+    let mut text = format!("{} to {}\n", local(from), local(to));
+    for (heading, counts) in [("New", new), ("Still open", open), ("Resolved", resolved)] {
+        text.push_str(&format!("\n{} ({}):\n", heading, total(counts)));
+        if counts.is_empty() {
+            text.push_str("  none\n");
+        }
+        for (kind, count) in counts {
+            text.push_str(&format!("  {:<24} {}\n", kind, count));
+        }
+    }
+
+    Notification {
+        kind: "digest".to_string(),
+        title: format!(
+            "{} digest for {}: {} new, {} still open, {} resolved",
+            digest.name, host, total(new), total(open), total(resolved)
+        ),
+        text,
+        // Loud enough to match what it summarizes
+        severity: if total(new) + total(open) > 0 { digest.min_severity } else { Severity::Info },
+        host: host.to_string(),
+        ts: Utc::now().to_rfc3339(),
+        service_id: None,
+        event: None,
+        digest: Some(json!({
+            "name": digest.name,
+            "from": DateTime::<Utc>::from_timestamp(from, 0).map(|t| t.to_rfc3339()),
+            "to": DateTime::<Utc>::from_timestamp(to, 0).map(|t| t.to_rfc3339()),
+            "new": new,
+            "open": open,
+            "resolved": resolved,
+        })),
+    }
+}
+
+/// Seconds between two consecutive firings, used for the first digest's period
+fn schedule_period(schedule: &cron::Schedule) -> i64 {
+    let mut upcoming = schedule.upcoming(Utc);
+    match (upcoming.next(), upcoming.next()) {
+        (Some(a), Some(b)) => (b - a).num_seconds(),
+        _ => 86400,
+    }
+}
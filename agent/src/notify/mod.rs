@@ -0,0 +1,529 @@
+mod digest;
+mod sinks;
+mod smtp;
+
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sysinfo::{System, SystemExt};
+use tokio::sync::{broadcast, Notify};
+use tokio::time::{interval, Duration};
+use common::{Event, NotifyConfig, NotifyRoute, Severity};
+use crate::storage::{NewNotification, NotificationJob, Storage};
+use anyhow::{bail, Result};
+use log::{info, warn, error};
+
+use sinks::Sinks;
+
+/// Notifications claimed per pass of the delivery loop
+const CLAIM_BATCH: usize = 16;
+
+/// What a sink delivers. Stored as the `notifications.payload`, so a retry
+/// after a restart sends exactly what was first rendered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    /// alert, resolved or digest
+    pub kind: String,
+    pub title: String,
+    /// Plain-text body; chat sinks and email use it as is
+    pub text: String,
+    pub severity: Severity,
+    pub host: String,
+    pub ts: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<Event>,
+    /// Counts behind a digest, for webhook templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<Value>,
+}
+
+impl Notification {
+    pub fn alert(event: &Event, service_id: &str, host: &str) -> Self {
+        let mut text = format!(
+            "Event:    {}\nTime:     {}\nService:  {}\n",
+            event.event_id, event.ts, service_id
+        );
+        if let Some(entity) = scalars(&event.entity) {
+            text.push_str(&format!("Entity:   {}\n", entity));
+        }
+        if let Some(evidence) = scalars(&event.evidence) {
+            text.push_str(&format!("Evidence: {}\n", evidence));
+        }
+        text.push_str(&format!("\nsia-cli show {}", event.event_id));
+
+        Self {
+            kind: "alert".to_string(),
+            title: format!("{} {} on {}", event.severity, event.r#type, host),
+            text,
+            severity: event.severity,
+            host: host.to_string(),
+            ts: chrono::Utc::now().to_rfc3339(),
+            service_id: Some(service_id.to_string()),
+            event: Some(event.clone()),
+            digest: None,
+        }
+    }
+
+    /// Follow-up to a delivered alert whose event has been resolved
+    pub fn resolved(alert: &Notification) -> Self {
+        let now = chrono::Utc::now();
+        let mut text = String::new();
+        let mut title = format!("RESOLVED {}", alert.title);
+        if let Some(ref event) = alert.event {
+            title = format!("RESOLVED {} on {}", event.r#type, alert.host);
+            text.push_str(&format!("Event:    {}\nOpened:   {}\n", event.event_id, event.ts));
+            if let Ok(opened) = chrono::DateTime::parse_from_rfc3339(&event.ts) {
+                text.push_str(&format!("Open for: {}\n", format_duration((now - opened.with_timezone(&chrono::Utc)).num_seconds())));
+            }
+            text.push_str(&format!("\nsia-cli show {}", event.event_id));
+        }
+
+        Self {
+            kind: "resolved".to_string(),
+            title,
+            text,
+            severity: alert.severity,
+            host: alert.host.clone(),
+            ts: now.to_rfc3339(),
+            service_id: alert.service_id.clone(),
+            event: alert.event.clone(),
+            digest: None,
+        }
+    }
+}
+
+/// 3725 -> "1h 2m"
+fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+/// `key=value` for the top-level scalar fields of an entity/evidence object
+fn scalars(value: &Value) -> Option<String> {
+    let fields: Vec<String> = value.as_object()?
+        .iter()
+        .filter_map(|(key, v)| match v {
+            Value::String(s) => Some(format!("{}={}", key, s)),
+            Value::Number(n) => Some(format!("{}={}", key, n)),
+            Value::Bool(b) => Some(format!("{}={}", key, b)),
+            _ => None,
+        })
+        .collect();
+    (!fields.is_empty()).then(|| fields.join(", "))
+}
+
+/// Route stored events to sinks and deliver them in the background.
+///
+/// Deliveries are rows in the `notifications` table: they survive restarts,
+/// failed ones are retried with backoff, and each event keeps a record of
+/// what was sent where.
+pub fn start_notifier(config: NotifyConfig, storage: Storage, mut rx: broadcast::Receiver<Event>) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let sinks = Sinks::new(config.sinks.clone(), config.timeout_secs)?;
+    for route in &config.routes {
+        if let Some(missing) = route.sinks.iter().find(|s| !sinks.contains(s)) {
+            bail!("notify route '{}' uses unknown sink '{}'", route.name, missing);
+        }
+    }
+    for digest in &config.digests {
+        if let Some(missing) = digest.sinks.iter().find(|s| !sinks.contains(s)) {
+            bail!("notify digest '{}' uses unknown sink '{}'", digest.name, missing);
+        }
+    }
+    info!(
+        "Starting notifier ({} sinks, {} routes, {} digests)",
+        config.sinks.len(), config.routes.len(), config.digests.len()
+    );
+
+    let wake = Arc::new(Notify::new());
+    let host = host_name();
+    digest::start_digests(config.digests.clone(), storage.clone(), wake.clone(), host.clone())?;
+
+    let router_storage = storage.clone();
+    let router_wake = wake.clone();
+    let routes = config.routes.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Notifier lagged, {} events were not routed", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            match route_event(&router_storage, &routes, &event, &host).await {
+                Ok(0) => {}
+                Ok(_) => router_wake.notify_one(),
+                Err(e) => error!("Failed to route notifications for {}: {}", event.event_id, e),
+            }
+        }
+    });
+
+    let resolved_routes: Vec<String> = config.routes.iter()
+        .filter(|r| r.send_resolved)
+        .map(|r| r.name.clone())
+        .collect();
+    tokio::spawn(async move {
+        match storage.requeue_sending_notifications(chrono::Utc::now().timestamp()).await {
+            Ok(0) => {}
+            Ok(n) => info!("Requeued {} notifications interrupted by a restart", n),
+            Err(e) => error!("Failed to requeue notifications: {}", e),
+        }
+
+        let mut ticker = interval(Duration::from_secs(config.poll_interval_secs.max(1)));
+        loop {
+            // New notifications wake the loop; the ticker picks up due retries
+            tokio::select! {
+                _ = wake.notified() => {}
+                _ = ticker.tick() => {}
+            }
+
+            if let Err(e) = queue_resolved(&storage, &resolved_routes).await {
+                error!("Failed to queue resolved notifications: {}", e);
+            }
+
+            loop {
+                let jobs = match storage.claim_notifications(CLAIM_BATCH, chrono::Utc::now().timestamp()).await {
+                    Ok(jobs) => jobs,
+                    Err(e) => {
+                        error!("Failed to claim notifications: {}", e);
+                        break;
+                    }
+                };
+                let claimed = jobs.len();
+                for job in jobs {
+                    deliver(&storage, &sinks, &config, job).await;
+                }
+                if claimed < CLAIM_BATCH {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Queue a notification per sink of every route the event matches; returns how many will be sent
+async fn route_event(storage: &Storage, routes: &[NotifyRoute], event: &Event, host: &str) -> Result<usize> {
+    if event.silenced_by.is_some() || event.status != "open" {
+        return Ok(0);
+    }
+
+    let mut service_id: Option<String> = None;
+    let mut queued = 0;
+    for route in routes {
+        if event.severity < route.min_severity {
+            continue;
+        }
+        if !route.types.is_empty() && !route.types.contains(&event.r#type) {
+            continue;
+        }
+        if service_id.is_none() {
+            service_id = Some(storage.get_event_by_id(&event.event_id).await?
+                .map(|e| e.service_id)
+                .unwrap_or_default());
+        }
+        let service = service_id.as_deref().unwrap_or_default();
+        if !route.services.is_empty() && !route.services.iter().any(|s| s == service) {
+            continue;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let throttled = route.throttle_secs > 0 && storage.last_route_notification(&route.name, event.r#type.as_str()).await?
+            .is_some_and(|last| now - last < route.throttle_secs as i64);
+        if throttled {
+            info!("Notification for {} throttled on route {}", event.event_id, route.name);
+        }
+
+        let payload = serde_json::to_string(&Notification::alert(event, service, host))?;
+        for sink in &route.sinks {
+            storage.enqueue_notification(&NewNotification {
+                event_id: Some(event.event_id.clone()),
+                event_type: Some(event.r#type.to_string()),
+                kind: "alert".to_string(),
+                route: route.name.clone(),
+                sink: sink.clone(),
+                payload: payload.clone(),
+                throttled,
+            }, now).await?;
+        }
+        if !throttled {
+            queued += route.sinks.len();
+        }
+    }
+    Ok(queued)
+}
+
+/// Queue a follow-up for every delivered alert whose event has since been resolved
+async fn queue_resolved(storage: &Storage, routes: &[String]) -> Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    for (event_id, route, sink, payload) in storage.get_pending_resolved_followups(routes).await? {
+        let alert: Notification = serde_json::from_str(&payload)?;
+        let resolved = Notification::resolved(&alert);
+        storage.enqueue_notification(&NewNotification {
+            event_id: Some(event_id),
+            event_type: alert.event.as_ref().map(|e| e.r#type.to_string()),
+            kind: resolved.kind.clone(),
+            route,
+            sink,
+            payload: serde_json::to_string(&resolved)?,
+            throttled: false,
+        }, now).await?;
+    }
+    Ok(())
+}
+
+async fn deliver(storage: &Storage, sinks: &Sinks, config: &NotifyConfig, job: NotificationJob) {
+    let result = match serde_json::from_str::<Notification>(&job.payload) {
+        Ok(notification) => sinks.send(&job.sink, &notification).await,
+        Err(e) => Err(anyhow::anyhow!("unreadable notification payload: {}", e)),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let outcome = match result {
+        Ok(()) => {
+            info!("Notification {} sent via {}", job.id, job.sink);
+            storage.complete_notification(job.id, now).await
+        }
+        Err(e) if job.attempts < config.max_attempts as i64 => {
+            let delay = retry_delay(config, job.attempts);
+            warn!("Notification {} via {} failed (attempt {}), retrying in {}s: {:#}", job.id, job.sink, job.attempts, delay, e);
+            storage.fail_notification(job.id, &format!("{:#}", e), Some(now + delay as i64), now).await
+        }
+        Err(e) => {
+            error!("Notification {} via {} failed after {} attempts: {:#}", job.id, job.sink, job.attempts, e);
+            storage.fail_notification(job.id, &format!("{:#}", e), None, now).await
+        }
+    };
+
+    if let Err(e) = outcome {
+        error!("Failed to update notification {}: {}", job.id, e);
+    }
+}
+
+/// Exponential backoff: base, 2*base, 4*base, ... capped at backoff_max_secs
+fn retry_delay(config: &NotifyConfig, attempts: i64) -> u64 {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    config.backoff_base_secs.saturating_mul(1 << exponent).min(config.backoff_max_secs)
+}
+
+fn host_name() -> String {
+    let mut sys = System::new();
+    sys.refresh_system();
+    sys.host_name().unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{EmailSinkConfig, EventKind, SmtpTls};
+    use serde_json::json;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    async fn test_storage(name: &str) -> Storage {
+        let path = std::env::temp_dir().join(format!("sia-notify-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        std::fs::File::create(&path).unwrap();
+        Storage::new(path.to_str().unwrap()).await.unwrap()
+    }
+
+    fn test_event(id: &str, severity: Severity, kind: EventKind) -> Event {
+        Event {
+            event_id: id.to_string(),
+            ts: chrono::Utc::now().to_rfc3339(),
+            severity,
+            r#type: kind,
+            entity: json!({ "type": "host", "mount": "/" }),
+            evidence: json!({ "used_percent": 97 }),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_by_severity_and_type_with_throttling() {
+        let storage = test_storage("route").await;
+        let routes = vec![
+            NotifyRoute {
+                name: "critical".to_string(),
+                sinks: vec!["chat".to_string(), "mail".to_string()],
+                min_severity: Severity::Critical,
+                types: vec![],
+                services: vec![],
+                throttle_secs: 0,
+                send_resolved: true,
+            },
+            NotifyRoute {
+                name: "disk".to_string(),
+                sinks: vec!["chat".to_string()],
+                min_severity: Severity::Warning,
+                types: vec![EventKind::DiskHigh],
+                services: vec![],
+                throttle_secs: 300,
+                send_resolved: true,
+            },
+        ];
+
+        let warning = test_event("disk_1", Severity::Warning, EventKind::DiskHigh);
+        assert_eq!(route_event(&storage, &routes, &warning, "box").await.unwrap(), 1);
+
+        // Same type within the throttle window: recorded but not sent
+        let again = test_event("disk_2", Severity::Warning, EventKind::DiskHigh);
+        assert_eq!(route_event(&storage, &routes, &again, "box").await.unwrap(), 0);
+        let throttled = storage.get_notifications_for_event("disk_2").await.unwrap();
+        assert_eq!(throttled.len(), 1);
+        assert_eq!(throttled[0].status, "throttled");
+
+        let critical = test_event("cpu_1", Severity::Critical, EventKind::CpuHigh);
+        assert_eq!(route_event(&storage, &routes, &critical, "box").await.unwrap(), 2);
+
+        let mut silenced = test_event("cpu_2", Severity::Critical, EventKind::CpuHigh);
+        silenced.silenced_by = Some("s1".to_string());
+        assert_eq!(route_event(&storage, &routes, &silenced, "box").await.unwrap(), 0);
+
+        let jobs = storage.claim_notifications(10, chrono::Utc::now().timestamp()).await.unwrap();
+        assert_eq!(jobs.len(), 3);
+        let notification: Notification = serde_json::from_str(&jobs[0].payload).unwrap();
+        assert_eq!(notification.title, "WARNING disk_high on box");
+        assert!(notification.text.contains("mount=/"));
+    }
+
+    #[tokio::test]
+    async fn follows_up_resolved_alerts_and_digests_counts() {
+        let storage = test_storage("resolved").await;
+        let route = NotifyRoute {
+            name: "all".to_string(),
+            sinks: vec!["chat".to_string()],
+            min_severity: Severity::Warning,
+            types: vec![],
+            services: vec![],
+            throttle_secs: 0,
+            send_resolved: true,
+        };
+
+        let event = test_event("disk_1", Severity::Warning, EventKind::DiskHigh);
+        storage.insert_event(&crate::storage::StoredEvent {
+            event_id: event.event_id.clone(),
+            ts: chrono::Utc::now().timestamp(),
+            severity: event.severity,
+            type_: event.r#type.clone(),
+            service_id: "system".to_string(),
+            fingerprint: Some("disk_high:/".to_string()),
+            snapshot: b"{}".to_vec(),
+            status: "open".to_string(),
+            silenced_by: None,
+            suggestion: None,
+        }).await.unwrap();
+        route_event(&storage, std::slice::from_ref(&route), &event, "box").await.unwrap();
+
+        // Nothing to follow up until the alert went out and the event closed
+        let routes = vec![route.name.clone()];
+        let now = chrono::Utc::now().timestamp();
+        let alert = storage.claim_notifications(10, now).await.unwrap().remove(0);
+        storage.complete_notification(alert.id, now).await.unwrap();
+        queue_resolved(&storage, &routes).await.unwrap();
+        assert!(storage.claim_notifications(10, now).await.unwrap().is_empty());
+
+        storage.resolve_events(std::slice::from_ref(&event.event_id)).await.unwrap();
+        storage.insert_audit("event.resolved", &json!({ "event_ids": [event.event_id] })).await.unwrap();
+        queue_resolved(&storage, &routes).await.unwrap();
+        queue_resolved(&storage, &routes).await.unwrap();
+        let followups = storage.claim_notifications(10, now).await.unwrap();
+        assert_eq!(followups.len(), 1);
+        let resolved: Notification = serde_json::from_str(&followups[0].payload).unwrap();
+        assert_eq!(resolved.kind, "resolved");
+        assert_eq!(resolved.title, "RESOLVED disk_high on box");
+
+        let digest = common::NotifyDigest {
+            name: "hourly".to_string(),
+            schedule: "@hourly".to_string(),
+            sinks: vec!["chat".to_string()],
+            min_severity: Severity::Warning,
+            skip_empty: true,
+        };
+        let schedule = std::str::FromStr::from_str(&digest.schedule).unwrap();
+        assert!(digest::queue_digest(&storage, &digest, &schedule, "box").await.unwrap());
+        let queued = storage.claim_notifications(10, chrono::Utc::now().timestamp()).await.unwrap();
+        let summary: Notification = serde_json::from_str(&queued[0].payload).unwrap();
+        assert_eq!(summary.kind, "digest");
+        let counts = summary.digest.unwrap();
+        assert_eq!(counts["new"]["disk_high"], 1);
+        assert_eq!(counts["resolved"]["disk_high"], 1);
+        assert!(counts["open"].as_object().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_mail_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut transcript = Vec::new();
+            stream.write_all(b"220 mock ESMTP\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let command = line.trim_end().to_string();
+                let reply: &[u8] = match command.split(' ').next().unwrap() {
+                    "EHLO" => b"250-mock\r\n250 AUTH PLAIN\r\n",
+                    "DATA" => {
+                        transcript.push(command);
+                        stream.write_all(b"354 go ahead\r\n").await.unwrap();
+                        loop {
+                            let mut data = String::new();
+                            stream.read_line(&mut data).await.unwrap();
+                            if data == ".\r\n" {
+                                break;
+                            }
+                            transcript.push(data.trim_end().to_string());
+                        }
+                        stream.write_all(b"250 queued\r\n").await.unwrap();
+                        continue;
+                    }
+                    "QUIT" => {
+                        stream.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+                transcript.push(command);
+                stream.write_all(reply).await.unwrap();
+            }
+            transcript
+        });
+
+        let config = EmailSinkConfig {
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: port,
+            tls: SmtpTls::None,
+            username: None,
+            password_file: None,
+            from: "SIA <sia@example.com>".to_string(),
+            to: vec!["oncall@example.com".to_string()],
+        };
+        smtp::send_mail(&config, "CRITICAL cpu_high on box", "line one\n.dotted", Duration::from_secs(5)).await.unwrap();
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains(&"MAIL FROM:<sia@example.com>".to_string()));
+        assert!(transcript.contains(&"RCPT TO:<oncall@example.com>".to_string()));
+        assert!(transcript.contains(&"Subject: CRITICAL cpu_high on box".to_string()));
+        assert!(transcript.contains(&"..dotted".to_string()));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use minijinja::Environment;
+use reqwest::Client;
+use serde_json::{json, Value};
+use common::{Severity, SinkConfig};
+use super::{smtp, Notification};
+
+/// Discord rejects messages longer than this
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// The configured sinks, ready to deliver
+pub struct Sinks {
+    client: Client,
+    sinks: BTreeMap<String, SinkConfig>,
+    /// Webhook body templates, named after their sink
+    templates: Environment<'static>,
+    timeout: Duration,
+}
+
+impl Sinks {
+    pub fn new(sinks: BTreeMap<String, SinkConfig>, timeout_secs: u64) -> Result<Self> {
+        let timeout = Duration::from_secs(timeout_secs.max(1));
+        let mut templates = Environment::new();
+        for (name, sink) in &sinks {
+            if let SinkConfig::Webhook { template: Some(source), .. } = sink {
+                templates.add_template_owned(name.clone(), source.clone())
+                    .with_context(|| format!("invalid template for sink '{}'", name))?;
+            }
+        }
+        Ok(Self {
+            client: Client::builder().timeout(timeout).build()?,
+            sinks,
+            templates,
+            timeout,
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.sinks.contains_key(name)
+    }
+
+    pub async fn send(&self, name: &str, notification: &Notification) -> Result<()> {
+        let sink = self.sinks.get(name).ok_or_else(|| anyhow!("sink '{}' is no longer configured", name))?;
+        match sink {
+            SinkConfig::Webhook { url, headers, template } => {
+                let body = match template {
+                    Some(_) => {
+                        let rendered = self.templates.get_template(name)?.render(notification)?;
+                        serde_json::from_str(&rendered)
+                            .with_context(|| format!("template for sink '{}' did not render JSON", name))?
+                    }
+                    None => serde_json::to_value(notification)?,
+                };
+                let mut request = self.client.post(url).json(&body);
+                for (header, value) in headers {
+                    request = request.header(header, value);
+                }
+                check(request.send().await).await
+            }
+            SinkConfig::Slack { url } => self.post(url, &json!({
+                "text": format!("*{}*\n{}", notification.title, notification.text),
+            })).await,
+            SinkConfig::Discord { url } => {
+                let content: String = format!("**{}**\n{}", notification.title, notification.text)
+                    .chars()
+                    .take(DISCORD_MAX_CHARS)
+                    .collect();
+                self.post(url, &json!({ "content": content })).await
+            }
+            SinkConfig::Teams { url } => self.post(url, &json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": notification.title,
+                "themeColor": theme_color(notification),
+                "title": notification.title,
+                // Teams renders the text as markdown; keep our line breaks
+                "text": notification.text.replace('\n', "  \n"),
+            })).await,
+            SinkConfig::Email(config) => {
+                smtp::send_mail(config, &format!("[SIA] {}", notification.title), &notification.text, self.timeout).await
+            }
+            SinkConfig::Desktop { command } => {
+                let output = tokio::process::Command::new(command)
+                    .args(["--app-name", "sia", "--urgency", urgency(notification)])
+                    .arg(&notification.title)
+                    .arg(&notification.text)
+                    .output()
+                    .await
+                    .with_context(|| format!("cannot run {}", command))?;
+                if !output.status.success() {
+                    bail!("{} exited with {}: {}", command, output.status, String::from_utf8_lossy(&output.stderr).trim());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, body: &Value) -> Result<()> {
+        check(self.client.post(url).json(body).send().await).await
+    }
+}
+
+async fn check(response: reqwest::Result<reqwest::Response>) -> Result<()> {
+    // reqwest's message already includes its causes
+    let response = response.map_err(|e| anyhow!("{}", e))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    bail!("{} {}", status, body.chars().take(200).collect::<String>().trim())
+}
+
+fn theme_color(notification: &Notification) -> &'static str {
+    if notification.kind == "resolved" {
+        return "2E7D32";
+    }
+    match notification.severity {
+        Severity::Critical => "B00020",
+        Severity::Error => "E65100",
+        Severity::Warning => "F9A825",
+        Severity::Info | Severity::Debug => "2E7D32",
+    }
+}
+
+fn urgency(notification: &Notification) -> &'static str {
+    if notification.kind != "alert" {
+        return "low";
+    }
+    match notification.severity {
+        Severity::Critical | Severity::Error => "critical",
+        Severity::Warning => "normal",
+        Severity::Info | Severity::Debug => "low",
+    }
+}
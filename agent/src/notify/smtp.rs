@@ -0,0 +1,170 @@
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use common::{EmailSinkConfig, SmtpTls};
+
+/// Minimal SMTP submission: EHLO, optional STARTTLS and AUTH PLAIN, one
+/// message to every recipient in `config.to`
+pub async fn send_mail(config: &EmailSinkConfig, subject: &str, body: &str, timeout: Duration) -> Result<()> {
+    tokio::time::timeout(timeout, send(config, subject, body))
+        .await
+        .map_err(|_| anyhow!("SMTP session with {} timed out", config.smtp_host))?
+}
+
+async fn send(config: &EmailSinkConfig, subject: &str, body: &str) -> Result<()> {
+    if config.to.is_empty() {
+        bail!("no recipients configured");
+    }
+    let password = match config.password_file {
+        Some(ref path) => Some(std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read SMTP password file {}", path))?
+            .trim()
+            .to_string()),
+        None => None,
+    };
+    let message = format_message(config, subject, body);
+
+    let tcp = TcpStream::connect((config.smtp_host.as_str(), config.smtp_port))
+        .await
+        .with_context(|| format!("cannot connect to {}:{}", config.smtp_host, config.smtp_port))?;
+
+    match config.tls {
+        SmtpTls::None => {
+            let mut session = Session::new(tcp);
+            session.greet().await?;
+            session.deliver(config, password.as_deref(), &message).await
+        }
+        SmtpTls::Tls => {
+            let mut session = Session::new(tls_connect(&config.smtp_host, tcp).await?);
+            session.greet().await?;
+            session.deliver(config, password.as_deref(), &message).await
+        }
+        SmtpTls::StartTls => {
+            let mut plain = Session::new(tcp);
+            plain.greet().await?;
+            plain.command("STARTTLS", 220).await?;
+            let mut session = Session::new(tls_connect(&config.smtp_host, plain.into_inner()).await?);
+            session.ehlo().await?;
+            session.deliver(config, password.as_deref(), &message).await
+        }
+    }
+}
+
+async fn tls_connect(host: &str, tcp: TcpStream) -> Result<tokio_native_tls::TlsStream<TcpStream>> {
+    let connector = tokio_native_tls::TlsConnector::from(tokio_native_tls::native_tls::TlsConnector::new()?);
+    Ok(connector.connect(host, tcp).await?)
+}
+
+struct Session<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self { stream: BufReader::new(stream) }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Server banner, then EHLO
+    async fn greet(&mut self) -> Result<()> {
+        self.expect(220).await?;
+        self.ehlo().await
+    }
+
+    async fn ehlo(&mut self) -> Result<()> {
+        self.command(&format!("EHLO {}", super::host_name()), 250).await?;
+        Ok(())
+    }
+
+    async fn deliver(&mut self, config: &EmailSinkConfig, password: Option<&str>, message: &str) -> Result<()> {
+        if let (Some(user), Some(password)) = (config.username.as_deref(), password) {
+            let credentials = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", user, password));
+            self.command(&format!("AUTH PLAIN {}", credentials), 235).await?;
+        }
+        self.command(&format!("MAIL FROM:<{}>", address(&config.from)), 250).await?;
+        for to in &config.to {
+            self.command(&format!("RCPT TO:<{}>", address(to)), 250).await?;
+        }
+        self.command("DATA", 354).await?;
+        self.stream.write_all(message.as_bytes()).await?;
+        self.command(".", 250).await?;
+        let _ = self.command("QUIT", 221).await;
+        Ok(())
+    }
+
+    async fn command(&mut self, line: &str, expected: u16) -> Result<String> {
+        self.stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+        self.stream.flush().await?;
+        // Name the command but never echo credentials into the delivery error
+        let verb = if line.starts_with("AUTH") { "AUTH PLAIN" } else { line.split(' ').next().unwrap_or(line) };
+        self.expect(expected).await.with_context(|| format!("SMTP {} failed", verb))
+    }
+
+    /// Read a (possibly multi-line) reply and check its code
+    async fn expect(&mut self, expected: u16) -> Result<String> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                bail!("connection closed by server");
+            }
+            reply.push_str(&line);
+            // "250-..." continues, "250 ..." ends the reply
+            if line.len() < 4 || line.as_bytes()[3] != b'-' {
+                break;
+            }
+        }
+        let code: u16 = reply.get(..3).and_then(|c| c.parse().ok())
+            .ok_or_else(|| anyhow!("malformed SMTP reply: {}", reply.trim()))?;
+        if code != expected {
+            bail!("server replied {}", reply.trim());
+        }
+        Ok(reply)
+    }
+}
+
+/// `Name <user@host>` or a bare address, as used in the envelope
+fn address(mailbox: &str) -> &str {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// RFC 5322 message, dot-stuffed and CRLF-terminated for DATA
+fn format_message(config: &EmailSinkConfig, subject: &str, body: &str) -> String {
+    let now = chrono::Utc::now();
+    let domain = address(&config.from).rsplit('@').next().unwrap_or("localhost");
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <sia.{}@{}>\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        encode_header(subject),
+        now.to_rfc2822(),
+        now.timestamp_nanos_opt().unwrap_or_default(),
+        domain,
+    );
+    for line in body.lines() {
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// RFC 2047 encoded-word for non-ASCII subjects
+fn encode_header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        return value;
+    }
+    format!("=?utf-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value))
+}
@@ -0,0 +1,507 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+use reqwest::Client;
+use serde_json::{json, Value};
+use sysinfo::{System, SystemExt};
+use tokio::sync::broadcast;
+use tokio::time::{interval, Instant};
+use common::{Event, OtlpConfig, Severity};
+use crate::selfmon::metrics;
+use log::{info, warn, error};
+
+/// First retry delay after a failed export; doubles up to `MAX_BACKOFF`
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Signal {
+    Logs,
+    Metrics,
+}
+
+impl Signal {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Signal::Logs => "logs",
+            Signal::Metrics => "metrics",
+        }
+    }
+
+    fn from_file(path: &Path) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        if stem.ends_with("-logs") {
+            Some(Signal::Logs)
+        } else if stem.ends_with("-metrics") {
+            Some(Signal::Metrics)
+        } else {
+            None
+        }
+    }
+}
+
+enum ExportError {
+    /// The collector is unreachable or asked us to back off; keep the batch
+    Retryable(String),
+    /// The collector rejected the batch; sending it again won't help
+    Rejected(String),
+}
+
+/// OTLP/HTTP JSON exporter with an on-disk buffer for batches the collector
+/// couldn't take. Buffered batches are replayed oldest first, before anything
+/// new, so the collector sees events in order.
+pub struct OtlpExporter {
+    client: Client,
+    config: OtlpConfig,
+    resource: Value,
+    buffer_dir: PathBuf,
+    backoff: Duration,
+    retry_at: Option<Instant>,
+    seq: u64,
+}
+
+pub fn start_otlp_exporter(config: OtlpConfig, mut rx: broadcast::Receiver<Event>) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let mut exporter = OtlpExporter::new(config.clone())?;
+    info!("Exporting to OTLP collector at {} every {}s", config.endpoint, config.export_interval_secs);
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(config.export_interval_secs.max(1)));
+        let mut pending: Vec<Value> = Vec::new();
+        loop {
+            tokio::select! {
+                event = rx.recv(), if config.export_events => match event {
+                    Ok(event) => {
+                        pending.push(log_record(&event));
+                        if pending.len() >= config.batch_size.max(1) {
+                            exporter.deliver(Signal::Logs, logs_payload(&exporter.resource, std::mem::take(&mut pending))).await;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => warn!("OTLP exporter lagged, skipped {} events", n),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = ticker.tick() => {
+                    exporter.flush_buffer().await;
+                    if !pending.is_empty() {
+                        exporter.deliver(Signal::Logs, logs_payload(&exporter.resource, std::mem::take(&mut pending))).await;
+                    }
+                    if config.export_metrics {
+                        let payload = metrics_payload(&exporter.resource, &metrics().snapshot(), chrono::Utc::now());
+                        exporter.deliver(Signal::Metrics, payload).await;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+impl OtlpExporter {
+    pub fn new(config: OtlpConfig) -> Result<Self> {
+        let buffer_dir = PathBuf::from(&config.buffer_dir);
+        std::fs::create_dir_all(&buffer_dir)?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &config.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())?,
+                reqwest::header::HeaderValue::from_str(value)?,
+            );
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            client,
+            resource: resource(&config),
+            config,
+            buffer_dir,
+            backoff: MIN_BACKOFF,
+            retry_at: None,
+            seq: 0,
+        })
+    }
+
+    /// Send `payload` now, or buffer it if the collector is down or older
+    /// batches are still waiting
+    async fn deliver(&mut self, signal: Signal, payload: Value) {
+        if self.flush_buffer().await {
+            match self.export(signal, &payload).await {
+                Ok(()) => return,
+                Err(ExportError::Rejected(e)) => {
+                    warn!("OTLP collector rejected {} batch, dropping it: {}", signal.as_str(), e);
+                    return;
+                }
+                Err(ExportError::Retryable(e)) => self.failed(signal, &e),
+            }
+        }
+        if let Err(e) = self.buffer(signal, &payload) {
+            error!("Failed to buffer OTLP {} batch: {}", signal.as_str(), e);
+        }
+    }
+
+    /// Replay buffered batches oldest first; true once the buffer is empty
+    async fn flush_buffer(&mut self) -> bool {
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return false;
+        }
+        for path in self.buffered() {
+            let Some(signal) = Signal::from_file(&path) else { continue };
+            let payload = match std::fs::read(&path).map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(serde_json::from_slice::<Value>(&bytes)?))
+            {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("Discarding unreadable OTLP buffer file {}: {}", path.display(), e);
+                    let _ = std::fs::remove_file(&path);
+                    continue;
+                }
+            };
+            match self.export(signal, &payload).await {
+                Ok(()) => {}
+                Err(ExportError::Rejected(e)) => warn!("OTLP collector rejected buffered {} batch, dropping it: {}", signal.as_str(), e),
+                Err(ExportError::Retryable(e)) => {
+                    self.failed(signal, &e);
+                    return false;
+                }
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        if self.retry_at.take().is_some() {
+            info!("OTLP collector reachable again, buffer drained");
+        }
+        self.backoff = MIN_BACKOFF;
+        true
+    }
+
+    async fn export(&self, signal: Signal, payload: &Value) -> Result<(), ExportError> {
+        let url = format!("{}/v1/{}", self.config.endpoint.trim_end_matches('/'), signal.as_str());
+        let response = self.client.post(&url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| ExportError::Retryable(e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = format!("{} {}", status, body.trim());
+        // Per the OTLP spec only these are worth retrying
+        if matches!(status.as_u16(), 429 | 502 | 503 | 504) {
+            Err(ExportError::Retryable(message))
+        } else {
+            Err(ExportError::Rejected(message))
+        }
+    }
+
+    fn failed(&mut self, signal: Signal, error: &str) {
+        warn!("OTLP {} export failed, retrying in {}s: {}", signal.as_str(), self.backoff.as_secs(), error);
+        self.retry_at = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+
+    fn buffer(&mut self, signal: Signal, payload: &Value) -> Result<()> {
+        self.seq += 1;
+        let name = format!("{:013}-{:06}-{}.json", chrono::Utc::now().timestamp_millis(), self.seq % 1_000_000, signal.as_str());
+        std::fs::write(self.buffer_dir.join(name), serde_json::to_vec(payload)?)?;
+
+        // Keep the newest data when the buffer is full
+        let max_bytes = self.config.buffer_max_mb * 1024 * 1024;
+        let mut files: Vec<(PathBuf, u64)> = self.buffered().into_iter()
+            .map(|p| {
+                let size = std::fs::metadata(&p).map(|m| m.len()).unwrap_or(0);
+                (p, size)
+            })
+            .collect();
+        let mut total: u64 = files.iter().map(|(_, size)| size).sum();
+        let mut dropped = 0;
+        for (path, size) in files.drain(..) {
+            if total <= max_bytes {
+                break;
+            }
+            let _ = std::fs::remove_file(&path);
+            total -= size;
+            dropped += 1;
+        }
+        if dropped > 0 {
+            warn!("OTLP buffer over {} MB, dropped {} oldest batches", self.config.buffer_max_mb, dropped);
+        }
+        Ok(())
+    }
+
+    /// Buffered batch files, oldest first
+    fn buffered(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.buffer_dir) else { return Vec::new() };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+fn resource(config: &OtlpConfig) -> Value {
+    let mut sys = System::new();
+    sys.refresh_system();
+    let mut attributes = vec![
+        attribute("host.name", &sys.host_name().unwrap_or_default()),
+        attribute("service.name", &config.service_name),
+        attribute("service.version", env!("CARGO_PKG_VERSION")),
+    ];
+    for (key, value) in &config.resource_attributes {
+        attributes.push(attribute(key, value));
+    }
+    json!({ "attributes": attributes })
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// OTel severity numbers: DEBUG 5, INFO 9, WARN 13, ERROR 17, FATAL 21
+fn severity_number(severity: Severity) -> u8 {
+    match severity {
+        Severity::Debug => 5,
+        Severity::Info => 9,
+        Severity::Warning => 13,
+        Severity::Error => 17,
+        Severity::Critical => 21,
+    }
+}
+
+/// An event as an OTel log record; entity and evidence travel as JSON strings
+fn log_record(event: &Event) -> Value {
+    let ts_nanos = chrono::DateTime::parse_from_rfc3339(&event.ts)
+        .ok()
+        .and_then(|t| t.timestamp_nanos_opt())
+        .unwrap_or_default();
+    let mut attributes = vec![
+        attribute("sia.event.id", &event.event_id),
+        attribute("sia.event.type", event.r#type.as_str()),
+        attribute("sia.event.status", &event.status),
+        attribute("sia.event.entity", &event.entity.to_string()),
+        attribute("sia.event.evidence", &event.evidence.to_string()),
+    ];
+    if let Some(ref silence) = event.silenced_by {
+        attributes.push(attribute("sia.event.silenced_by", silence));
+    }
+    json!({
+        "timeUnixNano": ts_nanos.to_string(),
+        "observedTimeUnixNano": chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default().to_string(),
+        "severityNumber": severity_number(event.severity),
+        "severityText": event.severity.as_str(),
+        "body": { "stringValue": format!("{} {}", event.severity, event.r#type) },
+        "attributes": attributes,
+    })
+}
+
+fn logs_payload(resource: &Value, records: Vec<Value>) -> Value {
+    json!({
+        "resourceLogs": [{
+            "resource": resource,
+            "scopeLogs": [{
+                "scope": { "name": "sia-agent", "version": env!("CARGO_PKG_VERSION") },
+                "logRecords": records,
+            }],
+        }],
+    })
+}
+
+/// Latest host samples and the agent's own gauges from the self-monitor snapshot
+fn metrics_payload(resource: &Value, snapshot: &Value, now: chrono::DateTime<chrono::Utc>) -> Value {
+    let ts = now.timestamp_nanos_opt().unwrap_or_default().to_string();
+    let gauge = |name: String, unit: &str, value: f64| json!({
+        "name": name,
+        "unit": unit,
+        "gauge": { "dataPoints": [{ "timeUnixNano": ts, "asDouble": value }] },
+    });
+
+    let mut metrics: Vec<Value> = snapshot["host"].as_object()
+        .map(|host| host.iter()
+            .map(|(name, value)| {
+                let unit = if name.ends_with("_percent") { "%" } else { "1" };
+                gauge(format!("sia.host.{}", name), unit, value.as_f64().unwrap_or(0.0))
+            })
+            .collect())
+        .unwrap_or_default();
+    metrics.push(gauge("sia.agent.rss".to_string(), "By", snapshot["process"]["rss_bytes"].as_f64().unwrap_or(0.0)));
+    metrics.push(gauge("sia.agent.cpu".to_string(), "%", snapshot["process"]["cpu_percent"].as_f64().unwrap_or(0.0)));
+    metrics.push(gauge("sia.agent.event_queue.depth".to_string(), "1", snapshot["event_queue"]["depth"].as_f64().unwrap_or(0.0)));
+
+    json!({
+        "resourceMetrics": [{
+            "resource": resource,
+            "scopeMetrics": [{
+                "scope": { "name": "sia-agent", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Request captured by the stand-in collector
+    struct Captured {
+        request_line: String,
+        headers: String,
+        body: Value,
+    }
+
+    /// Stand-in OTLP collector: answers one request per entry in `statuses`
+    async fn mock_collector(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Captured>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let header_end = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head.lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let reply = format!("HTTP/1.1 {} X\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{{}}", status);
+                stream.write_all(reply.as_bytes()).await.unwrap();
+
+                let (request_line, headers) = head.split_once("\r\n").unwrap();
+                let _ = tx.send(Captured {
+                    request_line: request_line.to_string(),
+                    headers: headers.to_ascii_lowercase(),
+                    body: serde_json::from_slice(&buf[header_end..]).unwrap_or(Value::Null),
+                });
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn test_config(endpoint: &str, buffer: &str) -> OtlpConfig {
+        let dir = std::env::temp_dir().join(format!("sia-otlp-{}-{}", buffer, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        OtlpConfig {
+            enabled: true,
+            endpoint: endpoint.to_string(),
+            buffer_dir: dir.to_string_lossy().to_string(),
+            ..OtlpConfig::default()
+        }
+    }
+
+    fn test_event() -> Event {
+        Event {
+            event_id: "cpu_1".to_string(),
+            ts: "2026-01-01T00:00:00+00:00".to_string(),
+            severity: Severity::Error,
+            r#type: common::EventKind::CpuHigh,
+            entity: json!({ "type": "host" }),
+            evidence: json!({ "cpu_percent": 97.5 }),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        }
+    }
+
+    fn attr<'a>(attributes: &'a Value, key: &str) -> &'a Value {
+        &attributes.as_array().unwrap().iter().find(|a| a["key"] == key).unwrap()["value"]["stringValue"]
+    }
+
+    #[tokio::test]
+    async fn exports_events_as_log_records() {
+        let (url, mut rx) = mock_collector(vec![200]).await;
+        let mut config = test_config(&url, "logs");
+        config.headers.insert("x-api-key".to_string(), "secret".to_string());
+        config.resource_attributes.insert("deployment.environment".to_string(), "test".to_string());
+        let mut exporter = OtlpExporter::new(config).unwrap();
+
+        let payload = logs_payload(&exporter.resource, vec![log_record(&test_event())]);
+        exporter.deliver(Signal::Logs, payload).await;
+
+        let captured = rx.recv().await.unwrap();
+        assert!(captured.request_line.starts_with("POST /v1/logs "));
+        assert!(captured.headers.contains("x-api-key: secret"));
+
+        let resource_logs = &captured.body["resourceLogs"][0];
+        let resource = &resource_logs["resource"]["attributes"];
+        assert_eq!(attr(resource, "service.name"), "sia-agent");
+        assert_eq!(attr(resource, "deployment.environment"), "test");
+        assert!(attr(resource, "host.name").is_string());
+
+        let record = &resource_logs["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityNumber"], 17);
+        assert_eq!(record["severityText"], "ERROR");
+        assert_eq!(record["timeUnixNano"], "1767225600000000000");
+        assert_eq!(attr(&record["attributes"], "sia.event.type"), "cpu_high");
+        assert_eq!(attr(&record["attributes"], "sia.event.evidence"), r#"{"cpu_percent":97.5}"#);
+        assert!(exporter.buffered().is_empty());
+    }
+
+    #[tokio::test]
+    async fn buffers_while_collector_is_unreachable() {
+        // Nothing listens on a port we bound and released
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let mut exporter = OtlpExporter::new(test_config(&format!("http://{}", closed), "buffer")).unwrap();
+
+        let first = logs_payload(&exporter.resource, vec![log_record(&test_event())]);
+        exporter.deliver(Signal::Logs, first).await;
+        assert_eq!(exporter.buffered().len(), 1);
+        assert!(exporter.retry_at.is_some());
+
+        // Still backing off: new batches queue up behind the buffered one
+        let second = metrics_payload(&exporter.resource, &metrics().snapshot(), chrono::Utc::now());
+        exporter.deliver(Signal::Metrics, second).await;
+        assert_eq!(exporter.buffered().len(), 2);
+
+        let (url, mut rx) = mock_collector(vec![200, 200]).await;
+        exporter.config.endpoint = url;
+        exporter.retry_at = None;
+        assert!(exporter.flush_buffer().await);
+
+        assert!(rx.recv().await.unwrap().request_line.starts_with("POST /v1/logs "));
+        let metrics = rx.recv().await.unwrap();
+        assert!(metrics.request_line.starts_with("POST /v1/metrics "));
+        assert!(metrics.body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"].as_array().is_some_and(|m| !m.is_empty()));
+        assert!(exporter.buffered().is_empty());
+    }
+
+    #[tokio::test]
+    async fn drops_rejected_batches() {
+        let (url, mut rx) = mock_collector(vec![400]).await;
+        let mut exporter = OtlpExporter::new(test_config(&url, "rejected")).unwrap();
+
+        let payload = logs_payload(&exporter.resource, vec![log_record(&test_event())]);
+        exporter.deliver(Signal::Logs, payload).await;
+
+        assert!(rx.recv().await.is_some());
+        assert!(exporter.buffered().is_empty());
+        assert!(exporter.retry_at.is_none());
+    }
+}
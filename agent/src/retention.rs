@@ -0,0 +1,128 @@
+use tokio::time::{interval, Duration};
+use common::RetentionConfig;
+use crate::llm::AUDIT_KIND as LLM_AUDIT_KIND;
+use crate::storage::Storage;
+use serde_json::json;
+use log::{info, warn, error};
+
+/// `audits.kind` of retention runs that deleted something; `storage.stats` reports the latest
+pub const AUDIT_KIND: &str = "storage.retention";
+
+/// Events deleted per round while evicting for quota
+const EVICT_BATCH: i64 = 500;
+
+/// Upper bound on eviction rounds per run, so one run can't stall the writer
+const MAX_EVICT_ROUNDS: usize = 200;
+
+/// Keeps the event store within `disk_quota_mb`: applies the per-severity age
+/// policies, then evicts the oldest resolved events while the database and
+/// its WAL are over quota, and hands freed pages back to the filesystem.
+pub async fn start_retention(storage: Storage, disk_quota_mb: usize, config: RetentionConfig) -> anyhow::Result<()> {
+    if !config.enabled {
+        info!("Storage retention disabled");
+        return Ok(());
+    }
+
+    if storage.enable_incremental_vacuum().await? {
+        info!("Enabled incremental auto-vacuum on the event store");
+    }
+
+    let quota_bytes = disk_quota_mb as u64 * 1024 * 1024;
+    info!("Starting storage retention ({}s interval, {} MB quota)", config.interval_secs, disk_quota_mb);
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(config.interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = enforce(&storage, quota_bytes, &config).await {
+                error!("Storage retention failed: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn enforce(storage: &Storage, quota_bytes: u64, config: &RetentionConfig) -> anyhow::Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    let before = storage.get_storage_stats().await?;
+
+    let mut expired = 0;
+    for (severity, days) in &config.max_age_days {
+        expired += storage.delete_events_before(*severity, now - *days as i64 * 86400).await?;
+    }
+    let mut samples = 0;
+    if config.metric_sample_days > 0 {
+        samples += storage.delete_metric_samples_before(now - config.metric_sample_days as i64 * 86400).await?;
+    }
+    let mut chat_messages = 0;
+    if config.chat_session_days > 0 {
+        chat_messages += storage.delete_chat_sessions_before(now - config.chat_session_days as i64 * 86400).await?;
+    }
+    let mut audits = 0;
+    if config.audit_days > 0 {
+        audits += storage.delete_audits_except_before(LLM_AUDIT_KIND, now - config.audit_days as i64 * 86400).await?;
+    }
+    let mut notifications = 0;
+    if config.notification_days > 0 {
+        notifications += storage.delete_finished_notifications_before(now - config.notification_days as i64 * 86400).await?;
+    }
+    storage.compact().await?;
+
+    // Deleting rows only frees pages inside the file, so re-measure after
+    // every batch and compaction
+    let target = quota_bytes * config.target_pct.clamp(1, 100) / 100;
+    let mut evicted = 0;
+    let mut stats = storage.get_storage_stats().await?;
+    if stats.total_bytes() > quota_bytes {
+        for _ in 0..MAX_EVICT_ROUNDS {
+            let deleted = storage.delete_oldest_resolved_events(EVICT_BATCH).await?;
+            if deleted == 0 {
+                break;
+            }
+            evicted += deleted;
+            storage.compact().await?;
+            stats = storage.get_storage_stats().await?;
+            if stats.total_bytes() <= target {
+                break;
+            }
+        }
+        if stats.total_bytes() > quota_bytes {
+            warn!(
+                "Event store still over quota ({} of {} bytes) with no resolved events left to evict",
+                stats.total_bytes(),
+                quota_bytes
+            );
+        }
+    }
+
+    if expired + samples + chat_messages + audits + notifications + evicted > 0 {
+        storage.delete_orphans().await?;
+        storage.compact().await?;
+        stats = storage.get_storage_stats().await?;
+        info!(
+            "Retention deleted {} expired and {} evicted events, {} metric samples, {} chat messages, {} audits, {} notifications ({} -> {} bytes)",
+            expired,
+            evicted,
+            samples,
+            chat_messages,
+            audits,
+            notifications,
+            before.total_bytes(),
+            stats.total_bytes()
+        );
+        storage.insert_audit(AUDIT_KIND, &json!({
+            "quota_bytes": quota_bytes,
+            "bytes_before": before.total_bytes(),
+            "bytes_after": stats.total_bytes(),
+            "expired_events": expired,
+            "evicted_events": evicted,
+            "metric_samples": samples,
+            "chat_messages": chat_messages,
+            "audits": audits,
+            "notifications": notifications,
+        })).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sysinfo::{ProcessExt, System, SystemExt};
+use tokio::sync::mpsc;
+use tokio::time::interval;
+use common::{Event, EventKind, SelfMonitorConfig, Severity};
+use crate::governor::MemoryGovernor;
+use log::{info, warn};
+
+/// While degraded, `agent_degraded` is re-raised this often so the sweeper
+/// doesn't resolve it; the sweeper doesn't count these as recurrences
+const REEMIT_SECS: u64 = 300;
+
+static METRICS: OnceLock<AgentMetrics> = OnceLock::new();
+
+/// Counters the agent keeps about itself, served by `agent.metrics`
+pub fn metrics() -> &'static AgentMetrics {
+    METRICS.get_or_init(AgentMetrics::default)
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceCounters {
+    /// Events that reached the analyzer
+    pub received: u64,
+    pub stored: u64,
+    /// Events lost on the way: channel closed, shed, or failed to store
+    pub dropped: u64,
+    /// Sends that found the event channel full and had to wait
+    pub blocked: u64,
+    /// Metric samples lost to a full channel or load shedding
+    pub samples_dropped: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub errors: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyStats {
+    fn record(&mut self, elapsed: Duration, ok: bool) {
+        let ms = elapsed.as_millis() as u64;
+        self.count += 1;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+        if !ok {
+            self.errors += 1;
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "count": self.count,
+            "errors": self.errors,
+            "total_ms": self.total_ms,
+            "avg_ms": self.total_ms.checked_div(self.count).unwrap_or(0),
+            "max_ms": self.max_ms,
+        })
+    }
+}
+
+struct Process {
+    rss_bytes: u64,
+    cpu_percent: f32,
+    fds: Option<usize>,
+    shedding: &'static str,
+}
+
+impl Default for Process {
+    fn default() -> Self {
+        Self { rss_bytes: 0, cpu_percent: 0.0, fds: None, shedding: "none" }
+    }
+}
+
+#[derive(Default)]
+pub struct AgentMetrics {
+    sources: Mutex<BTreeMap<String, SourceCounters>>,
+    storage_writes: Mutex<LatencyStats>,
+    llm: Mutex<BTreeMap<String, LatencyStats>>,
+    ipc: Mutex<BTreeMap<String, u64>>,
+    event_queue: Mutex<Option<mpsc::Sender<Event>>>,
+    process: Mutex<Process>,
+    degraded: Mutex<Vec<String>>,
+    /// Latest value of every host metric a collector sampled
+    host: Mutex<BTreeMap<String, f64>>,
+    /// Unix time each collector last ran a cycle
+    collector_ticks: Mutex<BTreeMap<String, i64>>,
+}
+
+impl AgentMetrics {
+    fn source(&self, source: &str, update: impl FnOnce(&mut SourceCounters)) {
+        update(self.sources.lock().unwrap().entry(source.to_string()).or_default());
+    }
+
+    pub fn event_received(&self, kind: &EventKind) {
+        self.source(source_of(kind), |c| c.received += 1);
+    }
+
+    pub fn event_stored(&self, kind: &EventKind, elapsed: Duration) {
+        self.source(source_of(kind), |c| c.stored += 1);
+        self.storage_writes.lock().unwrap().record(elapsed, true);
+    }
+
+    pub fn event_store_failed(&self, kind: &EventKind, elapsed: Duration) {
+        self.source(source_of(kind), |c| c.dropped += 1);
+        self.storage_writes.lock().unwrap().record(elapsed, false);
+    }
+
+    pub fn event_dropped(&self, kind: &EventKind) {
+        self.source(source_of(kind), |c| c.dropped += 1);
+    }
+
+    pub fn send_blocked(&self, kind: &EventKind) {
+        self.source(source_of(kind), |c| c.blocked += 1);
+    }
+
+    pub fn sample_dropped(&self, source: &str) {
+        self.source(source, |c| c.samples_dropped += 1);
+    }
+
+    pub fn host_sample(&self, name: &str, value: f64) {
+        self.host.lock().unwrap().insert(name.to_string(), value);
+    }
+
+    pub fn collector_tick(&self, collector: &str) {
+        self.collector_ticks.lock().unwrap().insert(collector.to_string(), chrono::Utc::now().timestamp());
+    }
+
+    pub fn llm_request(&self, purpose: &str, elapsed: Duration, ok: bool) {
+        self.llm.lock().unwrap().entry(purpose.to_string()).or_default().record(elapsed, ok);
+    }
+
+    pub fn ipc_request(&self, method: &str) {
+        *self.ipc.lock().unwrap().entry(method.to_string()).or_default() += 1;
+    }
+
+    /// The channel whose depth is reported as `event_queue`
+    pub fn watch_event_queue(&self, tx: mpsc::Sender<Event>) {
+        *self.event_queue.lock().unwrap() = Some(tx);
+    }
+
+    fn queue_depth(&self) -> (usize, usize) {
+        match *self.event_queue.lock().unwrap() {
+            Some(ref tx) => (tx.max_capacity() - tx.capacity(), tx.max_capacity()),
+            None => (0, 0),
+        }
+    }
+
+    fn blocked_total(&self) -> u64 {
+        self.sources.lock().unwrap().values().map(|c| c.blocked).sum()
+    }
+
+    pub fn snapshot(&self) -> Value {
+        let (depth, capacity) = self.queue_depth();
+        let process = self.process.lock().unwrap();
+        json!({
+            "process": {
+                "pid": std::process::id(),
+                "rss_bytes": process.rss_bytes,
+                "rss_mb": process.rss_bytes / 1024 / 1024,
+                "cpu_percent": process.cpu_percent,
+                "fds": process.fds,
+                "shedding": process.shedding,
+            },
+            "event_queue": { "depth": depth, "capacity": capacity },
+            "sources": *self.sources.lock().unwrap(),
+            "storage_writes": self.storage_writes.lock().unwrap().to_json(),
+            "llm": self.llm.lock().unwrap().iter()
+                .map(|(purpose, stats)| (purpose.clone(), stats.to_json()))
+                .collect::<serde_json::Map<_, _>>(),
+            "ipc": *self.ipc.lock().unwrap(),
+            "degraded": *self.degraded.lock().unwrap(),
+            "host": *self.host.lock().unwrap(),
+            "collector_ticks": *self.collector_ticks.lock().unwrap(),
+        })
+    }
+}
+
+/// Collectors are named after what they watch; events are attributed by type
+pub fn source_of(kind: &EventKind) -> &str {
+    match kind {
+        EventKind::CpuHigh => "cpu",
+        EventKind::MemoryHigh => "memory",
+        EventKind::Anomaly => "baseline",
+        EventKind::AgentMemoryHigh | EventKind::AgentDegraded => "agent",
+        other => other.as_str(),
+    }
+}
+
+/// Sample the agent process every `interval_secs` and raise `agent_degraded`
+/// when collectors block on the event channel, the channel backs up, or
+/// event writes get slow.
+pub fn start_self_monitor(config: SelfMonitorConfig, governor: MemoryGovernor, tx: mpsc::Sender<Event>) {
+    metrics().watch_event_queue(tx.clone());
+    if !config.enabled {
+        info!("Agent self-monitoring disabled");
+        return;
+    }
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        warn!("Cannot find own pid, agent self-monitoring disabled");
+        return;
+    };
+    info!("Starting agent self-monitor ({}s interval)", config.interval_secs);
+
+    tokio::spawn(async move {
+        let mut sys = System::new();
+        let mut ticker = interval(Duration::from_secs(config.interval_secs.max(1)));
+        let mut last_blocked = 0;
+        let mut last_writes = LatencyStats::default();
+        let mut raised_at: Option<Instant> = None;
+        loop {
+            ticker.tick().await;
+
+            if sys.refresh_process(pid) {
+                if let Some(process) = sys.process(pid) {
+                    *metrics().process.lock().unwrap() = Process {
+                        rss_bytes: process.memory(),
+                        cpu_percent: process.cpu_usage(),
+                        fds: std::fs::read_dir("/proc/self/fd").ok().map(|d| d.count()),
+                        shedding: governor.shedding().as_str(),
+                    };
+                }
+            }
+
+            let mut reasons = Vec::new();
+            let blocked = metrics().blocked_total();
+            if blocked > last_blocked {
+                reasons.push(format!("collectors blocked {} times on a full event channel", blocked - last_blocked));
+            }
+            last_blocked = blocked;
+
+            let (depth, capacity) = metrics().queue_depth();
+            if capacity > 0 && depth * 100 >= capacity * config.queue_depth_pct as usize {
+                reasons.push(format!("event channel at {} of {}", depth, capacity));
+            }
+
+            let writes = metrics().storage_writes.lock().unwrap().clone();
+            let window_avg_ms = (writes.total_ms - last_writes.total_ms).checked_div(writes.count - last_writes.count);
+            if let Some(avg_ms) = window_avg_ms.filter(|ms| *ms >= config.write_latency_ms) {
+                reasons.push(format!("event writes averaging {} ms", avg_ms));
+            }
+            last_writes = writes;
+
+            *metrics().degraded.lock().unwrap() = reasons.clone();
+            if reasons.is_empty() {
+                if raised_at.take().is_some() {
+                    info!("Agent no longer degraded");
+                }
+                continue;
+            }
+            if raised_at.is_some_and(|t| t.elapsed().as_secs() < REEMIT_SECS) {
+                continue;
+            }
+            warn!("Agent degraded: {}", reasons.join("; "));
+            // The channel may be what's backed up; retry on the next tick if so
+            if tx.try_send(create_degraded_event(&reasons)).is_ok() {
+                raised_at = Some(Instant::now());
+            }
+        }
+    });
+}
+
+fn create_degraded_event(reasons: &[String]) -> Event {
+    use chrono::Utc;
+
+    let event_id = format!("agentdeg_{}", Utc::now().timestamp_millis());
+    let ts = Utc::now().to_rfc3339();
+
+    let entity = json!({
+        "type": "agent",
+        "pid": std::process::id(),
+    });
+
+    let evidence = json!({
+        "reasons": reasons,
+        "metrics": metrics().snapshot(),
+        "timestamp": ts.clone()
+    });
+
+    Event {
+        event_id,
+        ts,
+        severity: Severity::Warning,
+        r#type: EventKind::AgentDegraded,
+        entity,
+        evidence,
+        suggestion: None,
+        status: "open".to_string(),
+        silenced_by: None,
+    }
+}
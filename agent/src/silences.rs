@@ -0,0 +1,129 @@
+use std::str::FromStr;
+use chrono::{DateTime, Duration, Local, Utc};
+use common::{Event, MaintenanceWindow, Silence};
+use crate::storage::Storage;
+use log::{info, warn};
+
+/// How long stored silences are cached before being re-read
+const REFRESH_SECS: i64 = 5;
+
+struct ScheduledWindow {
+    window: MaintenanceWindow,
+    schedule: cron::Schedule,
+}
+
+/// Active silences from storage plus recurring maintenance windows from config
+pub struct SilenceSet {
+    storage: Storage,
+    silences: Vec<Silence>,
+    loaded_at: Option<DateTime<Utc>>,
+    windows: Vec<ScheduledWindow>,
+}
+
+impl SilenceSet {
+    pub fn new(storage: Storage, windows: Vec<MaintenanceWindow>) -> Self {
+        let windows = windows.into_iter()
+            .filter_map(|window| match cron::Schedule::from_str(&window.schedule) {
+                Ok(schedule) => {
+                    info!("Maintenance window '{}' scheduled at '{}' for {}m", window.name, window.schedule, window.duration_mins);
+                    Some(ScheduledWindow { window, schedule })
+                }
+                Err(e) => {
+                    warn!("Ignoring maintenance window '{}': invalid schedule '{}': {}", window.name, window.schedule, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            storage,
+            silences: Vec::new(),
+            loaded_at: None,
+            windows,
+        }
+    }
+
+    /// Returns the id of the first silence or maintenance window matching the event
+    pub async fn matching(&mut self, event: &Event, service_id: &str) -> Option<String> {
+        let now = Utc::now();
+        self.refresh(now).await;
+
+        if let Some(silence) = self.silences.iter()
+            .find(|s| s.is_active(now.timestamp()) && s.matchers.matches(event, service_id))
+        {
+            return Some(silence.id.clone());
+        }
+
+        let local_now = now.with_timezone(&Local);
+        self.windows.iter()
+            .find(|w| in_window(w, &local_now) && w.window.matchers.matches(event, service_id))
+            .map(|w| format!("maintenance:{}", w.window.name))
+    }
+
+    async fn refresh(&mut self, now: DateTime<Utc>) {
+        if self.loaded_at.is_some_and(|t| (now - t).num_seconds() < REFRESH_SECS) {
+            return;
+        }
+        match self.storage.get_silences(false, now.timestamp()).await {
+            Ok(silences) => {
+                self.silences = silences;
+                self.loaded_at = Some(now);
+            }
+            Err(e) => warn!("Failed to load silences: {}", e),
+        }
+    }
+}
+
+/// A window is open if the schedule fired within the last `duration_mins`
+fn in_window(w: &ScheduledWindow, now: &DateTime<Local>) -> bool {
+    let opened_after = *now - Duration::minutes(w.window.duration_mins as i64);
+    w.schedule.after(&opened_after)
+        .next()
+        .is_some_and(|start| start <= *now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use common::SilenceMatchers;
+
+    fn window(schedule: &str, duration_mins: u64) -> ScheduledWindow {
+        ScheduledWindow {
+            window: MaintenanceWindow {
+                name: "nightly".to_string(),
+                schedule: schedule.to_string(),
+                duration_mins,
+                comment: String::new(),
+                matchers: SilenceMatchers::default(),
+            },
+            schedule: cron::Schedule::from_str(schedule).unwrap(),
+        }
+    }
+
+    fn local(day: u32, hour: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, day, hour, min, 0).unwrap()
+    }
+
+    #[test]
+    fn windows_span_midnight_in_local_time() {
+        // 23:30 every night for an hour
+        let nightly = window("0 30 23 * * *", 60);
+        assert!(!in_window(&nightly, &local(14, 23, 29)));
+        assert!(in_window(&nightly, &local(14, 23, 30)));
+        assert!(in_window(&nightly, &local(14, 23, 59)));
+        assert!(in_window(&nightly, &local(15, 0, 0)));
+        assert!(in_window(&nightly, &local(15, 0, 29)));
+        assert!(!in_window(&nightly, &local(15, 0, 31)));
+        assert!(!in_window(&nightly, &local(15, 12, 0)));
+    }
+
+    #[test]
+    fn windows_only_open_on_scheduled_days() {
+        // 2026-01-18 is a Sunday
+        let sundays = window("0 0 2 * * Sun", 120);
+        assert!(in_window(&sundays, &local(18, 3, 0)));
+        assert!(!in_window(&sundays, &local(18, 4, 1)));
+        assert!(!in_window(&sundays, &local(17, 3, 0)));
+    }
+}
@@ -1,19 +1,191 @@
+use std::collections::BTreeMap;
 use sqlx::SqlitePool;
 use anyhow::Result;
+use log::error;
+use common::{Event, EventKind, Service, ServiceDiscovery, Severity, Silence, SilenceMatchers};
 
 
 #[derive(Clone)]
-pub struct Storage { pool: SqlitePool }
+pub struct Storage { pool: SqlitePool, path: String }
 
 #[derive(Debug)]
 pub struct StoredEvent {
     pub event_id: String,
     pub ts: i64,
-    pub severity: String,
-    pub type_: String,
+    pub severity: Severity,
+    pub type_: EventKind,
     pub service_id: String,
+    pub fingerprint: Option<String>,
     pub snapshot: Vec<u8>,
     pub status: String,
+    pub silenced_by: Option<String>,
+    pub suggestion: Option<serde_json::Value>,
+}
+
+impl StoredEvent {
+    /// Rebuild the pipeline event; snapshots written before `{entity, evidence}`
+    /// hold only the entity.
+    pub fn to_event(&self) -> Event {
+        let snapshot: serde_json::Value = serde_json::from_slice(&self.snapshot).unwrap_or_default();
+        let (entity, evidence) = match snapshot.get("entity") {
+            Some(entity) => (entity.clone(), snapshot.get("evidence").cloned().unwrap_or_default()),
+            None => (snapshot, serde_json::Value::Null),
+        };
+        Event {
+            event_id: self.event_id.clone(),
+            ts: chrono::DateTime::from_timestamp(self.ts, 0).unwrap_or_default().to_rfc3339(),
+            severity: self.severity,
+            r#type: self.type_.clone(),
+            entity,
+            evidence,
+            suggestion: self.suggestion.clone(),
+            status: self.status.clone(),
+            silenced_by: self.silenced_by.clone(),
+        }
+    }
+}
+
+/// Open event summary used by the escalation/decay sweeper
+#[derive(Debug, Clone)]
+pub struct OpenEvent {
+    pub event_id: String,
+    pub ts: i64,
+    pub severity: Severity,
+    pub kind: EventKind,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredIncident {
+    pub id: String,
+    pub opened_at: i64,
+    pub updated_at: i64,
+    pub status: String,
+    pub severity: Severity,
+    pub entities: Vec<String>,
+    pub root_cause: Option<String>,
+}
+
+/// An event grouped into an incident, ordered by root-cause rank
+#[derive(Debug, Clone)]
+pub struct StoredIncidentMember {
+    pub event_id: String,
+    pub ts: i64,
+    pub severity: Severity,
+    pub kind: EventKind,
+}
+
+/// Aggregate of one metric over a time range
+#[derive(Debug, Clone)]
+pub struct MetricSummary {
+    pub name: String,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub samples: i64,
+    pub first_ts: i64,
+    pub last_ts: i64,
+}
+
+/// Pending LLM analysis claimed by the job queue
+#[derive(Debug, Clone)]
+pub struct LlmJob {
+    pub id: i64,
+    pub event_id: String,
+    /// The event as it was enqueued (serialized `common::Event`)
+    pub payload: String,
+    pub attempts: i64,
+}
+
+/// Latest analysis job for an event
+#[derive(Debug, Clone)]
+pub struct LlmJobStatus {
+    pub status: String,
+    pub attempts: i64,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+}
+
+/// A notification to queue for one sink
+#[derive(Debug, Clone)]
+pub struct NewNotification {
+    /// None for notifications not about a single event
+    pub event_id: Option<String>,
+    pub event_type: Option<String>,
+    /// alert, resolved, digest
+    pub kind: String,
+    pub route: String,
+    pub sink: String,
+    /// Serialized `notify::Notification`
+    pub payload: String,
+    /// Recorded for the delivery history but never sent
+    pub throttled: bool,
+}
+
+/// Due notification claimed by the delivery loop
+#[derive(Debug, Clone)]
+pub struct NotificationJob {
+    pub id: i64,
+    pub sink: String,
+    pub payload: String,
+    pub attempts: i64,
+}
+
+/// Delivery state of one notification, for `event show`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NotificationStatus {
+    pub kind: String,
+    pub route: String,
+    pub sink: String,
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub updated_at: i64,
+}
+
+/// A grant as stored; the token itself is only known to its holder
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GrantRecord {
+    pub id: String,
+    pub service_id: String,
+    pub scopes: Vec<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+    /// SHA-256 of the current token; refreshing replaces it
+    #[serde(skip)]
+    pub token_hash: String,
+}
+
+/// On-disk size and row counts, for quota enforcement and `storage.stats`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StorageStats {
+    pub db_bytes: u64,
+    pub wal_bytes: u64,
+    /// Space inside the database file that is allocated but unused
+    pub free_bytes: u64,
+    pub tables: BTreeMap<String, i64>,
+}
+
+impl StorageStats {
+    pub fn total_bytes(&self) -> u64 {
+        self.db_bytes + self.wal_bytes
+    }
+}
+
+/// Tables reported by `storage.stats`
+const STAT_TABLES: &[&str] = &[
+    "events", "incidents", "incident_events", "audits", "baselines", "silences",
+    "llm_jobs", "metric_samples", "chat_messages", "notifications", "services", "grants",
+];
+
+#[derive(Debug, Clone)]
+pub struct StoredBaseline {
+    pub metric: String,
+    pub bucket: i64,
+    pub mean: f64,
+    pub variance: f64,
+    pub samples: i64,
 }
 
 
@@ -23,68 +195,1068 @@ let url = format!("sqlite://{}", path);
 let pool = SqlitePool::connect(&url).await?;
 // run migrations / schema
 sqlx::query(include_str!("../../sql/schema.sql")).execute(&pool).await?;
-Ok(Self { pool })
+// columns added after the first release; CREATE TABLE IF NOT EXISTS won't add them
+add_column_if_missing(&pool, "events", "silenced_by", "TEXT").await?;
+add_column_if_missing(&pool, "events", "suggestion", "TEXT").await?;
+add_column_if_missing(&pool, "grants", "created_at", "INTEGER").await?;
+add_column_if_missing(&pool, "grants", "revoked_at", "INTEGER").await?;
+add_column_if_missing(&pool, "chat_messages", "owner", "TEXT").await?;
+Ok(Self { pool, path: path.to_string() })
 }
 
 
-// simple insert stub
-pub async fn insert_event(&self, id: &str, ts: i64, severity: &str, ty: &str, service: &str, snapshot: &[u8]) -> Result<()> {
-sqlx::query("INSERT INTO events(event_id, ts, severity, type, service_id, snapshot, status) VALUES (?, ?, ?, ?, ?, ?, 'open')")
-.bind(id)
-.bind(ts)
-.bind(severity)
-.bind(ty)
-.bind(service)
-.bind(snapshot)
+pub async fn insert_event(&self, event: &StoredEvent) -> Result<()> {
+sqlx::query("INSERT INTO events(event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+.bind(&event.event_id)
+.bind(event.ts)
+.bind(event.severity.as_str())
+.bind(event.type_.as_str())
+.bind(&event.service_id)
+.bind(&event.fingerprint)
+.bind(&event.snapshot)
+.bind(&event.status)
+.bind(&event.silenced_by)
+.bind(event.suggestion.as_ref().map(|s| s.to_string()))
 .execute(&self.pool).await?;
 Ok(())
 }
 
 pub async fn get_recent_events(&self, limit: i32) -> Result<Vec<StoredEvent>> {
-    let rows = sqlx::query_as::<_, (String, i64, String, String, String, Vec<u8>, String)>(
-        "SELECT event_id, ts, severity, type, service_id, snapshot, status FROM events ORDER BY ts DESC LIMIT ?"
+    let rows = sqlx::query_as::<_, EventRow>(
+        "SELECT event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion FROM events ORDER BY ts DESC LIMIT ?"
     )
     .bind(limit)
     .fetch_all(&self.pool)
     .await?;
     
-    Ok(rows.into_iter().map(|(event_id, ts, severity, type_, service_id, snapshot, status)| {
-        StoredEvent { event_id, ts, severity, type_, service_id, snapshot, status }
-    }).collect())
+    Ok(rows.into_iter().map(event_from_row).collect())
+}
+
+/// Events in `[from, to]`, newest first
+pub async fn get_events_between(&self, from: i64, to: i64, limit: i64) -> Result<Vec<StoredEvent>> {
+    let rows = sqlx::query_as::<_, EventRow>(
+        "SELECT event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion FROM events \
+         WHERE ts >= ? AND ts <= ? ORDER BY ts DESC LIMIT ?"
+    )
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(event_from_row).collect())
+}
+
+/// CRITICAL, unsilenced events since `since` with no suggestion and no job
+/// in flight: what analysis missed while the LLM was unavailable. Oldest first.
+pub async fn get_unanalyzed_critical_events(&self, since: i64) -> Result<Vec<StoredEvent>> {
+    let rows = sqlx::query_as::<_, EventRow>(
+        "SELECT event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion FROM events e \
+         WHERE ts >= ? AND severity = 'CRITICAL' AND silenced_by IS NULL AND suggestion IS NULL \
+         AND NOT EXISTS (SELECT 1 FROM llm_jobs j WHERE j.event_id = e.event_id AND j.status IN ('pending', 'running')) \
+         ORDER BY ts"
+    )
+    .bind(since)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(event_from_row).collect())
 }
 
 pub async fn get_event_by_id(&self, id: &str) -> Result<Option<StoredEvent>> {
-    let row = sqlx::query_as::<_, (String, i64, String, String, String, Vec<u8>, String)>(
-        "SELECT event_id, ts, severity, type, service_id, snapshot, status FROM events WHERE event_id = ?"
+    let row = sqlx::query_as::<_, EventRow>(
+        "SELECT event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion FROM events WHERE event_id = ?"
+    )
+    .bind(id)
+    .fetch_optional(&self.pool)
+    .await?;
+    
+    Ok(row.map(event_from_row))
+}
+
+/// Open event counts for every severity level, including levels with no events
+pub async fn get_event_counts(&self) -> Result<BTreeMap<Severity, i64>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT severity, COUNT(*) FROM events WHERE status = 'open' GROUP BY severity"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    
+    let mut counts: BTreeMap<Severity, i64> = Severity::ALL.iter().map(|s| (*s, 0)).collect();
+    for (severity, count) in rows {
+        if let Ok(severity) = severity.parse::<Severity>() {
+            *counts.entry(severity).or_default() += count;
+        }
+    }
+    Ok(counts)
+}
+
+/// Open events as (severity, type, count)
+pub async fn get_open_event_counts_by_type(&self) -> Result<Vec<(String, String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, String, i64)>(
+        "SELECT severity, type, COUNT(*) FROM events WHERE status = 'open' GROUP BY severity, type"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn load_baselines(&self) -> Result<Vec<StoredBaseline>> {
+    let rows = sqlx::query_as::<_, (String, i64, f64, f64, i64)>(
+        "SELECT metric, bucket, mean, variance, samples FROM baselines"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(metric, bucket, mean, variance, samples)| {
+        StoredBaseline { metric, bucket, mean, variance, samples }
+    }).collect())
+}
+
+/// Write several baseline buckets in one transaction
+pub async fn upsert_baselines(&self, baselines: &[StoredBaseline], updated_at: i64) -> Result<()> {
+    let mut tx = self.pool.begin().await?;
+    for baseline in baselines {
+        sqlx::query(
+            "INSERT INTO baselines(metric, bucket, mean, variance, samples, updated_at) VALUES (?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(metric, bucket) DO UPDATE SET mean = excluded.mean, variance = excluded.variance, \
+             samples = excluded.samples, updated_at = excluded.updated_at"
+        )
+        .bind(&baseline.metric)
+        .bind(baseline.bucket)
+        .bind(baseline.mean)
+        .bind(baseline.variance)
+        .bind(baseline.samples)
+        .bind(updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn upsert_incident(&self, incident: &StoredIncident) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO incidents(id, opened_at, updated_at, status, severity, entities, root_cause) VALUES (?, ?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(id) DO UPDATE SET opened_at = excluded.opened_at, updated_at = excluded.updated_at, \
+         status = excluded.status, severity = excluded.severity, entities = excluded.entities, root_cause = excluded.root_cause"
+    )
+    .bind(&incident.id)
+    .bind(incident.opened_at)
+    .bind(incident.updated_at)
+    .bind(&incident.status)
+    .bind(incident.severity.as_str())
+    .bind(serde_json::to_string(&incident.entities)?)
+    .bind(&incident.root_cause)
+    .execute(&self.pool)
+    .await?;
+    Ok(())
+}
+
+/// Replace the member list of an incident; `rank` 0 is the most likely root cause
+pub async fn set_incident_members(&self, incident_id: &str, members: &[(String, i64)]) -> Result<()> {
+    let mut tx = self.pool.begin().await?;
+    sqlx::query("DELETE FROM incident_events WHERE incident_id = ?")
+        .bind(incident_id)
+        .execute(&mut *tx)
+        .await?;
+    for (event_id, rank) in members {
+        sqlx::query("INSERT INTO incident_events(incident_id, event_id, rank) VALUES (?, ?, ?)")
+            .bind(incident_id)
+            .bind(event_id)
+            .bind(rank)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_recent_incidents(&self, limit: i32) -> Result<Vec<StoredIncident>> {
+    let rows = sqlx::query_as::<_, (String, i64, i64, String, String, String, Option<String>)>(
+        "SELECT id, opened_at, updated_at, status, severity, entities, root_cause FROM incidents ORDER BY updated_at DESC LIMIT ?"
+    )
+    .bind(limit)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(incident_from_row).collect())
+}
+
+/// Incidents that were open at any point in `[from, to]`
+pub async fn get_incidents_between(&self, from: i64, to: i64) -> Result<Vec<StoredIncident>> {
+    let rows = sqlx::query_as::<_, (String, i64, i64, String, String, String, Option<String>)>(
+        "SELECT id, opened_at, updated_at, status, severity, entities, root_cause FROM incidents \
+         WHERE opened_at <= ? AND updated_at >= ? ORDER BY opened_at"
+    )
+    .bind(to)
+    .bind(from)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(incident_from_row).collect())
+}
+
+pub async fn get_incident_by_id(&self, id: &str) -> Result<Option<StoredIncident>> {
+    let row = sqlx::query_as::<_, (String, i64, i64, String, String, String, Option<String>)>(
+        "SELECT id, opened_at, updated_at, status, severity, entities, root_cause FROM incidents WHERE id = ?"
     )
     .bind(id)
     .fetch_optional(&self.pool)
     .await?;
     
-    Ok(row.map(|(event_id, ts, severity, type_, service_id, snapshot, status)| {
-        StoredEvent { event_id, ts, severity, type_, service_id, snapshot, status }
+    Ok(row.map(incident_from_row))
+}
+
+pub async fn get_incident_members(&self, incident_id: &str) -> Result<Vec<StoredIncidentMember>> {
+    let rows = sqlx::query_as::<_, (String, i64, String, String)>(
+        "SELECT e.event_id, e.ts, e.severity, e.type FROM incident_events ie \
+         JOIN events e ON e.event_id = ie.event_id WHERE ie.incident_id = ? ORDER BY ie.rank"
+    )
+    .bind(incident_id)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(event_id, ts, severity, kind)| {
+        StoredIncidentMember { event_id, ts, severity: parse_severity(&severity), kind: EventKind::from(kind) }
+    }).collect())
+}
+
+pub async fn get_open_events(&self) -> Result<Vec<OpenEvent>> {
+    let rows = sqlx::query_as::<_, (String, i64, String, String, String)>(
+        "SELECT event_id, ts, severity, type, fingerprint FROM events WHERE status = 'open' AND fingerprint IS NOT NULL ORDER BY ts"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(event_id, ts, severity, kind, fingerprint)| {
+        OpenEvent { event_id, ts, severity: parse_severity(&severity), kind: EventKind::from(kind), fingerprint }
+    }).collect())
+}
+
+pub async fn set_event_suggestion(&self, id: &str, suggestion: &serde_json::Value) -> Result<()> {
+    sqlx::query("UPDATE events SET suggestion = ? WHERE event_id = ?")
+        .bind(suggestion.to_string())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn insert_metric_sample(&self, name: &str, ts: i64, value: f64) -> Result<()> {
+    sqlx::query("INSERT INTO metric_samples(name, ts, value) VALUES (?, ?, ?)")
+        .bind(name)
+        .bind(ts)
+        .bind(value)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// (ts, value) samples of one metric in `[from, to]`, oldest first
+pub async fn get_metric_samples(&self, name: &str, from: i64, to: i64) -> Result<Vec<(i64, f64)>> {
+    let rows = sqlx::query_as::<_, (i64, f64)>(
+        "SELECT ts, value FROM metric_samples WHERE name = ? AND ts >= ? AND ts <= ? ORDER BY ts"
+    )
+    .bind(name)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Min/avg/max per metric over `[from, to]`
+pub async fn get_metric_summaries(&self, from: i64, to: i64) -> Result<Vec<MetricSummary>> {
+    let rows = sqlx::query_as::<_, (String, f64, f64, f64, i64, i64, i64)>(
+        "SELECT name, MIN(value), AVG(value), MAX(value), COUNT(*), MIN(ts), MAX(ts) FROM metric_samples \
+         WHERE ts >= ? AND ts <= ? GROUP BY name ORDER BY name"
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(name, min, avg, max, samples, first_ts, last_ts)| {
+        MetricSummary { name, min, avg, max, samples, first_ts, last_ts }
+    }).collect())
+}
+
+pub async fn insert_chat_message(&self, session_id: &str, owner: &str, role: &str, content: &str, citations: Option<&serde_json::Value>) -> Result<()> {
+    sqlx::query("INSERT INTO chat_messages(session_id, owner, ts, role, content, citations) VALUES (?, ?, ?, ?, ?, ?)")
+        .bind(session_id)
+        .bind(owner)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(role)
+        .bind(content)
+        .bind(citations.map(|c| c.to_string()))
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// (role, content) of every turn in a chat session, oldest first
+pub async fn get_chat_messages(&self, session_id: &str) -> Result<Vec<(String, String)>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT role, content FROM chat_messages WHERE session_id = ? ORDER BY id"
+    )
+    .bind(session_id)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Who started a chat session, if it exists
+pub async fn get_chat_session_owner(&self, session_id: &str) -> Result<Option<String>> {
+    let owner = sqlx::query_as::<_, (Option<String>,)>(
+        "SELECT owner FROM chat_messages WHERE session_id = ? ORDER BY id LIMIT 1"
+    )
+    .bind(session_id)
+    .fetch_optional(&self.pool)
+    .await?;
+    Ok(owner.and_then(|(owner,)| owner))
+}
+
+/// Delete chat sessions with no message since `before`
+pub async fn delete_chat_sessions_before(&self, before: i64) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM chat_messages WHERE session_id IN \
+         (SELECT session_id FROM chat_messages GROUP BY session_id HAVING MAX(ts) < ?)"
+    )
+    .bind(before)
+    .execute(&self.pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn update_event_severity(&self, id: &str, severity: Severity) -> Result<()> {
+    sqlx::query("UPDATE events SET severity = ? WHERE event_id = ?")
+        .bind(severity.as_str())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn resolve_events(&self, ids: &[String]) -> Result<()> {
+    let mut tx = self.pool.begin().await?;
+    for id in ids {
+        sqlx::query("UPDATE events SET status = 'resolved' WHERE event_id = ? AND status = 'open'")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Mark open incidents resolved once none of their events are open; returns how many changed
+pub async fn resolve_settled_incidents(&self) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE incidents SET status = 'resolved' WHERE status = 'open' AND NOT EXISTS ( \
+         SELECT 1 FROM incident_events ie JOIN events e ON e.event_id = ie.event_id \
+         WHERE ie.incident_id = incidents.id AND e.status = 'open')"
+    )
+    .execute(&self.pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn insert_audit(&self, kind: &str, payload: &serde_json::Value) -> Result<()> {
+    sqlx::query("INSERT INTO audits(ts, kind, payload) VALUES (?, ?, ?)")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(kind)
+        .bind(serde_json::to_vec(payload)?)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Like `insert_audit`, returning the new row's id so it can be updated later
+pub async fn insert_audit_returning_id(&self, kind: &str, payload: &serde_json::Value) -> Result<i64> {
+    let result = sqlx::query("INSERT INTO audits(ts, kind, payload) VALUES (?, ?, ?)")
+        .bind(chrono::Utc::now().timestamp())
+        .bind(kind)
+        .bind(serde_json::to_vec(payload)?)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Replace the payload of an existing audit record, keeping its timestamp
+pub async fn update_audit_payload(&self, id: i64, payload: &serde_json::Value) -> Result<()> {
+    sqlx::query("UPDATE audits SET payload = ? WHERE id = ?")
+        .bind(serde_json::to_vec(payload)?)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Audit records of one kind since `since`, newest first, as (ts, payload)
+pub async fn get_audits(&self, kind: &str, since: i64, limit: i64) -> Result<Vec<(i64, serde_json::Value)>> {
+    let rows = sqlx::query_as::<_, (i64, Vec<u8>)>(
+        "SELECT ts, payload FROM audits WHERE kind = ? AND ts >= ? ORDER BY ts DESC, id DESC LIMIT ?"
+    )
+    .bind(kind)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    Ok(rows.into_iter()
+        .map(|(ts, payload)| (ts, serde_json::from_slice(&payload).unwrap_or(serde_json::Value::Null)))
+        .collect())
+}
+
+pub async fn delete_audits_before(&self, kind: &str, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM audits WHERE kind = ? AND ts < ?")
+        .bind(kind)
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete audits of every kind but `except` recorded before `before`
+pub async fn delete_audits_except_before(&self, except: &str, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM audits WHERE kind != ? AND ts < ?")
+        .bind(except)
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete sent and failed notifications created before `before`; pending ones are kept
+pub async fn delete_finished_notifications_before(&self, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM notifications WHERE status IN ('sent', 'failed') AND created_at < ?")
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn insert_silence(&self, silence: &Silence) -> Result<()> {
+    sqlx::query("INSERT INTO silences(id, matchers, starts_at, ends_at, created_by, comment, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)")
+        .bind(&silence.id)
+        .bind(serde_json::to_string(&silence.matchers)?)
+        .bind(silence.starts_at)
+        .bind(silence.ends_at)
+        .bind(&silence.created_by)
+        .bind(&silence.comment)
+        .bind(silence.created_at)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Silences that have not ended yet (active or scheduled); `include_expired` returns all
+pub async fn get_silences(&self, include_expired: bool, now: i64) -> Result<Vec<Silence>> {
+    let rows = sqlx::query_as::<_, (String, String, i64, i64, String, String, i64)>(
+        "SELECT id, matchers, starts_at, ends_at, created_by, comment, created_at FROM silences \
+         WHERE ? OR ends_at > ? ORDER BY starts_at DESC"
+    )
+    .bind(include_expired)
+    .bind(now)
+    .fetch_all(&self.pool)
+    .await?;
+    
+    // An empty matcher set matches everything, so a row whose matchers
+    // can't be read is left out rather than defaulted
+    Ok(rows.into_iter().filter_map(|(id, matchers, starts_at, ends_at, created_by, comment, created_at)| {
+        let matchers = match serde_json::from_str::<SilenceMatchers>(&matchers) {
+            Ok(matchers) => matchers,
+            Err(e) => {
+                error!("Ignoring silence {}: unreadable matchers: {}", id, e);
+                return None;
+            }
+        };
+        Some(Silence {
+            id,
+            matchers,
+            starts_at,
+            ends_at,
+            created_by,
+            comment,
+            created_at,
+        })
+    }).collect())
+}
+
+pub async fn enqueue_llm_job(&self, event_id: &str, payload: &str, now: i64) -> Result<()> {
+    sqlx::query("INSERT INTO llm_jobs(event_id, payload, status, attempts, next_attempt_at, created_at, updated_at) VALUES (?, ?, 'pending', 0, ?, ?, ?)")
+        .bind(event_id)
+        .bind(payload)
+        .bind(now)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Mark up to `limit` due pending jobs running and return them, oldest first
+pub async fn claim_llm_jobs(&self, limit: usize, now: i64) -> Result<Vec<LlmJob>> {
+    let mut rows = sqlx::query_as::<_, (i64, String, String, i64, i64)>(
+        "UPDATE llm_jobs SET status = 'running', attempts = attempts + 1, updated_at = ? \
+         WHERE id IN (SELECT id FROM llm_jobs WHERE status = 'pending' AND next_attempt_at <= ? ORDER BY next_attempt_at, id LIMIT ?) \
+         RETURNING id, event_id, payload, attempts, next_attempt_at"
+    )
+    .bind(now)
+    .bind(now)
+    .bind(limit as i64)
+    .fetch_all(&self.pool)
+    .await?;
+    // RETURNING order is unspecified
+    rows.sort_by_key(|(id, _, _, _, next_attempt_at)| (*next_attempt_at, *id));
+    
+    Ok(rows.into_iter().map(|(id, event_id, payload, attempts, _)| {
+        LlmJob { id, event_id, payload, attempts }
+    }).collect())
+}
+
+/// Store the suggestion on its event and close the job in one transaction
+pub async fn complete_llm_job(&self, job_id: i64, event_id: &str, suggestion: &serde_json::Value, now: i64) -> Result<()> {
+    let mut tx = self.pool.begin().await?;
+    sqlx::query("UPDATE events SET suggestion = ? WHERE event_id = ?")
+        .bind(suggestion.to_string())
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE llm_jobs SET status = 'done', last_error = NULL, updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(job_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Record a failed attempt; the job is retried at `retry_at`, or marked failed when `None`
+pub async fn fail_llm_job(&self, job_id: i64, error: &str, retry_at: Option<i64>, now: i64) -> Result<()> {
+    sqlx::query("UPDATE llm_jobs SET status = ?, next_attempt_at = COALESCE(?, next_attempt_at), last_error = ?, updated_at = ? WHERE id = ?")
+        .bind(if retry_at.is_some() { "pending" } else { "failed" })
+        .bind(retry_at)
+        .bind(error)
+        .bind(now)
+        .bind(job_id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Jobs left running by a previous agent process go back to pending; returns how many
+pub async fn requeue_running_llm_jobs(&self, now: i64) -> Result<u64> {
+    let result = sqlx::query("UPDATE llm_jobs SET status = 'pending', next_attempt_at = ?, updated_at = ? WHERE status = 'running'")
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Job counts per status (pending/running/done/failed)
+pub async fn get_llm_job_counts(&self) -> Result<BTreeMap<String, i64>> {
+    let rows = sqlx::query_as::<_, (String, i64)>("SELECT status, COUNT(*) FROM llm_jobs GROUP BY status")
+        .fetch_all(&self.pool)
+        .await?;
+    
+    let mut counts: BTreeMap<String, i64> = ["pending", "running", "done", "failed"].iter()
+        .map(|s| (s.to_string(), 0))
+        .collect();
+    counts.extend(rows);
+    Ok(counts)
+}
+
+pub async fn get_llm_job_for_event(&self, event_id: &str) -> Result<Option<LlmJobStatus>> {
+    let row = sqlx::query_as::<_, (String, i64, i64, Option<String>)>(
+        "SELECT status, attempts, next_attempt_at, last_error FROM llm_jobs WHERE event_id = ? ORDER BY id DESC LIMIT 1"
+    )
+    .bind(event_id)
+    .fetch_optional(&self.pool)
+    .await?;
+    
+    Ok(row.map(|(status, attempts, next_attempt_at, last_error)| {
+        LlmJobStatus { status, attempts, next_attempt_at, last_error }
     }))
 }
 
-pub async fn get_event_counts(&self) -> Result<(i64, i64, i64)> {
-    let critical: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM events WHERE severity = 'CRITICAL' AND status = 'open'"
+/// End a silence now; returns false if it doesn't exist or already ended
+pub async fn expire_silence(&self, id: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query("UPDATE silences SET ends_at = ? WHERE id = ? AND ends_at > ?")
+        .bind(now)
+        .bind(id)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_storage_stats(&self) -> Result<StorageStats> {
+    let file_size = |path: &str| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let (page_size,) = sqlx::query_as::<_, (i64,)>("PRAGMA page_size").fetch_one(&self.pool).await?;
+    let (free_pages,) = sqlx::query_as::<_, (i64,)>("PRAGMA freelist_count").fetch_one(&self.pool).await?;
+    
+    let mut tables = BTreeMap::new();
+    for table in STAT_TABLES {
+        let (count,) = sqlx::query_as::<_, (i64,)>(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&self.pool)
+            .await?;
+        tables.insert(table.to_string(), count);
+    }
+    
+    Ok(StorageStats {
+        db_bytes: file_size(&self.path),
+        wal_bytes: file_size(&format!("{}-wal", self.path)),
+        free_bytes: (page_size * free_pages) as u64,
+        tables,
+    })
+}
+
+/// Switch the database to incremental auto-vacuum so freed pages can be
+/// returned to the filesystem; needs one full VACUUM the first time
+pub async fn enable_incremental_vacuum(&self) -> Result<bool> {
+    let (mode,) = sqlx::query_as::<_, (i64,)>("PRAGMA auto_vacuum").fetch_one(&self.pool).await?;
+    if mode == 2 {
+        return Ok(false);
+    }
+    // The mode only sticks if VACUUM runs on the connection that set it
+    let mut conn = self.pool.acquire().await?;
+    sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&mut *conn).await?;
+    sqlx::query("VACUUM").execute(&mut *conn).await?;
+    Ok(true)
+}
+
+/// Release free pages, then checkpoint so the shrink reaches the database
+/// file and the WAL is truncated
+pub async fn compact(&self) -> Result<()> {
+    // Frees one page per result row, so the statement has to be stepped to the end
+    sqlx::query("PRAGMA incremental_vacuum").fetch_all(&self.pool).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&self.pool).await?;
+    Ok(())
+}
+
+/// Ask SQLite to release page cache; applies to whichever pooled connection runs it
+pub async fn shrink_memory(&self) -> Result<()> {
+    sqlx::query("PRAGMA shrink_memory").execute(&self.pool).await?;
+    Ok(())
+}
+
+/// Delete events of `severity` recorded before `before`; returns how many
+pub async fn delete_events_before(&self, severity: Severity, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM events WHERE severity = ? AND ts < ?")
+        .bind(severity.as_str())
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete up to `limit` of the oldest resolved events
+pub async fn delete_oldest_resolved_events(&self, limit: i64) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM events WHERE event_id IN ( \
+         SELECT event_id FROM events WHERE status = 'resolved' ORDER BY ts LIMIT ?)"
     )
+    .bind(limit)
+    .execute(&self.pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn delete_metric_samples_before(&self, before: i64) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM metric_samples WHERE ts < ?")
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Remove rows that only made sense for events that have been deleted
+pub async fn delete_orphans(&self) -> Result<()> {
+    sqlx::query("DELETE FROM incident_events WHERE event_id NOT IN (SELECT event_id FROM events)")
+        .execute(&self.pool)
+        .await?;
+    sqlx::query("DELETE FROM incidents WHERE id NOT IN (SELECT incident_id FROM incident_events)")
+        .execute(&self.pool)
+        .await?;
+    sqlx::query("DELETE FROM llm_jobs WHERE event_id NOT IN (SELECT event_id FROM events)")
+        .execute(&self.pool)
+        .await?;
+    sqlx::query("DELETE FROM notifications WHERE event_id IS NOT NULL AND event_id NOT IN (SELECT event_id FROM events)")
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn enqueue_notification(&self, notification: &NewNotification, now: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO notifications(event_id, event_type, kind, route, sink, payload, status, attempts, next_attempt_at, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?)"
+    )
+    .bind(&notification.event_id)
+    .bind(&notification.event_type)
+    .bind(&notification.kind)
+    .bind(&notification.route)
+    .bind(&notification.sink)
+    .bind(&notification.payload)
+    .bind(if notification.throttled { "throttled" } else { "pending" })
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(&self.pool)
+    .await?;
+    Ok(())
+}
+
+/// When `route` last queued an alert for `event_type`, throttled ones excluded
+pub async fn last_route_notification(&self, route: &str, event_type: &str) -> Result<Option<i64>> {
+    let (ts,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MAX(created_at) FROM notifications WHERE kind = 'alert' AND route = ? AND event_type = ? AND status != 'throttled'"
+    )
+    .bind(route)
+    .bind(event_type)
     .fetch_one(&self.pool)
     .await?;
+    Ok(ts)
+}
+
+/// Mark up to `limit` due pending notifications sending and return them, oldest first.
+/// One statement, so it never has to upgrade a read transaction under contention.
+pub async fn claim_notifications(&self, limit: usize, now: i64) -> Result<Vec<NotificationJob>> {
+    let mut rows = sqlx::query_as::<_, (i64, String, String, i64)>(
+        "UPDATE notifications SET status = 'sending', attempts = attempts + 1, updated_at = ? \
+         WHERE id IN (SELECT id FROM notifications WHERE status = 'pending' AND next_attempt_at <= ? ORDER BY next_attempt_at, id LIMIT ?) \
+         RETURNING id, sink, payload, attempts"
+    )
+    .bind(now)
+    .bind(now)
+    .bind(limit as i64)
+    .fetch_all(&self.pool)
+    .await?;
+    rows.sort_by_key(|(id, ..)| *id);
     
-    let warning: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM events WHERE severity = 'WARNING' AND status = 'open'"
+    Ok(rows.into_iter().map(|(id, sink, payload, attempts)| {
+        NotificationJob { id, sink, payload, attempts }
+    }).collect())
+}
+
+pub async fn complete_notification(&self, id: i64, now: i64) -> Result<()> {
+    sqlx::query("UPDATE notifications SET status = 'sent', last_error = NULL, updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Record a failed attempt; the notification is retried at `retry_at`, or marked failed when `None`
+pub async fn fail_notification(&self, id: i64, error: &str, retry_at: Option<i64>, now: i64) -> Result<()> {
+    sqlx::query("UPDATE notifications SET status = ?, next_attempt_at = COALESCE(?, next_attempt_at), last_error = ?, updated_at = ? WHERE id = ?")
+        .bind(if retry_at.is_some() { "pending" } else { "failed" })
+        .bind(retry_at)
+        .bind(error)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+/// Notifications left sending by a previous agent process go back to pending; returns how many
+pub async fn requeue_sending_notifications(&self, now: i64) -> Result<u64> {
+    let result = sqlx::query("UPDATE notifications SET status = 'pending', next_attempt_at = ?, updated_at = ? WHERE status = 'sending'")
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_notifications_for_event(&self, event_id: &str) -> Result<Vec<NotificationStatus>> {
+    let rows = sqlx::query_as::<_, (String, String, String, String, i64, Option<String>, i64)>(
+        "SELECT kind, route, sink, status, attempts, last_error, updated_at FROM notifications WHERE event_id = ? ORDER BY id"
     )
-    .fetch_one(&self.pool)
+    .bind(event_id)
+    .fetch_all(&self.pool)
     .await?;
     
-    let info: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM events WHERE severity = 'INFO' AND status = 'open'"
+    Ok(rows.into_iter().map(|(kind, route, sink, status, attempts, last_error, updated_at)| {
+        NotificationStatus { kind, route, sink, status, attempts, last_error, updated_at }
+    }).collect())
+}
+
+/// When the digest `name` was last queued
+pub async fn last_digest_at(&self, name: &str) -> Result<Option<i64>> {
+    let (ts,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MAX(created_at) FROM notifications WHERE kind = 'digest' AND route = ?"
     )
+    .bind(name)
     .fetch_one(&self.pool)
     .await?;
-    
-    Ok((critical.0, warning.0, info.0))
+    Ok(ts)
+}
+
+/// Alerts delivered on one of `routes` whose event has since been resolved
+/// and that have no resolved follow-up yet: (event_id, route, sink, alert payload)
+pub async fn get_pending_resolved_followups(&self, routes: &[String]) -> Result<Vec<(String, String, String, String)>> {
+    if routes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; routes.len()].join(", ");
+    let sql = format!(
+        "SELECT n.event_id, n.route, n.sink, n.payload FROM notifications n JOIN events e ON e.event_id = n.event_id \
+         WHERE n.kind = 'alert' AND n.status = 'sent' AND e.status = 'resolved' AND n.route IN ({}) \
+         AND NOT EXISTS (SELECT 1 FROM notifications r WHERE r.kind = 'resolved' AND r.event_id = n.event_id AND r.route = n.route AND r.sink = n.sink) \
+         ORDER BY n.id",
+        placeholders
+    );
+    let mut query = sqlx::query_as::<_, (String, String, String, String)>(&sql);
+    for route in routes {
+        query = query.bind(route);
+    }
+    Ok(query.fetch_all(&self.pool).await?)
+}
+
+/// (severity, type, count) of events first seen in (from, to]
+pub async fn get_new_event_counts(&self, from: i64, to: i64) -> Result<Vec<(String, String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, String, i64)>(
+        "SELECT severity, type, COUNT(*) FROM events WHERE ts > ? AND ts <= ? GROUP BY severity, type"
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+/// (severity, type, count) of events still open that were first seen at or before `before`
+pub async fn get_open_event_counts_before(&self, before: i64) -> Result<Vec<(String, String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, String, i64)>(
+        "SELECT severity, type, COUNT(*) FROM events WHERE status = 'open' AND ts <= ? GROUP BY severity, type"
+    )
+    .bind(before)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Insert or update a registered service, keeping any discovery data
+pub async fn upsert_service(&self, service: &Service, now: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO services(id, name, discovery, default_scopes, requested_scopes, updated_at) VALUES (?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, discovery = COALESCE(excluded.discovery, discovery), \
+         default_scopes = excluded.default_scopes, requested_scopes = excluded.requested_scopes, updated_at = excluded.updated_at"
+    )
+    .bind(&service.id)
+    .bind(&service.name)
+    .bind(service.discovery.as_ref().map(serde_json::to_string).transpose()?)
+    .bind(serde_json::to_string(&service.default_scopes)?)
+    .bind(serde_json::to_string(&service.requested_scopes)?)
+    .bind(now)
+    .execute(&self.pool)
+    .await?;
+    Ok(())
+}
+
+/// Record what discovery found; names and scopes of registered services are kept
+pub async fn upsert_discovered_service(&self, id: &str, name: &str, discovery: &ServiceDiscovery, now: i64) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO services(id, name, discovery, default_scopes, requested_scopes, updated_at) VALUES (?, ?, ?, '[]', '[]', ?) \
+         ON CONFLICT(id) DO UPDATE SET discovery = excluded.discovery, updated_at = excluded.updated_at"
+    )
+    .bind(id)
+    .bind(name)
+    .bind(serde_json::to_string(discovery)?)
+    .bind(now)
+    .execute(&self.pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_services(&self) -> Result<Vec<Service>> {
+    let rows = sqlx::query_as::<_, ServiceRow>(
+        "SELECT id, name, discovery, default_scopes, requested_scopes FROM services ORDER BY id"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows.into_iter().map(service_from_row).collect())
+}
+
+pub async fn get_service(&self, id: &str) -> Result<Option<Service>> {
+    let row = sqlx::query_as::<_, ServiceRow>(
+        "SELECT id, name, discovery, default_scopes, requested_scopes FROM services WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(&self.pool)
+    .await?;
+    Ok(row.map(service_from_row))
+}
+
+/// (service_id, count) of open events per service, including "system"
+pub async fn get_open_event_counts_by_service(&self) -> Result<Vec<(String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT service_id, COUNT(*) FROM events WHERE status = 'open' GROUP BY service_id"
+    )
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+/// (severity, count) of one service's open events
+pub async fn get_service_open_event_counts(&self, service_id: &str) -> Result<Vec<(String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT severity, COUNT(*) FROM events WHERE status = 'open' AND service_id = ? GROUP BY severity"
+    )
+    .bind(service_id)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_recent_events_for_service(&self, service_id: &str, limit: i32) -> Result<Vec<StoredEvent>> {
+    let rows = sqlx::query_as::<_, EventRow>(
+        "SELECT event_id, ts, severity, type, service_id, fingerprint, snapshot, status, silenced_by, suggestion FROM events \
+         WHERE service_id = ? ORDER BY ts DESC LIMIT ?"
+    )
+    .bind(service_id)
+    .bind(limit)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows.into_iter().map(event_from_row).collect())
+}
+
+pub async fn insert_grant(&self, grant: &GrantRecord) -> Result<()> {
+    sqlx::query("INSERT INTO grants(id, service_id, scopes, expires_at, token, created_at, revoked_at) VALUES (?, ?, ?, ?, ?, ?, ?)")
+        .bind(&grant.id)
+        .bind(&grant.service_id)
+        .bind(serde_json::to_string(&grant.scopes)?)
+        .bind(grant.expires_at)
+        .bind(&grant.token_hash)
+        .bind(grant.created_at)
+        .bind(grant.revoked_at)
+        .execute(&self.pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_grant(&self, id: &str) -> Result<Option<GrantRecord>> {
+    let row = sqlx::query_as::<_, GrantRow>(
+        "SELECT id, service_id, scopes, created_at, expires_at, revoked_at, token FROM grants WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(&self.pool)
+    .await?;
+    Ok(row.map(grant_from_row))
+}
+
+/// Newest first; revoked and expired grants only with `include_inactive`
+pub async fn get_grants(&self, include_inactive: bool, now: i64) -> Result<Vec<GrantRecord>> {
+    let rows = sqlx::query_as::<_, GrantRow>(
+        "SELECT id, service_id, scopes, created_at, expires_at, revoked_at, token FROM grants \
+         WHERE ? OR (revoked_at IS NULL AND expires_at > ?) ORDER BY created_at DESC"
+    )
+    .bind(include_inactive)
+    .bind(now)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(rows.into_iter().map(grant_from_row).collect())
+}
+
+pub async fn revoke_grant(&self, id: &str, now: i64) -> Result<bool> {
+    let result = sqlx::query("UPDATE grants SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Replace the token of a grant that hasn't been revoked; the old one stops verifying
+pub async fn refresh_grant(&self, id: &str, expires_at: i64, token_hash: &str) -> Result<bool> {
+    let result = sqlx::query("UPDATE grants SET expires_at = ?, token = ? WHERE id = ? AND revoked_at IS NULL")
+        .bind(expires_at)
+        .bind(token_hash)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+}
+
+async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, decl: &str) -> Result<()> {
+    let columns = sqlx::query_as::<_, (String,)>(&format!("SELECT name FROM pragma_table_info('{}')", table))
+        .fetch_all(pool)
+        .await?;
+    if !columns.iter().any(|(name,)| name == column) {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Rows written before severities were typed may hold unexpected strings
+fn parse_severity(s: &str) -> Severity {
+    s.parse().unwrap_or(Severity::Info)
+}
+
+type ServiceRow = (String, String, Option<String>, String, String);
+
+fn service_from_row(row: ServiceRow) -> Service {
+    let (id, name, discovery, default_scopes, requested_scopes) = row;
+    Service {
+        id,
+        name,
+        discovery: discovery.and_then(|d| serde_json::from_str(&d).ok()),
+        default_scopes: serde_json::from_str(&default_scopes).unwrap_or_default(),
+        requested_scopes: serde_json::from_str(&requested_scopes).unwrap_or_default(),
+    }
+}
+
+type GrantRow = (String, String, String, i64, i64, Option<i64>, String);
+
+fn grant_from_row(row: GrantRow) -> GrantRecord {
+    let (id, service_id, scopes, created_at, expires_at, revoked_at, token_hash) = row;
+    GrantRecord {
+        id,
+        service_id,
+        scopes: serde_json::from_str(&scopes).unwrap_or_default(),
+        created_at,
+        expires_at,
+        revoked_at,
+        token_hash,
+    }
+}
+
+type EventRow = (String, i64, String, String, String, Option<String>, Vec<u8>, String, Option<String>, Option<String>);
+
+fn event_from_row(row: EventRow) -> StoredEvent {
+    let (event_id, ts, severity, type_, service_id, fingerprint, snapshot, status, silenced_by, suggestion) = row;
+    StoredEvent {
+        event_id,
+        ts,
+        severity: parse_severity(&severity),
+        type_: EventKind::from(type_),
+        service_id,
+        fingerprint,
+        snapshot,
+        status,
+        silenced_by,
+        suggestion: suggestion.and_then(|s| serde_json::from_str(&s).ok()),
+    }
+}
+
+fn incident_from_row(row: (String, i64, i64, String, String, String, Option<String>)) -> StoredIncident {
+    let (id, opened_at, updated_at, status, severity, entities, root_cause) = row;
+    StoredIncident {
+        id,
+        opened_at,
+        updated_at,
+        status,
+        severity: parse_severity(&severity),
+        entities: serde_json::from_str(&entities).unwrap_or_default(),
+        root_cause,
+    }
 }
-}
\ No newline at end of file
@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+use tokio::sync::broadcast;
+use tokio::time::{interval, Duration};
+use common::{EscalationConfig, Event, EventKind, Severity};
+use crate::collectors::{CPU_METRIC, CPU_WARNING_PERCENT, MEMORY_METRIC, MEMORY_WARNING_PERCENT};
+use crate::llm::LlmQueue;
+use crate::storage::{Storage, OpenEvent};
+use serde_json::json;
+use log::{info, warn, error};
+
+/// A metric only counts as healthy if its samples reach this close to both
+/// ends of the period, so a stalled or paused collector doesn't look like a
+/// recovery
+const MAX_SAMPLE_GAP_SECS: i64 = 300;
+
+/// Periodically escalates long-running WARNING events and resolves events
+/// whose condition has cleared. Escalated events go through the same LLM
+/// queue and stored-event broadcast as new CRITICAL events, so they are
+/// analyzed and notified.
+pub async fn start_sweeper(
+    storage: Storage,
+    config: EscalationConfig,
+    llm_queue: Option<LlmQueue>,
+    stored_tx: broadcast::Sender<Event>,
+) -> anyhow::Result<()> {
+    if !config.enabled {
+        info!("Severity escalation/decay disabled");
+        return Ok(());
+    }
+
+    info!("Starting severity sweeper ({}s interval)", config.sweep_interval_secs);
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(config.sweep_interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = sweep(&storage, &config, llm_queue.as_ref(), &stored_tx).await {
+                error!("Severity sweep failed: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn sweep(
+    storage: &Storage,
+    config: &EscalationConfig,
+    llm_queue: Option<&LlmQueue>,
+    stored_tx: &broadcast::Sender<Event>,
+) -> anyhow::Result<()> {
+    let now = chrono::Utc::now().timestamp();
+
+    // Group open events by condition; events come back oldest first
+    let mut groups: BTreeMap<String, Vec<OpenEvent>> = BTreeMap::new();
+    for event in storage.get_open_events().await? {
+        groups.entry(event.fingerprint.clone()).or_default().push(event);
+    }
+
+    for (fingerprint, events) in &groups {
+        // Collectors re-emit while a condition persists, so the newest event
+        // marks the last time the condition was reported.
+        let last_seen = events.last().map(|e| e.ts).unwrap_or(now);
+        let healthy_secs = now - last_seen;
+
+        let resolve_after_secs = config.resolve_after_healthy_mins as i64 * 60;
+        if resolve_after_secs > 0 && healthy_secs >= resolve_after_secs {
+            // Threshold and anomaly events are checked against their metric's
+            // samples; other events have no metric, so going without a new
+            // event is taken as healthy
+            let range = match events.last() {
+                Some(newest) => storage.get_event_by_id(&newest.event_id).await?
+                    .and_then(|e| healthy_range(&e.to_event())),
+                None => None,
+            };
+            let healthy = match &range {
+                Some((metric, low, high)) => metric_healthy(storage, metric, (*low, *high), resolve_after_secs, now).await?,
+                None => true,
+            };
+            if healthy {
+                let ids: Vec<String> = events.iter().map(|e| e.event_id.clone()).collect();
+                storage.resolve_events(&ids).await?;
+                storage.insert_audit("event.resolved", &json!({
+                    "fingerprint": fingerprint,
+                    "event_ids": ids,
+                    "reason": "healthy",
+                    "healthy_secs": healthy_secs,
+                    "metric": range.map(|(metric, ..)| metric),
+                })).await?;
+                info!("Auto-resolved {} events for {} (healthy for {}s)", ids.len(), fingerprint, healthy_secs);
+                continue;
+            }
+        }
+
+        let Some(escalation) = escalation(events, config, now) else { continue };
+        let event = escalation.event;
+        storage.update_event_severity(&event.event_id, Severity::Critical).await?;
+        storage.insert_audit("event.escalated", &json!({
+            "event_id": event.event_id,
+            "fingerprint": fingerprint,
+            "from": Severity::Warning,
+            "to": Severity::Critical,
+            "reason": escalation.reason,
+            "age_secs": escalation.age_secs,
+            "recurrences": escalation.recurrences,
+        })).await?;
+        warn!("Escalated {} to CRITICAL ({})", event.event_id, escalation.reason);
+
+        let Some(escalated) = storage.get_event_by_id(&event.event_id).await? else { continue };
+        let escalated = escalated.to_event();
+        if escalated.silenced_by.is_none() {
+            if let Some(queue) = llm_queue {
+                if let Err(e) = queue.enqueue(&escalated).await {
+                    error!("Failed to queue LLM analysis for {}: {}", escalated.event_id, e);
+                }
+            }
+        }
+        let _ = stored_tx.send(escalated);
+    }
+
+    let resolved = storage.resolve_settled_incidents().await?;
+    if resolved > 0 {
+        info!("Resolved {} incidents with no open events", resolved);
+    }
+
+    Ok(())
+}
+
+struct Escalation<'a> {
+    /// The newest open event of the condition
+    event: &'a OpenEvent,
+    reason: &'static str,
+    /// Since the condition was first reported
+    age_secs: i64,
+    recurrences: u64,
+}
+
+/// Whether a condition's open events (oldest first) call for escalation.
+/// A condition is escalated once, through its newest event: a group that
+/// already holds a CRITICAL event is left alone, so a condition that keeps
+/// recurring doesn't escalate again on every sweep. The self-monitor re-raises
+/// its events while the agent stays degraded, so for those repeats are
+/// reminders and only age escalates.
+fn escalation<'a>(events: &'a [OpenEvent], config: &EscalationConfig, now: i64) -> Option<Escalation<'a>> {
+    if events.iter().any(|e| e.severity >= Severity::Critical) {
+        return None;
+    }
+    let warnings: Vec<&OpenEvent> = events.iter().filter(|e| e.severity == Severity::Warning).collect();
+    let (first, newest) = (warnings.first()?, warnings.last()?);
+    let age_secs = now - first.ts;
+    let recurrences = (warnings.len() - 1) as u64;
+
+    let reason = if config.escalate_after_mins > 0 && age_secs >= config.escalate_after_mins as i64 * 60 {
+        "open_too_long"
+    } else if config.escalate_after_recurrences > 0
+        && recurrences >= config.escalate_after_recurrences
+        && !matches!(first.kind, EventKind::AgentMemoryHigh | EventKind::AgentDegraded)
+    {
+        "recurring"
+    } else {
+        return None;
+    };
+    Some(Escalation { event: newest, reason, age_secs, recurrences })
+}
+
+/// Whether `metric` was sampled throughout the last `secs` and every sample
+/// was within `range`
+async fn metric_healthy(storage: &Storage, metric: &str, range: (f64, f64), secs: i64, now: i64) -> anyhow::Result<bool> {
+    let samples = storage.get_metric_samples(metric, now - secs, now).await?;
+    let (Some((first, _)), Some((last, _))) = (samples.first(), samples.last()) else { return Ok(false) };
+    let covered = first - (now - secs) <= MAX_SAMPLE_GAP_SECS && now - last <= MAX_SAMPLE_GAP_SECS;
+    Ok(covered && samples.iter().all(|(_, value)| (range.0..=range.1).contains(value)))
+}
+
+/// The metric behind an event and the range it counts as healthy in
+fn healthy_range(event: &Event) -> Option<(String, f64, f64)> {
+    match event.r#type {
+        EventKind::CpuHigh => Some((CPU_METRIC.to_string(), f64::NEG_INFINITY, CPU_WARNING_PERCENT as f64)),
+        EventKind::MemoryHigh => Some((MEMORY_METRIC.to_string(), f64::NEG_INFINITY, MEMORY_WARNING_PERCENT as f64)),
+        EventKind::Anomaly => {
+            let range = event.evidence["expected_range"].as_array()?;
+            Some((event.entity["metric"].as_str()?.to_string(), range.first()?.as_f64()?, range.get(1)?.as_f64()?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredEvent;
+
+    async fn test_storage(name: &str) -> Storage {
+        let path = std::env::temp_dir().join(format!("sia-sweeper-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        std::fs::File::create(&path).unwrap();
+        Storage::new(path.to_str().unwrap()).await.unwrap()
+    }
+
+    /// An open cpu_high WARNING last reported `age_secs` ago, with a
+    /// `cpu.usage_percent` sample every 30s from `samples` (seconds ago, value)
+    async fn cpu_condition(name: &str, age_secs: i64, samples: &[(i64, f64)]) -> (Storage, i64) {
+        let storage = test_storage(name).await;
+        let now = chrono::Utc::now().timestamp();
+        storage.insert_event(&StoredEvent {
+            event_id: "cpu_1".to_string(),
+            ts: now - age_secs,
+            severity: Severity::Warning,
+            type_: EventKind::CpuHigh,
+            service_id: "system".to_string(),
+            fingerprint: Some("cpu_high:system_cpu".to_string()),
+            snapshot: serde_json::to_vec(&json!({"entity": {"type": "system_cpu"}, "evidence": {"threshold": 80.0}})).unwrap(),
+            status: "open".to_string(),
+            silenced_by: None,
+            suggestion: None,
+        }).await.unwrap();
+        for (ago, value) in samples {
+            storage.insert_metric_sample("cpu.usage_percent", now - ago, *value).await.unwrap();
+        }
+        (storage, now)
+    }
+
+    async fn sweep_status(storage: &Storage) -> String {
+        let config = EscalationConfig { resolve_after_healthy_mins: 10, ..Default::default() };
+        sweep(storage, &config, None, &broadcast::channel(4).0).await.unwrap();
+        storage.get_event_by_id("cpu_1").await.unwrap().unwrap().status
+    }
+
+    fn every_30s(from_ago: i64, to_ago: i64, value: f64) -> Vec<(i64, f64)> {
+        (to_ago..=from_ago).step_by(30).map(|ago| (ago, value)).collect()
+    }
+
+    #[tokio::test]
+    async fn resolves_after_healthy_minutes() {
+        let (storage, _) = cpu_condition("healthy", 11 * 60, &every_30s(10 * 60, 0, 12.0)).await;
+        assert_eq!(sweep_status(&storage).await, "resolved");
+    }
+
+    #[tokio::test]
+    async fn stays_open_while_samples_are_unhealthy() {
+        let mut samples = every_30s(10 * 60, 0, 12.0);
+        samples.push((120, 91.0));
+        let (storage, _) = cpu_condition("unhealthy", 11 * 60, &samples).await;
+        assert_eq!(sweep_status(&storage).await, "open");
+    }
+
+    #[tokio::test]
+    async fn stays_open_without_samples() {
+        let (storage, now) = cpu_condition("missing", 11 * 60, &[]).await;
+        assert_eq!(sweep_status(&storage).await, "open");
+        let range = (f64::NEG_INFINITY, 80.0);
+        assert!(!metric_healthy(&storage, "cpu.usage_percent", range, 600, now).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn stays_open_when_samples_leave_gaps() {
+        // Collector stopped 6 minutes ago
+        let (storage, now) = cpu_condition("stalled", 11 * 60, &every_30s(10 * 60, 6 * 60, 12.0)).await;
+        assert_eq!(sweep_status(&storage).await, "open");
+        // Collector only started 2 minutes ago
+        let range = (f64::NEG_INFINITY, 80.0);
+        let storage = cpu_condition("started", 11 * 60, &every_30s(2 * 60, 0, 12.0)).await.0;
+        assert!(!metric_healthy(&storage, "cpu.usage_percent", range, 600, now).await.unwrap());
+        assert_eq!(sweep_status(&storage).await, "open");
+    }
+
+    #[test]
+    fn anomaly_events_are_healthy_inside_their_expected_range() {
+        let event: Event = serde_json::from_value(json!({
+            "event_id": "anomaly_1",
+            "ts": "2026-01-01T00:00:00Z",
+            "severity": "WARNING",
+            "type": "anomaly",
+            "entity": {"type": "metric", "metric": "memory.used_percent"},
+            "evidence": {"expected_range": [20.0, 40.0]},
+            "status": "open",
+        })).unwrap();
+        assert_eq!(healthy_range(&event), Some(("memory.used_percent".to_string(), 20.0, 40.0)));
+    }
+
+    fn warnings(n: usize, first_ts: i64) -> Vec<OpenEvent> {
+        (0..n).map(|i| OpenEvent {
+            event_id: format!("cpu_{}", i),
+            ts: first_ts + i as i64 * 5,
+            severity: Severity::Warning,
+            kind: EventKind::CpuHigh,
+            fingerprint: "cpu_high:system_cpu".to_string(),
+        }).collect()
+    }
+
+    #[test]
+    fn escalates_a_recurring_condition_once() {
+        let config = EscalationConfig { escalate_after_mins: 0, escalate_after_recurrences: 10, ..Default::default() };
+        let now = 10_000;
+        let mut events = warnings(15, now - 100);
+
+        // Sweep repeatedly, applying each escalation as the sweeper would
+        let mut escalated = Vec::new();
+        for _ in 0..5 {
+            if let Some(e) = escalation(&events, &config, now) {
+                assert_eq!(e.reason, "recurring");
+                assert_eq!(e.recurrences, 14);
+                escalated.push(e.event.event_id.clone());
+            }
+            if let Some(newest) = escalated.last().and_then(|id| events.iter_mut().find(|e| &e.event_id == id)) {
+                newest.severity = Severity::Critical;
+            }
+        }
+        assert_eq!(escalated, vec!["cpu_14".to_string()]);
+
+        // Further recurrences of the same condition don't escalate it again
+        events.extend(warnings(3, now));
+        assert!(escalation(&events, &config, now).is_none());
+    }
+
+    #[test]
+    fn escalates_a_long_open_condition() {
+        let config = EscalationConfig { escalate_after_mins: 15, escalate_after_recurrences: 0, ..Default::default() };
+        let now = 10_000;
+        assert!(escalation(&warnings(2, now - 14 * 60), &config, now).is_none());
+        let events = warnings(2, now - 15 * 60);
+        let e = escalation(&events, &config, now).unwrap();
+        assert_eq!((e.reason, e.event.event_id.as_str(), e.age_secs), ("open_too_long", "cpu_1", 900));
+    }
+
+    #[test]
+    fn agent_reminders_only_escalate_with_age() {
+        let config = EscalationConfig { escalate_after_mins: 60, escalate_after_recurrences: 10, ..Default::default() };
+        let now = 100_000;
+        // Re-raised every 5 minutes while the agent stays degraded
+        let degraded = |n: usize, first_ts: i64| -> Vec<OpenEvent> {
+            (0..n).map(|i| OpenEvent {
+                event_id: format!("degraded_{}", i),
+                ts: first_ts + i as i64 * 300,
+                severity: Severity::Warning,
+                kind: EventKind::AgentDegraded,
+                fingerprint: "agent_degraded:agent".to_string(),
+            }).collect()
+        };
+        assert!(escalation(&degraded(11, now - 10 * 300), &config, now).is_none());
+
+        let events = degraded(13, now - 12 * 300);
+        let e = escalation(&events, &config, now).unwrap();
+        assert_eq!((e.reason, e.event.event_id.as_str()), ("open_too_long", "degraded_12"));
+    }
+}
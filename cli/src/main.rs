@@ -1,8 +1,9 @@
 use clap::{Parser, Subcommand};
 use tokio::net::UnixStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use common::{EventKind, Severity, SilenceMatchers};
 
 #[derive(Parser)]
 #[command(name = "sia-cli")]
@@ -15,7 +16,12 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Show agent status and metrics
-    Status,
+    Status {
+        /// Also show the agent's own counters: queue depth, per-collector
+        /// events, write and LLM latency, IPC requests
+        #[arg(short, long)]
+        verbose: bool,
+    },
     /// List recent events
     List {
         #[arg(short, long, default_value = "20")]
@@ -26,15 +32,257 @@ enum Commands {
         /// Event ID to display
         event_id: String,
     },
+    /// Ask the LLM to explain an event, streaming its answer
+    Explain {
+        /// Event ID to explain
+        event_id: String,
+        /// Extra question for the model
+        #[arg(short, long)]
+        question: Option<String>,
+    },
+    /// Ask a question about this host, answered from stored events, incidents and metrics.
+    /// Without a question, starts an interactive session.
+    Ask {
+        /// Question, e.g. "why was this box slow at 3am?"
+        question: Option<String>,
+        /// Continue an earlier conversation
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Look back this far instead of the range named in the question (e.g. 2h)
+        #[arg(long, conflicts_with = "from")]
+        since: Option<String>,
+        /// Start of the range to search (RFC 3339)
+        #[arg(long)]
+        from: Option<String>,
+        /// End of the range to search (RFC 3339)
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Inspect what the agent sends to the LLM
+    Llm {
+        #[command(subcommand)]
+        cmd: LlmCommands,
+    },
+    /// Review audit records
+    Audit {
+        #[command(subcommand)]
+        cmd: AuditCommands,
+    },
+    /// Inspect the event store
+    Storage {
+        #[command(subcommand)]
+        cmd: StorageCommands,
+    },
+    /// List recent incidents (correlated event groups)
+    Incidents {
+        #[arg(short, long, default_value = "20")]
+        limit: i32,
+    },
+    /// Inspect a single incident
+    Incident {
+        #[command(subcommand)]
+        cmd: IncidentCommands,
+    },
+    /// List registered and discovered services with their open event counts
+    Services,
+    /// Inspect a single service
+    Service {
+        #[command(subcommand)]
+        cmd: ServiceCommands,
+    },
+    /// Manage silences (matching events are stored but not notified or analyzed)
+    Silence {
+        #[command(subcommand)]
+        cmd: SilenceCommands,
+    },
+    /// Manage scoped tokens for registered services (set SIA_TOKEN to use one)
+    Grants {
+        #[command(subcommand)]
+        cmd: GrantsCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum LlmCommands {
+    /// Show the redacted prompt an analysis of this event would send, without sending it
+    Preview {
+        /// Event ID to preview
+        event_id: String,
+    },
 }
 
-#[derive(Serialize)]
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// Every request sent to the LLM: model, purpose, tokens, latency and outcome
+    Llm {
+        /// How far back to look (e.g. 30m, 24h, 7d)
+        #[arg(long, default_value = "24h")]
+        since: String,
+        #[arg(short, long, default_value = "50")]
+        limit: i32,
+        /// Also show prompt/response hashes, or their text when recorded
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// IPC requests refused for lack of a role: who, what, and the role needed
+    Denied {
+        /// How far back to look (e.g. 30m, 24h, 7d)
+        #[arg(long, default_value = "24h")]
+        since: String,
+        #[arg(short, long, default_value = "50")]
+        limit: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum StorageCommands {
+    /// Disk usage against the quota, row counts and the last retention run
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum IncidentCommands {
+    /// Show an incident with its events ordered by root-cause likelihood
+    Show {
+        /// Incident ID to display
+        incident_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SilenceCommands {
+    /// Create a silence
+    Add {
+        /// Event type to match (e.g. cpu_high)
+        #[arg(long = "type")]
+        event_type: Option<String>,
+        /// Severity to match (e.g. WARNING)
+        #[arg(long)]
+        severity: Option<Severity>,
+        /// Service ID to match
+        #[arg(long)]
+        service: Option<String>,
+        /// Entity label to match, as key=value (repeatable)
+        #[arg(long = "label", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
+        /// Start time (RFC 3339, defaults to now)
+        #[arg(long)]
+        start: Option<String>,
+        /// How long the silence lasts (e.g. 30m, 2h, 1d)
+        #[arg(short, long, default_value = "1h")]
+        duration: String,
+        /// Why the silence exists
+        #[arg(short, long, default_value = "")]
+        comment: String,
+    },
+    /// List active and pending silences
+    List {
+        /// Include expired silences
+        #[arg(long)]
+        all: bool,
+    },
+    /// End a silence immediately
+    Expire {
+        /// Silence ID to expire
+        silence_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceCommands {
+    /// Show how a service was discovered, its open events and recent events
+    Show {
+        /// Service ID to display
+        service_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum GrantsCommands {
+    /// Issue a token to a registered service; it is shown only once
+    Create {
+        /// Service ID from [[grants.services]]
+        service_id: String,
+        /// Scope to grant (repeatable; defaults to the service's default scopes)
+        #[arg(long = "scope")]
+        scopes: Vec<String>,
+        /// Token lifetime (e.g. 12h, 7d; defaults to [grants] default_ttl_secs)
+        #[arg(long)]
+        ttl: Option<String>,
+    },
+    /// List active grants
+    List {
+        /// Include revoked and expired grants
+        #[arg(long)]
+        all: bool,
+    },
+    /// Revoke a grant; its token stops working immediately
+    Revoke {
+        grant_id: String,
+    },
+    /// Issue a new token with a fresh expiry; the previous token stops working
+    Refresh {
+        grant_id: String,
+        /// Token lifetime (e.g. 12h, 7d)
+        #[arg(long)]
+        ttl: Option<String>,
+    },
+}
+
+#[derive(Serialize, Default)]
 struct IpcRequest {
     method: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     limit: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     event_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    question: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    incident_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    silence_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matchers: Option<SilenceMatchers>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starts_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ends_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    all: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scopes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grant_id: Option<String>,
+    /// Grant token, for callers whose own role doesn't allow the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl IpcRequest {
+    fn new(method: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            token: std::env::var("SIA_TOKEN").ok().filter(|t| !t.is_empty()),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -48,33 +296,153 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     
     match cli.cmd {
-        Commands::Status => {
-            let response = send_request("status", None, None).await?;
+        Commands::Status { verbose } => {
+            let response = send_request(IpcRequest::new("status")).await?;
             print_status(response);
+            if verbose {
+                let response = send_request(IpcRequest::new("agent.metrics")).await?;
+                print_agent_metrics(response);
+            }
         }
         Commands::List { limit } => {
-            let response = send_request("list", Some(limit), None).await?;
+            let request = IpcRequest { limit: Some(limit), ..IpcRequest::new("list") };
+            let response = send_request(request).await?;
             print_list(response);
         }
         Commands::Show { event_id } => {
-            let response = send_request("show", None, Some(event_id)).await?;
+            let request = IpcRequest { event_id: Some(event_id), ..IpcRequest::new("show") };
+            let response = send_request(request).await?;
             print_show(response);
         }
+        Commands::Explain { event_id, question } => {
+            let request = IpcRequest { event_id: Some(event_id), question, ..IpcRequest::new("explain") };
+            let response = send_streaming_request(request, |chunk| {
+                print!("{}", chunk);
+                let _ = std::io::Write::flush(&mut std::io::stdout());
+            }).await?;
+            if response.success {
+                println!();
+            } else {
+                eprintln!("\nError: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+            }
+        }
+        Commands::Ask { question, session, since, from, to } => {
+            let parse_time = |t: String| chrono::DateTime::parse_from_rfc3339(&t).map(|t| t.timestamp());
+            let from = match since {
+                Some(since) => Some(chrono::Utc::now().timestamp() - parse_duration(&since)?),
+                None => from.map(parse_time).transpose()?,
+            };
+            let to = to.map(parse_time).transpose()?;
+            run_ask(question, session, from, to).await?;
+        }
+        Commands::Llm { cmd: LlmCommands::Preview { event_id } } => {
+            let request = IpcRequest { event_id: Some(event_id), ..IpcRequest::new("llm.preview") };
+            let response = send_request(request).await?;
+            print_llm_preview(response);
+        }
+        Commands::Audit { cmd: AuditCommands::Llm { since, limit, verbose } } => {
+            let request = IpcRequest {
+                kind: Some("llm.request".to_string()),
+                since: Some(chrono::Utc::now().timestamp() - parse_duration(&since)?),
+                limit: Some(limit),
+                ..IpcRequest::new("audit.list")
+            };
+            let response = send_request(request).await?;
+            print_llm_audit(response, verbose);
+        }
+        Commands::Audit { cmd: AuditCommands::Denied { since, limit } } => {
+            let request = IpcRequest {
+                kind: Some("ipc.denied".to_string()),
+                since: Some(chrono::Utc::now().timestamp() - parse_duration(&since)?),
+                limit: Some(limit),
+                ..IpcRequest::new("audit.list")
+            };
+            let response = send_request(request).await?;
+            print_denied_audit(response);
+        }
+        Commands::Storage { cmd: StorageCommands::Stats } => {
+            let response = send_request(IpcRequest::new("storage.stats")).await?;
+            print_storage_stats(response);
+        }
+        Commands::Incidents { limit } => {
+            let request = IpcRequest { limit: Some(limit), ..IpcRequest::new("incidents") };
+            let response = send_request(request).await?;
+            print_incidents(response);
+        }
+        Commands::Incident { cmd: IncidentCommands::Show { incident_id } } => {
+            let request = IpcRequest { incident_id: Some(incident_id), ..IpcRequest::new("incident.show") };
+            let response = send_request(request).await?;
+            print_incident(response);
+        }
+        Commands::Services => {
+            let response = send_request(IpcRequest::new("services")).await?;
+            print_services(response);
+        }
+        Commands::Service { cmd: ServiceCommands::Show { service_id } } => {
+            let request = IpcRequest { service_id: Some(service_id), ..IpcRequest::new("service.show") };
+            let response = send_request(request).await?;
+            print_service(response);
+        }
+        Commands::Silence { cmd } => {
+            let request = match cmd {
+                SilenceCommands::Add { event_type, severity, service, labels, start, duration, comment } => {
+                    let starts_at = match start {
+                        Some(start) => chrono::DateTime::parse_from_rfc3339(&start)?.timestamp(),
+                        None => chrono::Utc::now().timestamp(),
+                    };
+                    let matchers = SilenceMatchers {
+                        r#type: event_type.map(EventKind::from),
+                        severity,
+                        service_id: service,
+                        labels: labels.into_iter().collect(),
+                    };
+                    IpcRequest {
+                        matchers: Some(matchers),
+                        starts_at: Some(starts_at),
+                        ends_at: Some(starts_at + parse_duration(&duration)?),
+                        comment: Some(comment),
+                        ..IpcRequest::new("silence.add")
+                    }
+                }
+                SilenceCommands::List { all } => IpcRequest { all: Some(all), ..IpcRequest::new("silence.list") },
+                SilenceCommands::Expire { silence_id } => {
+                    IpcRequest { silence_id: Some(silence_id), ..IpcRequest::new("silence.expire") }
+                }
+            };
+            let response = send_request(request).await?;
+            print_silences(response);
+        }
+        Commands::Grants { cmd } => {
+            let ttl_secs = |ttl: Option<String>| ttl.map(|t| parse_duration(&t)).transpose();
+            let request = match cmd {
+                GrantsCommands::Create { service_id, scopes, ttl } => IpcRequest {
+                    service_id: Some(service_id),
+                    scopes: Some(scopes),
+                    ttl_secs: ttl_secs(ttl)?,
+                    ..IpcRequest::new("grants.create")
+                },
+                GrantsCommands::List { all } => IpcRequest { all: Some(all), ..IpcRequest::new("grants.list") },
+                GrantsCommands::Revoke { grant_id } => {
+                    IpcRequest { grant_id: Some(grant_id), ..IpcRequest::new("grants.revoke") }
+                }
+                GrantsCommands::Refresh { grant_id, ttl } => IpcRequest {
+                    grant_id: Some(grant_id),
+                    ttl_secs: ttl_secs(ttl)?,
+                    ..IpcRequest::new("grants.refresh")
+                },
+            };
+            let response = send_request(request).await?;
+            print_grants(response);
+        }
     }
     
     Ok(())
 }
 
-async fn send_request(method: &str, limit: Option<i32>, event_id: Option<String>) -> Result<IpcResponse> {
+async fn send_request(request: IpcRequest) -> Result<IpcResponse> {
     let socket_path = "/run/sia/sia.sock";
     let mut stream = UnixStream::connect(socket_path).await?;
     
-    let request = IpcRequest {
-        method: method.to_string(),
-        limit,
-        event_id,
-    };
-    
     let request_json = serde_json::to_string(&request)?;
     stream.write_all(request_json.as_bytes()).await?;
     stream.shutdown().await?;
@@ -86,6 +454,234 @@ async fn send_request(method: &str, limit: Option<i32>, event_id: Option<String>
     Ok(response)
 }
 
+/// Like `send_request`, for methods that emit `{"chunk": ...}` lines before the response
+async fn send_streaming_request(request: IpcRequest, mut on_chunk: impl FnMut(&str)) -> Result<IpcResponse> {
+    let socket_path = "/run/sia/sia.sock";
+    let mut stream = UnixStream::connect(socket_path).await?;
+    
+    let request_json = serde_json::to_string(&request)?;
+    stream.write_all(request_json.as_bytes()).await?;
+    stream.shutdown().await?;
+    
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await? {
+        let value: serde_json::Value = serde_json::from_str(&line)?;
+        match value.get("chunk").and_then(|c| c.as_str()) {
+            Some(chunk) => on_chunk(chunk),
+            None => return Ok(serde_json::from_value(value)?),
+        }
+    }
+    
+    anyhow::bail!("Connection closed before a response was received")
+}
+
+/// One-shot question, or an interactive session reading questions from stdin
+async fn run_ask(question: Option<String>, mut session_id: Option<String>, from: Option<i64>, to: Option<i64>) -> Result<()> {
+    let interactive = question.is_none();
+    let mut question = question;
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    
+    if interactive {
+        println!("Ask about this host; an empty line or Ctrl-D ends the session.");
+    }
+    
+    loop {
+        let q = match question.take() {
+            Some(q) => q,
+            None if interactive => {
+                print!("\n> ");
+                let _ = std::io::Write::flush(&mut std::io::stdout());
+                match stdin.next_line().await? {
+                    Some(line) if !line.trim().is_empty() => line,
+                    _ => break,
+                }
+            }
+            None => break,
+        };
+        
+        let request = IpcRequest {
+            question: Some(q),
+            session_id: session_id.clone(),
+            from,
+            to,
+            ..IpcRequest::new("ask")
+        };
+        let response = send_streaming_request(request, |chunk| {
+            print!("{}", chunk);
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        }).await?;
+        
+        if !response.success {
+            eprintln!("\nError: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+            continue;
+        }
+        println!();
+        print_citations(&response.data);
+        session_id = response.data["session_id"].as_str().map(String::from);
+    }
+    
+    if let Some(id) = session_id {
+        println!("\nSession: {} (continue with --session {})", id, id);
+    }
+    Ok(())
+}
+
+fn print_citations(data: &serde_json::Value) {
+    let empty_vec = vec![];
+    let citations = data["citations"].as_array().unwrap_or(&empty_vec);
+    if citations.is_empty() {
+        return;
+    }
+    
+    println!("\nSources:");
+    for c in citations {
+        println!("  [{}] {} {}: {}",
+            c["id"].as_str().unwrap_or("?"),
+            c["kind"].as_str().unwrap_or("?"),
+            c["ref"].as_str().unwrap_or("?"),
+            truncate(c["text"].as_str().unwrap_or(""), 60)
+        );
+    }
+}
+
+fn print_llm_preview(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let data = &response.data;
+    println!("\nDry run: nothing was sent to {} ({})", data["provider"].as_str().unwrap_or("?"), data["model"].as_str().unwrap_or("?"));
+    if !data["redaction_enabled"].as_bool().unwrap_or(false) {
+        println!("Warning: redaction is disabled; this is exactly what the provider would see.");
+    }
+    
+    let empty_vec = vec![];
+    for message in data["messages"].as_array().unwrap_or(&empty_vec) {
+        println!("\n--- {} ---", message["role"].as_str().unwrap_or("?"));
+        println!("{}", message["content"].as_str().unwrap_or(""));
+    }
+    
+    let tools = data["tools"].as_array().unwrap_or(&empty_vec);
+    if !tools.is_empty() {
+        let names: Vec<&str> = tools.iter().filter_map(|t| t.as_str()).collect();
+        println!("\nTools offered: {}", names.join(", "));
+    }
+    
+    let redactions = data["redactions"].as_array().unwrap_or(&empty_vec);
+    if redactions.is_empty() {
+        println!("\nNo values were redacted.\n");
+        return;
+    }
+    println!("\nRedacted values (kept on this host):");
+    for r in redactions {
+        println!("  {:12} {:8} {}",
+            r["placeholder"].as_str().unwrap_or("?"),
+            r["kind"].as_str().unwrap_or("?"),
+            truncate(r["original"].as_str().unwrap_or(""), 55)
+        );
+    }
+    println!();
+}
+
+fn print_llm_audit(response: IpcResponse, verbose: bool) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let empty_vec = vec![];
+    let records = response.data["records"].as_array().unwrap_or(&empty_vec);
+    
+    if records.is_empty() {
+        println!("\nNo LLM requests recorded in this period.\n");
+        return;
+    }
+    
+    println!("\n┌────────────────────────┬─────────┬──────────────────────────┬───────────────┬───────────┬─────────┬─────────┐");
+    println!("│ Timestamp              │ Purpose │ Event ID                 │ Model         │ Tokens    │ Latency │ Outcome │");
+    println!("├────────────────────────┼─────────┼──────────────────────────┼───────────────┼───────────┼─────────┼─────────┤");
+    
+    for record in records {
+        let p = &record["payload"];
+        let tokens = match (p["input_tokens"].as_u64(), p["output_tokens"].as_u64()) {
+            (None, None) => "-".to_string(),
+            (input, output) => format!("{}/{}", input.unwrap_or(0), output.unwrap_or(0)),
+        };
+        println!("│ {:22} │ {:7} │ {:24} │ {:13} │ {:>9} │ {:>7} │ {:7} │",
+            format_timestamp(record["ts"].as_i64().unwrap_or(0)),
+            truncate(p["purpose"].as_str().unwrap_or("?"), 7),
+            truncate(p["event_id"].as_str().unwrap_or("-"), 24),
+            truncate(p["model"].as_str().unwrap_or("?"), 13),
+            tokens,
+            format!("{}ms", p["latency_ms"].as_u64().unwrap_or(0)),
+            truncate(p["outcome"].as_str().unwrap_or("?"), 7)
+        );
+    }
+    
+    println!("└────────────────────────┴─────────┴──────────────────────────┴───────────────┴───────────┴─────────┴─────────┘");
+    
+    if verbose {
+        for record in records {
+            let p = &record["payload"];
+            println!("\n{} {} via {} ({} values redacted)",
+                format_timestamp(record["ts"].as_i64().unwrap_or(0)),
+                p["purpose"].as_str().unwrap_or("?"),
+                p["provider"].as_str().unwrap_or("?"),
+                p["redactions"].as_u64().unwrap_or(0)
+            );
+            println!("  prompt sha256:   {}", p["prompt_sha256"].as_str().unwrap_or("-"));
+            println!("  response sha256: {}", p["response_sha256"].as_str().unwrap_or("-"));
+            if let Some(error) = p["error"].as_str() {
+                println!("  error: {}", error);
+            }
+            if let Some(prompt) = p["prompt"].as_str() {
+                println!("  --- prompt ---\n{}", prompt);
+            }
+            if let Some(reply) = p["response"].as_str() {
+                println!("  --- response ---\n{}", reply);
+            }
+        }
+    }
+    println!();
+}
+
+fn print_denied_audit(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let empty_vec = vec![];
+    let records = response.data["records"].as_array().unwrap_or(&empty_vec);
+    
+    if records.is_empty() {
+        println!("\nNo denied IPC requests in this period.\n");
+        return;
+    }
+    
+    println!("\n┌────────────────────────┬────────┬────────┬─────────┬──────────────────┬───────┬──────────┬──────────┐");
+    println!("│ Timestamp              │ UID    │ GID    │ PID     │ Method           │ Count │ Role     │ Required │");
+    println!("├────────────────────────┼────────┼────────┼─────────┼──────────────────┼───────┼──────────┼──────────┤");
+    
+    for record in records {
+        let p = &record["payload"];
+        let id = |field: &str| p[field].as_i64().map(|n| n.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("│ {:22} │ {:>6} │ {:>6} │ {:>7} │ {:16} │ {:>5} │ {:8} │ {:8} │",
+            format_timestamp(record["ts"].as_i64().unwrap_or(0)),
+            id("uid"),
+            id("gid"),
+            id("pid"),
+            truncate(p["method"].as_str().filter(|m| !m.is_empty()).unwrap_or("?"), 16),
+            p["count"].as_u64().unwrap_or(1),
+            p["role"].as_str().unwrap_or("none"),
+            p["required"].as_str().unwrap_or("-")
+        );
+    }
+    
+    println!("└────────────────────────┴────────┴────────┴─────────┴──────────────────┴───────┴──────────┴──────────┘\n");
+}
+
 fn print_status(response: IpcResponse) {
     if !response.success {
         eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
@@ -107,9 +703,139 @@ fn print_status(response: IpcResponse) {
     println!("║   Memory:   {:49} ║", format!("✓ {}", data["collectors"]["memory"].as_str().unwrap_or("unknown")));
     println!("╠═══════════════════════════════════════════════════════════════╣");
     println!("║ Events (open):                                                ║");
-    println!("║   Critical: {:49} ║", data["events"]["critical"].as_i64().unwrap_or(0));
-    println!("║   Warning:  {:49} ║", data["events"]["warning"].as_i64().unwrap_or(0));
-    println!("║   Info:     {:49} ║", data["events"]["info"].as_i64().unwrap_or(0));
+    for severity in Severity::ALL.iter().rev() {
+        let key = severity.as_str().to_lowercase();
+        let label = format!("{}{}:", &severity.as_str()[..1], &key[1..]);
+        println!("║   {:9} {:49} ║", label, data["events"][&key].as_i64().unwrap_or(0));
+    }
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    let llm = &data["llm"];
+    let llm_state = if llm["enabled"].as_bool().unwrap_or(false) {
+        format!("{} ({} {})", llm["state"].as_str().unwrap_or("unknown"), llm["provider"].as_str().unwrap_or("?"), llm["model"].as_str().unwrap_or("?"))
+    } else {
+        "disabled".to_string()
+    };
+    println!("║ LLM:        {:49} ║", truncate(&llm_state, 49));
+    if let Some(since) = llm["since"].as_i64() {
+        println!("║   Since:    {:49} ║", format_timestamp(since));
+    }
+    if let Some(error) = llm["last_error"].as_str() {
+        println!("║   {:59} ║", truncate(error, 59));
+    }
+    println!("║ LLM jobs:                                                     ║");
+    for status in ["pending", "running", "done", "failed"] {
+        println!("║   {:9} {:49} ║", format!("{}:", status), data["llm_jobs"][status].as_i64().unwrap_or(0));
+    }
+    println!("╚═══════════════════════════════════════════════════════════════╝\n");
+}
+
+fn print_agent_metrics(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let data = &response.data;
+    let process = &data["process"];
+    let queue = &data["event_queue"];
+    let writes = &data["storage_writes"];
+    
+    println!("╔═══════════════════════════════════════════════════════════════╗");
+    println!("║                    Agent Internals                            ║");
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ RSS:        {:49} ║", format!("{} MB", process["rss_mb"].as_u64().unwrap_or(0)));
+    println!("║ CPU:        {:49} ║", format!("{:.1}%", process["cpu_percent"].as_f64().unwrap_or(0.0)));
+    println!("║ Open fds:   {:49} ║", process["fds"].as_u64().map(|n| n.to_string()).unwrap_or_else(|| "?".to_string()));
+    println!("║ Shedding:   {:49} ║", process["shedding"].as_str().unwrap_or("none"));
+    println!("║ Queue:      {:49} ║", format!("{} / {}", queue["depth"].as_u64().unwrap_or(0), queue["capacity"].as_u64().unwrap_or(0)));
+    println!("║ Writes:     {:49} ║", format!(
+        "{} (avg {} ms, max {} ms, {} failed)",
+        writes["count"].as_u64().unwrap_or(0),
+        writes["avg_ms"].as_u64().unwrap_or(0),
+        writes["max_ms"].as_u64().unwrap_or(0),
+        writes["errors"].as_u64().unwrap_or(0)
+    ));
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Source       Received  Stored  Dropped  Blocked  Samples lost ║");
+    if let Some(sources) = data["sources"].as_object() {
+        for (source, c) in sources {
+            println!(
+                "║ {:12} {:>8} {:>7} {:>8} {:>8} {:>13} ║",
+                truncate(source, 12),
+                c["received"].as_u64().unwrap_or(0),
+                c["stored"].as_u64().unwrap_or(0),
+                c["dropped"].as_u64().unwrap_or(0),
+                c["blocked"].as_u64().unwrap_or(0),
+                c["samples_dropped"].as_u64().unwrap_or(0)
+            );
+        }
+    }
+    if let Some(llm) = data["llm"].as_object().filter(|l| !l.is_empty()) {
+        println!("╠═══════════════════════════════════════════════════════════════╣");
+        println!("║ LLM requests:                                                 ║");
+        for (purpose, stats) in llm {
+            println!("║   {:9} {:49} ║", format!("{}:", purpose), format!(
+                "{} (avg {} ms, max {} ms, {} errors)",
+                stats["count"].as_u64().unwrap_or(0),
+                stats["avg_ms"].as_u64().unwrap_or(0),
+                stats["max_ms"].as_u64().unwrap_or(0),
+                stats["errors"].as_u64().unwrap_or(0)
+            ));
+        }
+    }
+    if let Some(ipc) = data["ipc"].as_object().filter(|m| !m.is_empty()) {
+        println!("╠═══════════════════════════════════════════════════════════════╣");
+        println!("║ IPC requests:                                                 ║");
+        for (method, count) in ipc {
+            println!("║   {:20} {:38} ║", format!("{}:", method), count.as_u64().unwrap_or(0));
+        }
+    }
+    if let Some(reasons) = data["degraded"].as_array().filter(|r| !r.is_empty()) {
+        println!("╠═══════════════════════════════════════════════════════════════╣");
+        println!("║ Degraded:                                                     ║");
+        for reason in reasons {
+            println!("║   {:59} ║", truncate(reason.as_str().unwrap_or("?"), 59));
+        }
+    }
+    println!("╚═══════════════════════════════════════════════════════════════╝\n");
+}
+
+fn print_storage_stats(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let data = &response.data;
+    let mb = |key: &str| format!("{:.1} MB", data[key].as_u64().unwrap_or(0) as f64 / 1024.0 / 1024.0);
+    
+    println!("\n╔═══════════════════════════════════════════════════════════════╗");
+    println!("║                    SIA Event Store                            ║");
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Database:   {:49} ║", mb("db_bytes"));
+    println!("║ WAL:        {:49} ║", mb("wal_bytes"));
+    println!("║ Free pages: {:49} ║", mb("free_bytes"));
+    println!("║ Quota:      {:49} ║", format!("{} of {} ({:.0}%)", mb("total_bytes"), mb("quota_bytes"), data["usage_pct"].as_f64().unwrap_or(0.0)));
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Rows:                                                         ║");
+    if let Some(tables) = data["tables"].as_object() {
+        for (table, count) in tables {
+            println!("║   {:16} {:42} ║", format!("{}:", table), count.as_i64().unwrap_or(0));
+        }
+    }
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    match data["last_retention"].as_object() {
+        Some(run) => {
+            let payload = &run["payload"];
+            println!("║ Last retention: {:45} ║", format_timestamp(run["ts"].as_i64().unwrap_or(0)));
+            println!("║   Expired:  {:49} ║", payload["expired_events"].as_u64().unwrap_or(0));
+            println!("║   Evicted:  {:49} ║", payload["evicted_events"].as_u64().unwrap_or(0));
+            println!("║   Samples:  {:49} ║", payload["metric_samples"].as_u64().unwrap_or(0));
+            println!("║   Audits:   {:49} ║", payload["audits"].as_u64().unwrap_or(0));
+            println!("║   Notices:  {:49} ║", payload["notifications"].as_u64().unwrap_or(0));
+        }
+        None => println!("║ Last retention: {:45} ║", "nothing deleted yet"),
+    }
     println!("╚═══════════════════════════════════════════════════════════════╝\n");
 }
 
@@ -170,6 +896,9 @@ fn print_show(response: IpcResponse) {
     println!("║ Type:       {:49} ║", event["type"].as_str().unwrap_or("?"));
     println!("║ Service:    {:49} ║", event["service_id"].as_str().unwrap_or("?"));
     println!("║ Status:     {:49} ║", event["status"].as_str().unwrap_or("?"));
+    if let Some(silence) = event["silenced_by"].as_str() {
+        println!("║ Silenced:   {:49} ║", silence);
+    }
     println!("╠═══════════════════════════════════════════════════════════════╣");
     println!("║ Snapshot Data:                                                ║");
     
@@ -180,9 +909,413 @@ fn print_show(response: IpcResponse) {
         }
     }
     
+    if let Some(suggestion) = event.get("suggestion").filter(|s| !s.is_null()) {
+        print_suggestion(suggestion);
+    } else if let Some(job) = event.get("llm_job").filter(|j| !j.is_null()) {
+        println!("╠═══════════════════════════════════════════════════════════════╣");
+        let state = format!("{} (attempt {})", job["status"].as_str().unwrap_or("?"), job["attempts"].as_i64().unwrap_or(0));
+        println!("║ AI Suggestion: {:46} ║", truncate(&state, 46));
+        if let Some(error) = job["last_error"].as_str() {
+            println!("║   {:59} ║", truncate(error, 59));
+        }
+    }
+
+    if let Some(notifications) = event["notifications"].as_array().filter(|n| !n.is_empty()) {
+        println!("╠═══════════════════════════════════════════════════════════════╣");
+        println!("║ Notifications:                                                ║");
+        for n in notifications {
+            let line = format!(
+                "{} {} -> {}: {} ({}x)",
+                n["kind"].as_str().unwrap_or("?"),
+                n["route"].as_str().unwrap_or("?"),
+                n["sink"].as_str().unwrap_or("?"),
+                n["status"].as_str().unwrap_or("?"),
+                n["attempts"].as_i64().unwrap_or(0)
+            );
+            println!("║   {:59} ║", truncate(&line, 59));
+            if let Some(error) = n["last_error"].as_str() {
+                println!("║     {:57} ║", truncate(error, 57));
+            }
+        }
+    }
+
     println!("╚═══════════════════════════════════════════════════════════════╝\n");
 }
 
+fn print_suggestion(suggestion: &serde_json::Value) {
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ AI Suggestion ({:45}) ║", truncate(&format!(
+        "{} / {}",
+        suggestion["source"].as_str().unwrap_or("?"),
+        suggestion["model"].as_str().unwrap_or("?")
+    ), 45));
+    
+    if suggestion["format"].as_str() != Some("structured") {
+        // Free-text fallback (or suggestions stored before structured output)
+        for line in wrap(suggestion["analysis"].as_str().unwrap_or(""), 61) {
+            println!("║ {:61} ║", line);
+        }
+        return;
+    }
+    
+    println!("║ {:61} ║", "");
+    println!("║ Summary:{:53} ║", "");
+    for line in wrap(suggestion["summary"].as_str().unwrap_or(""), 59) {
+        println!("║   {:59} ║", line);
+    }
+    
+    print_list_section("Probable causes:", &suggestion["probable_causes"]);
+    
+    println!("║ Immediate actions:{:43} ║", "");
+    if let Some(actions) = suggestion["immediate_actions"].as_array() {
+        for (i, action) in actions.iter().enumerate() {
+            let text = format!("{}. [{} risk] {}",
+                i + 1,
+                action["risk"].as_str().unwrap_or("?"),
+                action["description"].as_str().unwrap_or("?")
+            );
+            for line in wrap(&text, 59) {
+                println!("║   {:59} ║", line);
+            }
+            if let Some(command) = action["command"].as_str().filter(|c| !c.is_empty()) {
+                println!("║      $ {:54} ║", truncate(command, 54));
+            }
+        }
+    }
+    
+    print_list_section("Prevention:", &suggestion["prevention"]);
+    
+    let confidence = suggestion["confidence"].as_f64().unwrap_or(0.0);
+    println!("║ Confidence: {:49} ║", format!("{:.0}%", confidence * 100.0));
+    
+    if let Some(calls) = suggestion["tool_transcript"].as_array().filter(|c| !c.is_empty()) {
+        let tools: Vec<&str> = calls.iter().filter_map(|c| c["tool"].as_str()).collect();
+        println!("║ Tools used: {:49} ║", truncate(&tools.join(", "), 49));
+    }
+}
+
+fn print_list_section(title: &str, items: &serde_json::Value) {
+    println!("║ {:61} ║", title);
+    if let Some(items) = items.as_array() {
+        for item in items {
+            for (i, line) in wrap(item.as_str().unwrap_or("?"), 57).into_iter().enumerate() {
+                let bullet = if i == 0 { "-" } else { " " };
+                println!("║   {} {:57} ║", bullet, line);
+            }
+        }
+    }
+}
+
+fn print_incidents(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let empty_vec = vec![];
+    let incidents = response.data["incidents"].as_array().unwrap_or(&empty_vec);
+    
+    if incidents.is_empty() {
+        println!("\nNo incidents found.\n");
+        return;
+    }
+    
+    println!("\n┌──────────────────────┬────────────────────────┬──────────┬────────┬──────────────────────────┐");
+    println!("│ Incident ID          │ Last Update            │ Severity │ Events │ Root Cause               │");
+    println!("├──────────────────────┼────────────────────────┼──────────┼────────┼──────────────────────────┤");
+    
+    for incident in incidents {
+        println!("│ {:20} │ {:22} │ {:8} │ {:6} │ {:24} │",
+            truncate(incident["incident_id"].as_str().unwrap_or("?"), 20),
+            format_timestamp(incident["updated_at"].as_i64().unwrap_or(0)),
+            incident["severity"].as_str().unwrap_or("?"),
+            incident["event_count"].as_u64().unwrap_or(0),
+            truncate(incident["root_cause"].as_str().unwrap_or("-"), 24)
+        );
+    }
+    
+    println!("└──────────────────────┴────────────────────────┴──────────┴────────┴──────────────────────────┘\n");
+}
+
+fn print_incident(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let incident = &response.data;
+    let entities: Vec<&str> = incident["entities"].as_array()
+        .map(|a| a.iter().filter_map(|e| e.as_str()).collect())
+        .unwrap_or_default();
+    
+    println!("\n╔═══════════════════════════════════════════════════════════════╗");
+    println!("║                     Incident Details                          ║");
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ ID:         {:49} ║", incident["incident_id"].as_str().unwrap_or("?"));
+    println!("║ Opened:     {:49} ║", format_timestamp(incident["opened_at"].as_i64().unwrap_or(0)));
+    println!("║ Updated:    {:49} ║", format_timestamp(incident["updated_at"].as_i64().unwrap_or(0)));
+    println!("║ Severity:   {:49} ║", incident["severity"].as_str().unwrap_or("?"));
+    println!("║ Status:     {:49} ║", incident["status"].as_str().unwrap_or("?"));
+    println!("║ Entities:   {:49} ║", truncate(&entities.join(", "), 49));
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Events (most likely root cause first):                        ║");
+    
+    if let Some(events) = incident["events"].as_array() {
+        for event in events {
+            let line = format!("{}. {} {} {} {}",
+                event["rank"].as_u64().unwrap_or(0),
+                format_timestamp(event["ts"].as_i64().unwrap_or(0)),
+                event["severity"].as_str().unwrap_or("?"),
+                event["type"].as_str().unwrap_or("?"),
+                event["event_id"].as_str().unwrap_or("?"),
+            );
+            println!("║   {:59} ║", truncate(&line, 59));
+        }
+    }
+    
+    println!("╚═══════════════════════════════════════════════════════════════╝\n");
+}
+
+/// running / stopped for discovered services, registered for configured ones never seen
+fn service_state(discovery: &serde_json::Value) -> &'static str {
+    match discovery["running"].as_bool() {
+        Some(true) => "running",
+        Some(false) => "stopped",
+        None => "registered",
+    }
+}
+
+fn print_services(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let empty_vec = vec![];
+    let services = response.data["services"].as_array().unwrap_or(&empty_vec);
+    let unassigned = response.data["unassigned_open_events"].as_i64().unwrap_or(0);
+    
+    if services.is_empty() {
+        println!("\nNo services found.\n");
+    } else {
+        println!("\n┌──────────────────────┬────────────┬──────────────────┬────────────────────────┬────────┐");
+        println!("│ Service ID           │ State      │ Sources          │ Listening              │ Open   │");
+        println!("├──────────────────────┼────────────┼──────────────────┼────────────────────────┼────────┤");
+        
+        for service in services {
+            let discovery = &service["discovery"];
+            let joined = |key: &str, none: &str| -> String {
+                let items: Vec<&str> = discovery[key].as_array().into_iter().flatten().filter_map(|v| v.as_str()).collect();
+                if items.is_empty() { none.to_string() } else { items.join(",") }
+            };
+            println!("│ {:20} │ {:10} │ {:16} │ {:22} │ {:>6} │",
+                truncate(service["service_id"].as_str().unwrap_or("?"), 20),
+                service_state(discovery),
+                truncate(&joined("sources", "config"), 16),
+                truncate(&joined("listen", "-"), 22),
+                service["open_events"].as_i64().unwrap_or(0)
+            );
+        }
+        
+        println!("└──────────────────────┴────────────┴──────────────────┴────────────────────────┴────────┘");
+    }
+    println!("Open events not linked to a service: {}\n", unassigned);
+}
+
+fn print_service(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    let service = &response.data;
+    let discovery = &service["discovery"];
+    let join = |value: &serde_json::Value| -> String {
+        let items: Vec<String> = value.as_array().into_iter().flatten()
+            .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+            .collect();
+        if items.is_empty() { "-".to_string() } else { items.join(", ") }
+    };
+    
+    println!("\n╔═══════════════════════════════════════════════════════════════╗");
+    println!("║                     Service Details                           ║");
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ ID:         {:49} ║", truncate(service["service_id"].as_str().unwrap_or("?"), 49));
+    println!("║ Name:       {:49} ║", truncate(service["name"].as_str().unwrap_or("?"), 49));
+    println!("║ State:      {:49} ║", service_state(discovery));
+    if !discovery.is_null() {
+        let processes = format!("{} (pids {})", discovery["process_count"].as_u64().unwrap_or(0), join(&discovery["pids"]));
+        println!("║ Found by:   {:49} ║", truncate(&join(&discovery["sources"]), 49));
+        println!("║ Unit:       {:49} ║", truncate(discovery["unit"].as_str().unwrap_or("-"), 49));
+        println!("║ Kind:       {:49} ║", truncate(discovery["kind"].as_str().unwrap_or("-"), 49));
+        println!("║ Processes:  {:49} ║", truncate(&processes, 49));
+        println!("║ Exe:        {:49} ║", truncate(discovery["exe"].as_str().unwrap_or("-"), 49));
+        println!("║ UID:        {:49} ║", discovery["uid"].as_u64().map(|u| u.to_string()).unwrap_or_else(|| "-".to_string()));
+        println!("║ Listening:  {:49} ║", truncate(&join(&discovery["listen"]), 49));
+        println!("║ First seen: {:49} ║", format_timestamp(discovery["first_seen"].as_i64().unwrap_or(0)));
+        println!("║ Last seen:  {:49} ║", format_timestamp(discovery["last_seen"].as_i64().unwrap_or(0)));
+    }
+    println!("║ Scopes:     {:49} ║", truncate(&join(&service["default_scopes"]), 49));
+    println!("║ Requested:  {:49} ║", truncate(&join(&service["requested_scopes"]), 49));
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Open events:                                                  ║");
+    for severity in Severity::ALL.iter().rev() {
+        println!("║   {:10} {:48} ║", severity.as_str(), service["open_events"][severity.as_str()].as_i64().unwrap_or(0));
+    }
+    println!("╠═══════════════════════════════════════════════════════════════╣");
+    println!("║ Recent events:                                                ║");
+    match service["recent_events"].as_array().filter(|e| !e.is_empty()) {
+        Some(events) => {
+            for event in events {
+                let line = format!("{} {} {} {} ({})",
+                    format_timestamp(event["ts"].as_i64().unwrap_or(0)),
+                    event["severity"].as_str().unwrap_or("?"),
+                    event["type"].as_str().unwrap_or("?"),
+                    event["event_id"].as_str().unwrap_or("?"),
+                    event["status"].as_str().unwrap_or("?"),
+                );
+                println!("║   {:59} ║", truncate(&line, 59));
+            }
+        }
+        None => println!("║   {:59} ║", "none"),
+    }
+    
+    println!("╚═══════════════════════════════════════════════════════════════╝\n");
+}
+
+fn print_grants(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    // create/refresh return the new token, revoke a confirmation, list many grants
+    if let Some(grant) = response.data.get("grant") {
+        let scopes: Vec<&str> = grant["scopes"].as_array().into_iter().flatten().filter_map(|s| s.as_str()).collect();
+        println!("\nGrant:   {}", grant["id"].as_str().unwrap_or("?"));
+        println!("Service: {}", grant["service_id"].as_str().unwrap_or("?"));
+        println!("Scopes:  {}", scopes.join(", "));
+        println!("Expires: {}", grant["expires_at"].as_str().unwrap_or("?"));
+        println!("\n{}\n", grant["token"].as_str().unwrap_or("?"));
+        println!("The token is not stored by the agent and won't be shown again.\n");
+        return;
+    }
+    if response.data.get("revoked").is_some() {
+        println!("\nGrant {} revoked.\n", response.data["grant_id"].as_str().unwrap_or("?"));
+        return;
+    }
+    
+    let empty_vec = vec![];
+    let grants = response.data["grants"].as_array().unwrap_or(&empty_vec);
+    if grants.is_empty() {
+        println!("\nNo grants found.\n");
+        return;
+    }
+    let now = response.data["now"].as_i64().unwrap_or(0);
+    
+    println!("\n┌────────────────────┬──────────────────┬─────────┬──────────────────────┬──────────────────────────────┐");
+    println!("│ Grant ID           │ Service          │ State   │ Expires              │ Scopes                       │");
+    println!("├────────────────────┼──────────────────┼─────────┼──────────────────────┼──────────────────────────────┤");
+    
+    for grant in grants {
+        let expires_at = grant["expires_at"].as_i64().unwrap_or(0);
+        let state = if !grant["revoked_at"].is_null() {
+            "revoked"
+        } else if expires_at <= now {
+            "expired"
+        } else {
+            "active"
+        };
+        let scopes: Vec<&str> = grant["scopes"].as_array().into_iter().flatten().filter_map(|s| s.as_str()).collect();
+        println!("│ {:18} │ {:16} │ {:7} │ {:20} │ {:28} │",
+            truncate(grant["id"].as_str().unwrap_or("?"), 18),
+            truncate(grant["service_id"].as_str().unwrap_or("?"), 16),
+            state,
+            format_timestamp(expires_at),
+            truncate(&scopes.join(","), 28)
+        );
+    }
+    
+    println!("└────────────────────┴──────────────────┴─────────┴──────────────────────┴──────────────────────────────┘\n");
+}
+
+fn print_silences(response: IpcResponse) {
+    if !response.success {
+        eprintln!("Error: {}", response.data.get("error").unwrap_or(&serde_json::json!("Unknown error")));
+        return;
+    }
+    
+    // add/expire return a single silence, list returns many
+    let single = [response.data.clone()];
+    let silences = match response.data["silences"].as_array() {
+        Some(silences) => silences.as_slice(),
+        None if response.data.get("expired").is_some() => {
+            println!("\nSilence {} expired.\n", response.data["silence_id"].as_str().unwrap_or("?"));
+            return;
+        }
+        None => &single,
+    };
+    
+    if silences.is_empty() {
+        println!("\nNo silences found.\n");
+        return;
+    }
+    
+    println!("\n┌────────────────────┬─────────┬──────────────────────┬──────────────────────┬──────────────────────────────┐");
+    println!("│ Silence ID         │ State   │ Starts               │ Ends                 │ Matchers                     │");
+    println!("├────────────────────┼─────────┼──────────────────────┼──────────────────────┼──────────────────────────────┤");
+    
+    for silence in silences {
+        let matchers = serde_json::from_value::<SilenceMatchers>(silence["matchers"].clone()).unwrap_or_default();
+        let mut parts = Vec::new();
+        if let Some(t) = matchers.r#type {
+            parts.push(format!("type={}", t));
+        }
+        if let Some(sev) = matchers.severity {
+            parts.push(format!("severity={}", sev));
+        }
+        if let Some(svc) = matchers.service_id {
+            parts.push(format!("service={}", svc));
+        }
+        parts.extend(matchers.labels.iter().map(|(k, v)| format!("{}={}", k, v)));
+        let matchers_str = if parts.is_empty() { "*".to_string() } else { parts.join(",") };
+        
+        println!("│ {:18} │ {:7} │ {:20} │ {:20} │ {:28} │",
+            truncate(silence["id"].as_str().unwrap_or("?"), 18),
+            silence["state"].as_str().unwrap_or("created"),
+            format_timestamp(silence["starts_at"].as_i64().unwrap_or(0)),
+            format_timestamp(silence["ends_at"].as_i64().unwrap_or(0)),
+            truncate(&matchers_str, 28)
+        );
+        if let Some(comment) = silence["comment"].as_str().filter(|c| !c.is_empty()) {
+            println!("│ {:18} │ {:7} │ {:76} │", "", "", truncate(&format!("{} ({})", comment, silence["created_by"].as_str().unwrap_or("?")), 76));
+        }
+    }
+    
+    println!("└────────────────────┴─────────┴──────────────────────┴──────────────────────┴──────────────────────────────┘\n");
+}
+
+fn parse_label(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected key=value, got '{}'", s))
+}
+
+/// Parse durations like "90s", "30m", "2h", "1d" (plain numbers are seconds)
+fn parse_duration(s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let value: i64 = num.parse().map_err(|_| anyhow::anyhow!("invalid duration '{}'", s))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => anyhow::bail!("invalid duration unit in '{}'", s),
+    };
+    Ok(value * multiplier)
+}
+
 fn format_uptime(seconds: u64) -> String {
     let hours = seconds / 3600;
     let minutes = (seconds % 3600) / 60;
@@ -206,6 +1339,30 @@ fn format_timestamp(ts: i64) -> String {
     }
 }
 
+/// Greedy word wrap; words longer than `width` are truncated
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let word_len = word.chars().count();
+        if !current.is_empty() && current.chars().count() + 1 + word_len > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        if word_len > width {
+            current.push_str(truncate(word, width).trim_end());
+        } else {
+            current.push_str(word);
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
 fn truncate(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         format!("{:width$}", s, width = max_len)
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use anyhow::Result;
+use std::collections::BTreeMap;
+use crate::types::{EventKind, Service, Severity, SilenceMatchers};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -8,6 +10,28 @@ pub struct Config {
     pub ipc: IpcConfig,
     pub llm: LlmConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub anomaly: AnomalyConfig,
+    #[serde(default)]
+    pub correlation: CorrelationConfig,
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub self_monitor: SelfMonitorConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub otlp: OtlpConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub grants: GrantsConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,17 +41,349 @@ pub struct AgentConfig {
     pub cpu_interval: u64,
     pub proc_interval: u64,
     pub event_ring_capacity: usize,
+    /// Load shedding as RSS approaches `memory_budget`
+    #[serde(default)]
+    pub memory: MemoryConfig,
+}
+
+/// Thresholds are percentages of `agent.memory_budget`; each stage also
+/// applies the ones below it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// How often the agent measures its own RSS (seconds)
+    pub check_interval_secs: u64,
+    /// Stop recording metric samples and DEBUG events
+    pub drop_debug_pct: u64,
+    /// Pause collectors that aren't needed to detect critical conditions
+    pub pause_collectors_pct: u64,
+    /// Trim in-memory caches (correlation window, process tables, SQLite page cache)
+    pub shrink_caches_pct: u64,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 10,
+            drop_debug_pct: 75,
+            pause_collectors_pct: 85,
+            shrink_caches_pct: 95,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IpcConfig {
     pub socket_path: String,
+    #[serde(default)]
+    pub auth: IpcAuthConfig,
+}
+
+/// Access levels for IPC clients; each includes everything the previous one may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpcRole {
+    /// Read events, incidents, silences and agent status
+    Viewer,
+    /// Also create and expire silences and query the LLM
+    Operator,
+    /// Also read audit records
+    Admin,
+}
+
+impl IpcRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IpcRole::Viewer => "viewer",
+            IpcRole::Operator => "operator",
+            IpcRole::Admin => "admin",
+        }
+    }
+}
+
+/// Peer credential checks on the IPC socket. root and the agent's own user
+/// are always admin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpcAuthConfig {
+    /// When off, every local user is admin
+    pub enabled: bool,
+    /// Role for peers no entry in `roles` matches; none denies them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_role: Option<IpcRole>,
+    /// The highest role among matching entries applies
+    pub roles: Vec<IpcRoleBinding>,
+}
+
+impl Default for IpcAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_role: None,
+            roles: Vec::new(),
+        }
+    }
+}
+
+/// Grants `role` to a user, or to every member (primary or supplementary) of a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcRoleBinding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    pub role: IpcRole,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmConfig {
-    pub ollama_url: String,
+    /// Which backend to use; its settings come from the matching `[llm.<provider>]` table
+    #[serde(default)]
+    pub provider: LlmProviderKind,
+    /// Legacy Ollama URL, used when `[llm.ollama]` is absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ollama_url: Option<String>,
+    /// Legacy Ollama model, used when `[llm.ollama]` is absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ollama: Option<LlmProviderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openai: Option<LlmProviderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anthropic: Option<LlmProviderConfig>,
+    #[serde(default)]
+    pub queue: LlmQueueConfig,
+    #[serde(default)]
+    pub ask: AskConfig,
+    #[serde(default)]
+    pub tools: LlmToolsConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub audit: LlmAuditConfig,
+    #[serde(default)]
+    pub prompts: LlmPromptsConfig,
+    #[serde(default)]
+    pub health: LlmHealthConfig,
+}
+
+/// What is masked before any text leaves the host (`[llm.redaction]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Built-in detectors: ip, email, jwt, api_key, aws_key, home_path, username, hostname
+    pub detectors: Vec<String>,
+    /// Extra regular expressions; matches become `<CUSTOM_n>` placeholders
+    pub patterns: Vec<String>,
+    /// JSON fields whose values are always replaced entirely
+    pub deny_fields: Vec<String>,
+    /// JSON fields that are never redacted
+    pub allow_fields: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            detectors: ["ip", "email", "jwt", "api_key", "aws_key", "home_path", "username", "hostname"]
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+            patterns: Vec::new(),
+            deny_fields: vec!["environ".to_string()],
+            allow_fields: vec!["type".to_string(), "metric".to_string(), "severity".to_string()],
+        }
+    }
+}
+
+/// Periodic provider probing (`[llm.health]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmHealthConfig {
+    pub interval_secs: u64,
+    /// On recovery, CRITICAL events this recent that were never analyzed are queued
+    pub backfill_hours: u64,
+}
+
+impl Default for LlmHealthConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            backfill_hours: 24,
+        }
+    }
+}
+
+/// Per-event-type prompt templates (`[llm.prompts]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmPromptsConfig {
+    /// Directory of `<event type>.j2` templates; the built-in ones are used when unset
+    pub dir: Option<String>,
+    /// Earlier events of the same type offered to templates as `history`
+    pub history_events: usize,
+}
+
+impl Default for LlmPromptsConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            history_events: 5,
+        }
+    }
+}
+
+/// Record of every request sent to the model, kept in `audits` (`[llm.audit]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmAuditConfig {
+    pub enabled: bool,
+    /// Store the redacted prompt and the response; otherwise only their SHA-256
+    pub full_text: bool,
+    /// LLM audit records older than this are deleted
+    pub retention_days: u64,
+}
+
+impl Default for LlmAuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            full_text: false,
+            retention_days: 90,
+        }
+    }
+}
+
+/// Read-only diagnostics the model may call during event analysis (`[llm.tools]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmToolsConfig {
+    pub enabled: bool,
+    /// Model round-trips with tool calls before a final answer is demanded
+    pub max_iterations: usize,
+}
+
+impl Default for LlmToolsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_iterations: 4,
+        }
+    }
+}
+
+/// Background analysis job queue (`[llm.queue]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmQueueConfig {
+    /// Jobs sent to the model at the same time
+    pub concurrency: usize,
+    /// Attempts before a job is marked failed
+    pub max_attempts: u32,
+    /// Delay before the first retry (seconds); doubles with each attempt
+    pub backoff_base_secs: u64,
+    /// Upper bound for the retry delay (seconds)
+    pub backoff_max_secs: u64,
+    /// How often the queue is checked for due retries (seconds)
+    pub poll_interval_secs: u64,
+}
+
+/// Grounded chat (`sia-cli ask`, `[llm.ask]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AskConfig {
+    /// Approximate tokens of retrieved events, incidents and metrics per question
+    pub context_tokens: usize,
+    /// Approximate tokens of earlier conversation turns kept in the prompt
+    pub history_tokens: usize,
+    /// Events considered for the context, most severe first
+    pub max_events: i64,
+    /// Time range searched when the question names no time (minutes back from now)
+    pub default_window_mins: i64,
+    /// Half-width of the range around a time named in the question ("at 3am")
+    pub around_window_mins: i64,
+}
+
+impl Default for AskConfig {
+    fn default() -> Self {
+        Self {
+            context_tokens: 2000,
+            history_tokens: 1000,
+            max_events: 50,
+            default_window_mins: 60,
+            around_window_mins: 30,
+        }
+    }
+}
+
+impl Default for LlmQueueConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 2,
+            max_attempts: 5,
+            backoff_base_secs: 10,
+            backoff_max_secs: 600,
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProviderKind {
+    /// Ollama's native API
+    #[default]
+    Ollama,
+    /// Any server exposing `/v1/chat/completions` (vLLM, llama.cpp server, LM Studio, ...)
+    OpenAi,
+    /// Anthropic Messages API
+    Anthropic,
+}
+
+impl LlmProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProviderKind::Ollama => "ollama",
+            LlmProviderKind::OpenAi => "openai",
+            LlmProviderKind::Anthropic => "anthropic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmProviderConfig {
+    pub url: String,
     pub model: String,
+    /// File holding the API key; not needed for local servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    #[serde(default = "default_llm_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_llm_timeout() -> u64 {
+    30
+}
+
+impl LlmConfig {
+    /// Settings for the selected provider
+    pub fn active(&self) -> Result<LlmProviderConfig> {
+        let section = match self.provider {
+            LlmProviderKind::Ollama => self.ollama.clone().or_else(|| {
+                Some(LlmProviderConfig {
+                    url: self.ollama_url.clone()?,
+                    model: self.model.clone()?,
+                    api_key_file: None,
+                    timeout_secs: default_llm_timeout(),
+                })
+            }),
+            LlmProviderKind::OpenAi => self.openai.clone(),
+            LlmProviderKind::Anthropic => self.anthropic.clone(),
+        };
+        section.ok_or_else(|| anyhow::anyhow!("no [llm.{}] settings for the selected provider", self.provider.as_str()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +391,433 @@ pub struct StorageConfig {
     pub db_path: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    pub enabled: bool,
+    /// EWMA smoothing factor applied to each new sample (0 < alpha <= 1)
+    pub alpha: f64,
+    /// Deviation from the learned mean, in standard deviations, that counts as anomalous
+    pub z_threshold: f64,
+    /// How long (seconds) a metric must stay outside the expected range before an event is raised
+    pub sustained_secs: u64,
+    /// Samples a baseline bucket must have seen before it is trusted
+    pub warmup_samples: u64,
+    /// Lower bound for the standard deviation so flat metrics don't flag tiny changes
+    pub min_stddev: f64,
+    /// Keep separate baselines per hour-of-day/day-of-week
+    pub seasonality: bool,
+    /// How often learned baselines are written to the database (seconds)
+    pub flush_secs: u64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            alpha: 0.05,
+            z_threshold: 3.0,
+            sustained_secs: 60,
+            warmup_samples: 120,
+            min_stddev: 1.0,
+            seasonality: true,
+            flush_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    pub enabled: bool,
+    /// Events sharing an entity within this many seconds are grouped into one incident
+    pub window_secs: u64,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EscalationConfig {
+    pub enabled: bool,
+    /// How often the sweeper scans open events (seconds)
+    pub sweep_interval_secs: u64,
+    /// WARNING events still open after this many minutes become CRITICAL (0 = never)
+    pub escalate_after_mins: u64,
+    /// WARNING events that recur this many times become CRITICAL (0 = never)
+    pub escalate_after_recurrences: u64,
+    /// Open events are resolved once their condition has been healthy this long (0 = never).
+    /// CPU, memory and anomaly events need their metric's samples back in range for the
+    /// whole period; other events need to go that long without recurring.
+    pub resolve_after_healthy_mins: u64,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sweep_interval_secs: 60,
+            escalate_after_mins: 15,
+            escalate_after_recurrences: 10,
+            resolve_after_healthy_mins: 10,
+        }
+    }
+}
+
+/// Age limits and `agent.disk_quota` enforcement for the event store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    /// How often the retention manager runs (seconds)
+    pub interval_secs: u64,
+    /// Events older than this many days are deleted, per severity; missing severities are kept
+    pub max_age_days: BTreeMap<Severity, u64>,
+    /// Metric samples older than this many days are deleted
+    pub metric_sample_days: u64,
+    /// `sia-cli ask` sessions idle for this many days are deleted
+    pub chat_session_days: u64,
+    /// Audit records older than this many days are deleted, except LLM
+    /// requests, which `[llm.audit] retention_days` governs
+    pub audit_days: u64,
+    /// Sent and failed notifications older than this many days are deleted
+    pub notification_days: u64,
+    /// Once over quota, evict until usage is back under this share of it (percent)
+    pub target_pct: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300,
+            max_age_days: [
+                (Severity::Debug, 1),
+                (Severity::Info, 7),
+                (Severity::Warning, 30),
+                (Severity::Error, 60),
+                (Severity::Critical, 90),
+            ].into_iter().collect(),
+            metric_sample_days: 7,
+            chat_session_days: 30,
+            audit_days: 90,
+            notification_days: 30,
+            target_pct: 90,
+        }
+    }
+}
+
+/// When the agent reports itself as `agent_degraded`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelfMonitorConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Event channel fill level that counts as backed up (percent)
+    pub queue_depth_pct: u64,
+    /// Average event write latency over one interval that counts as slow
+    pub write_latency_ms: u64,
+}
+
+impl Default for SelfMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 15,
+            queue_depth_pct: 80,
+            write_latency_ms: 250,
+        }
+    }
+}
+
+/// Optional HTTP listener for Prometheus scrapes and health probes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    pub listen: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:9464".to_string(),
+        }
+    }
+}
+
+/// Push events (as log records) and metrics to an OTLP/HTTP collector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtlpConfig {
+    pub enabled: bool,
+    /// Base URL; `/v1/logs` and `/v1/metrics` are appended
+    pub endpoint: String,
+    /// Extra request headers, e.g. an auth token
+    pub headers: BTreeMap<String, String>,
+    pub service_name: String,
+    /// Added to host.name, service.name and service.version
+    pub resource_attributes: BTreeMap<String, String>,
+    pub export_interval_secs: u64,
+    /// Log records per request; a full batch is sent without waiting for the interval
+    pub batch_size: usize,
+    pub timeout_secs: u64,
+    pub export_metrics: bool,
+    pub export_events: bool,
+    /// Batches that couldn't be delivered wait here until the collector is back
+    pub buffer_dir: String,
+    /// Oldest buffered batches are dropped beyond this size
+    pub buffer_max_mb: u64,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://127.0.0.1:4318".to_string(),
+            headers: BTreeMap::new(),
+            service_name: "sia-agent".to_string(),
+            resource_attributes: BTreeMap::new(),
+            export_interval_secs: 15,
+            batch_size: 512,
+            timeout_secs: 10,
+            export_metrics: true,
+            export_events: true,
+            buffer_dir: "./otlp-buffer".to_string(),
+            buffer_max_mb: 50,
+        }
+    }
+}
+
+/// Outbound notifications: named sinks, and routes deciding which events reach them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// How often due retries are picked up (seconds)
+    pub poll_interval_secs: u64,
+    /// Deliveries are marked failed after this many attempts
+    pub max_attempts: u32,
+    pub backoff_base_secs: u64,
+    pub backoff_max_secs: u64,
+    /// Per-request timeout for webhooks and SMTP
+    pub timeout_secs: u64,
+    pub sinks: BTreeMap<String, SinkConfig>,
+    /// Every matching route notifies its sinks; an event can match several
+    pub routes: Vec<NotifyRoute>,
+    /// Scheduled summaries of new, still-open and resolved events
+    pub digests: Vec<NotifyDigest>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_secs: 10,
+            max_attempts: 5,
+            backoff_base_secs: 30,
+            backoff_max_secs: 3600,
+            timeout_secs: 10,
+            sinks: BTreeMap::new(),
+            routes: Vec::new(),
+            digests: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// POST to any URL; `template` is a minijinja template rendering the JSON body
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        template: Option<String>,
+    },
+    /// Slack (or Mattermost/Rocket.Chat) incoming webhook
+    Slack { url: String },
+    /// Discord channel webhook
+    Discord { url: String },
+    /// Microsoft Teams incoming webhook (MessageCard)
+    Teams { url: String },
+    Email(EmailSinkConfig),
+    /// Desktop popup through `notify-send` (freedesktop notifications over D-Bus)
+    Desktop {
+        #[serde(default = "default_notify_send")]
+        command: String,
+    },
+}
+
+impl SinkConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SinkConfig::Webhook { .. } => "webhook",
+            SinkConfig::Slack { .. } => "slack",
+            SinkConfig::Discord { .. } => "discord",
+            SinkConfig::Teams { .. } => "teams",
+            SinkConfig::Email(_) => "email",
+            SinkConfig::Desktop { .. } => "desktop",
+        }
+    }
+}
+
+fn default_notify_send() -> String {
+    "notify-send".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain SMTP, for a relay on localhost or a trusted network
+    None,
+    /// Upgrade with STARTTLS after EHLO (usually port 587)
+    #[default]
+    StartTls,
+    /// TLS from the first byte (usually port 465)
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSinkConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// AUTH PLAIN credentials; the password is read from `password_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifyRoute {
+    pub name: String,
+    /// Names of entries in `[notify.sinks]`
+    pub sinks: Vec<String>,
+    #[serde(default = "default_route_severity")]
+    pub min_severity: Severity,
+    /// Event types to match; empty matches all
+    #[serde(default)]
+    pub types: Vec<EventKind>,
+    /// Service ids to match; empty matches all
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// After notifying about an event type, further events of that type are
+    /// held back on this route for this long (0 = never)
+    #[serde(default)]
+    pub throttle_secs: u64,
+    /// Follow up on delivered alerts once their event is resolved
+    #[serde(default = "default_send_resolved")]
+    pub send_resolved: bool,
+}
+
+fn default_route_severity() -> Severity {
+    Severity::Error
+}
+
+fn default_send_resolved() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifyDigest {
+    pub name: String,
+    /// Cron expression with a seconds field in local time, or @hourly / @daily
+    pub schedule: String,
+    pub sinks: Vec<String>,
+    /// Events below this severity are left out of the counts
+    #[serde(default = "default_digest_severity")]
+    pub min_severity: Severity,
+    /// Don't send a digest when nothing is new, open or resolved
+    #[serde(default)]
+    pub skip_empty: bool,
+}
+
+fn default_digest_severity() -> Severity {
+    Severity::Warning
+}
+
+/// Scoped, expiring tokens that let registered services use the IPC socket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrantsConfig {
+    /// HMAC key for signing tokens; generated on first start if missing
+    pub key_file: String,
+    /// Token lifetime when a grant is created or refreshed without one
+    pub default_ttl_secs: i64,
+    pub max_ttl_secs: i64,
+    /// Services that may be issued grants
+    pub services: Vec<Service>,
+}
+
+impl Default for GrantsConfig {
+    fn default() -> Self {
+        Self {
+            key_file: "./grants.key".to_string(),
+            default_ttl_secs: 86400,
+            max_ttl_secs: 30 * 86400,
+            services: Vec::new(),
+        }
+    }
+}
+
+/// Finding the services running on this host and linking events to them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Group processes by the systemd service unit they run under
+    pub systemd: bool,
+    /// Treat processes listening on TCP ports as services
+    pub sockets: bool,
+    /// Extra well-known services: service id -> process names
+    pub processes: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300,
+            systemd: true,
+            sockets: true,
+            processes: BTreeMap::new(),
+        }
+    }
+}
+
+/// Recurring window during which matching events are silenced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    /// Cron expression with a seconds field, evaluated in local time (e.g. "0 0 2 * * Sun")
+    pub schedule: String,
+    pub duration_mins: u64,
+    #[serde(default)]
+    pub comment: String,
+    #[serde(default)]
+    pub matchers: SilenceMatchers,
+}
+
 impl Config {
     pub fn load(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
@@ -0,0 +1,114 @@
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Marks the token format, so it can change without old tokens verifying
+const TOKEN_PREFIX: &str = "sia1";
+
+/// What a grant's token may be used for; each IPC method needs one of these
+/// unless the caller's role already allows it
+pub const SCOPES: &[&str] = &[
+    "status:read",
+    "events:read",
+    "incidents:read",
+    "services:read",
+    "silences:read",
+    "silences:write",
+    "llm:query",
+    "audit:read",
+];
+
+pub fn is_known_scope(scope: &str) -> bool {
+    SCOPES.contains(&scope)
+}
+
+/// Signed contents of a grant token
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenClaims {
+    /// Grant id
+    pub gid: String,
+    /// Service the grant was issued to
+    pub sid: String,
+    pub scp: Vec<String>,
+    /// Issued at, unix seconds
+    pub iat: i64,
+    /// Expires at, unix seconds
+    pub exp: i64,
+}
+
+/// `sia1.<claims>.<HMAC-SHA256 of "sia1.<claims>">`, both parts base64url
+pub fn sign_token(key: &[u8], claims: &TokenClaims) -> Result<String> {
+    let payload = format!("{}.{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?));
+    let mut mac = HmacSha256::new_from_slice(key)?;
+    mac.update(payload.as_bytes());
+    Ok(format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())))
+}
+
+/// Check the signature and expiry; whether the grant still exists is up to the caller
+pub fn verify_token(key: &[u8], token: &str, now: i64) -> Result<TokenClaims> {
+    let (payload, signature) = token.rsplit_once('.').ok_or_else(|| anyhow!("malformed token"))?;
+    let claims = payload.strip_prefix(TOKEN_PREFIX)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .ok_or_else(|| anyhow!("malformed token"))?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| anyhow!("malformed token"))?;
+
+    let mut mac = HmacSha256::new_from_slice(key)?;
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).map_err(|_| anyhow!("bad token signature"))?;
+
+    let claims = URL_SAFE_NO_PAD.decode(claims).map_err(|_| anyhow!("malformed token"))?;
+    let claims: TokenClaims = serde_json::from_slice(&claims)?;
+    if claims.exp <= now {
+        bail!("token expired");
+    }
+    Ok(claims)
+}
+
+/// What gets stored instead of the token itself
+pub fn token_hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(exp: i64) -> TokenClaims {
+        TokenClaims {
+            gid: "grant_1".to_string(),
+            sid: "backup".to_string(),
+            scp: vec!["events:read".to_string()],
+            iat: 1000,
+            exp,
+        }
+    }
+
+    #[test]
+    fn verifies_own_tokens_until_expiry() {
+        let token = sign_token(b"secret", &claims(2000)).unwrap();
+        assert_eq!(verify_token(b"secret", &token, 1500).unwrap(), claims(2000));
+        assert_eq!(verify_token(b"secret", &token, 2000).unwrap_err().to_string(), "token expired");
+        assert_eq!(verify_token(b"other", &token, 1500).unwrap_err().to_string(), "bad token signature");
+    }
+
+    #[test]
+    fn rejects_altered_claims() {
+        let token = sign_token(b"secret", &claims(2000)).unwrap();
+        let (_, signature) = token.rsplit_once('.').unwrap();
+        let mut widened = claims(2000);
+        widened.scp.push("silences:write".to_string());
+        let forged = format!(
+            "{}.{}.{}",
+            TOKEN_PREFIX,
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&widened).unwrap()),
+            signature
+        );
+        assert!(verify_token(b"secret", &forged, 1500).is_err());
+        assert!(verify_token(b"secret", "not-a-token", 1500).is_err());
+    }
+}
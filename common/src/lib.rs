@@ -1,6 +1,7 @@
 pub mod types;
 pub mod ipc;
 pub mod config;
+pub mod grants;
 
 pub use types::*;
 pub use config::*;
\ No newline at end of file
@@ -1,16 +1,49 @@
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Service {
 pub id: String,
 pub name: String,
-pub discovery: Option<serde_json::Value>,
+/// Set once the service has been found running on this host
+#[serde(default)]
+pub discovery: Option<ServiceDiscovery>,
+/// Granted when a grant is created without naming scopes
+#[serde(default)]
 pub default_scopes: Vec<String>,
+/// May also be granted, but only when asked for explicitly
+#[serde(default)]
 pub requested_scopes: Vec<String>,
 }
 
 
+/// How a running service was found, and what it looked like at the last scan
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ServiceDiscovery {
+/// Any of "systemd", "process" and "socket"
+pub sources: Vec<String>,
+/// systemd unit the processes run under
+pub unit: Option<String>,
+/// Well-known service type matched by process name, e.g. "postgres"
+pub kind: Option<String>,
+/// Lowest pids first, capped
+pub pids: Vec<u32>,
+pub process_count: usize,
+pub exe: Option<String>,
+pub uid: Option<u32>,
+/// Listening TCP addresses, e.g. "0.0.0.0:5432"
+pub listen: Vec<String>,
+/// Whether the last scan found it
+pub running: bool,
+pub first_seen: i64,
+pub last_seen: i64,
+}
+
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Grant {
 pub id: String,
@@ -25,10 +58,248 @@ pub token: String,
 pub struct Event {
 pub event_id: String,
 pub ts: String,
-pub severity: String,
-pub r#type: String,
+pub severity: Severity,
+pub r#type: EventKind,
 pub entity: serde_json::Value,
 pub evidence: serde_json::Value,
 pub suggestion: Option<serde_json::Value>,
 pub status: String,
-}
\ No newline at end of file
+/// Id of the silence or maintenance window that matched this event
+#[serde(default, skip_serializing_if = "Option::is_none")]
+pub silenced_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricSample {
+pub name: String,
+pub ts: String,
+pub value: f64,
+}
+
+
+/// Event severity, ordered from least to most severe
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [
+        Severity::Debug,
+        Severity::Info,
+        Severity::Warning,
+        Severity::Error,
+        Severity::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Debug => "DEBUG",
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Error => "ERROR",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Severity::ALL.iter()
+            .find(|sev| sev.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("unknown severity '{}'", s))
+    }
+}
+
+
+/// Event type. Unknown strings round-trip through `Other` so new collectors
+/// don't need a change here before they can emit events.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum EventKind {
+    CpuHigh,
+    MemoryHigh,
+    DiskHigh,
+    Anomaly,
+    OomKill,
+    UnitFailed,
+    /// The agent itself is near its memory budget
+    AgentMemoryHigh,
+    /// The agent is falling behind: blocked collectors, a backed-up queue or slow writes
+    AgentDegraded,
+    Other(String),
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            EventKind::CpuHigh => "cpu_high",
+            EventKind::MemoryHigh => "memory_high",
+            EventKind::DiskHigh => "disk_high",
+            EventKind::Anomaly => "anomaly",
+            EventKind::OomKill => "oom_kill",
+            EventKind::UnitFailed => "unit_failed",
+            EventKind::AgentMemoryHigh => "agent_memory_high",
+            EventKind::AgentDegraded => "agent_degraded",
+            EventKind::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for EventKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "cpu_high" => EventKind::CpuHigh,
+            "memory_high" => EventKind::MemoryHigh,
+            "disk_high" => EventKind::DiskHigh,
+            "anomaly" => EventKind::Anomaly,
+            "oom_kill" => EventKind::OomKill,
+            "unit_failed" => EventKind::UnitFailed,
+            "agent_memory_high" => EventKind::AgentMemoryHigh,
+            "agent_degraded" => EventKind::AgentDegraded,
+            other => EventKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for EventKind {
+    fn from(s: String) -> Self {
+        EventKind::from(s.as_str())
+    }
+}
+
+impl From<EventKind> for String {
+    fn from(kind: EventKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+/// Conditions an event must meet for a silence to apply. Empty matchers match everything.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SilenceMatchers {
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<EventKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    /// Matched against top-level entity fields
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl SilenceMatchers {
+    pub fn matches(&self, event: &Event, service_id: &str) -> bool {
+        if self.r#type.as_ref().is_some_and(|t| *t != event.r#type) {
+            return false;
+        }
+        if self.severity.is_some_and(|s| s != event.severity) {
+            return false;
+        }
+        if self.service_id.as_deref().is_some_and(|s| s != service_id) {
+            return false;
+        }
+        self.labels.iter().all(|(key, expected)| {
+            match event.entity.get(key) {
+                Some(serde_json::Value::String(s)) => s == expected,
+                Some(other) => serde_json::from_str::<serde_json::Value>(expected).is_ok_and(|v| v == *other),
+                None => false,
+            }
+        })
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Silence {
+    pub id: String,
+    pub matchers: SilenceMatchers,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub created_by: String,
+    pub comment: String,
+    pub created_at: i64,
+}
+
+impl Silence {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(kind: EventKind, severity: Severity, entity: serde_json::Value) -> Event {
+        Event {
+            event_id: "evt_1".to_string(),
+            ts: "2026-01-14T10:00:00Z".to_string(),
+            severity,
+            r#type: kind,
+            entity,
+            evidence: json!({}),
+            suggestion: None,
+            status: "open".to_string(),
+            silenced_by: None,
+        }
+    }
+
+    #[test]
+    fn silence_matchers_require_every_condition() {
+        let disk = event(EventKind::DiskHigh, Severity::Warning, json!({"mount": "/var", "pid": 42}));
+        let matchers = |m: serde_json::Value| serde_json::from_value::<SilenceMatchers>(m).unwrap();
+
+        assert!(matchers(json!({})).matches(&disk, "system"));
+        assert!(matchers(json!({"type": "disk_high", "severity": "WARNING"})).matches(&disk, "system"));
+        assert!(!matchers(json!({"type": "cpu_high"})).matches(&disk, "system"));
+        assert!(!matchers(json!({"severity": "CRITICAL"})).matches(&disk, "system"));
+        assert!(matchers(json!({"service_id": "backup"})).matches(&disk, "backup"));
+        assert!(!matchers(json!({"service_id": "backup"})).matches(&disk, "system"));
+
+        // Labels compare strings as-is and anything else as JSON
+        assert!(matchers(json!({"labels": {"mount": "/var", "pid": "42"}})).matches(&disk, "system"));
+        assert!(!matchers(json!({"labels": {"mount": "/"}})).matches(&disk, "system"));
+        assert!(!matchers(json!({"labels": {"pid": "43"}})).matches(&disk, "system"));
+        assert!(!matchers(json!({"labels": {"unit": "backup.service"}})).matches(&disk, "system"));
+    }
+
+    #[test]
+    fn silences_cover_start_up_to_end() {
+        let silence = Silence {
+            id: "sil_1".to_string(),
+            matchers: SilenceMatchers::default(),
+            starts_at: 1000,
+            ends_at: 2000,
+            created_by: "alice (uid 1000)".to_string(),
+            comment: String::new(),
+            created_at: 900,
+        };
+        assert!(!silence.is_active(999));
+        assert!(silence.is_active(1000));
+        assert!(silence.is_active(1999));
+        assert!(!silence.is_active(2000));
+    }
+}
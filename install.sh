@@ -72,10 +72,19 @@ else
     echo "ℹ️  Configuration already exists at $CONFIG_DIR/config.toml"
 fi
 
+# Copy prompt templates if they don't exist (local edits are kept)
+if [ ! -d "$CONFIG_DIR/prompts" ]; then
+    echo "📝 Installing prompt templates..."
+    cp -r config/prompts "$CONFIG_DIR/prompts"
+fi
+
 # Update config paths for system installation
 echo "🔄 Updating configuration paths..."
 sed -i "s|socket_path = \"/tmp/sia.sock\"|socket_path = \"/run/sia/sia.sock\"|g" "$CONFIG_DIR/config.toml"
 sed -i "s|db_path = \"./sia.db\"|db_path = \"/var/lib/sia/sia.db\"|g" "$CONFIG_DIR/config.toml"
+sed -i "s|dir = \"./config/prompts\"|dir = \"$CONFIG_DIR/prompts\"|g" "$CONFIG_DIR/config.toml"
+sed -i "s|buffer_dir = \"./otlp-buffer\"|buffer_dir = \"$DATA_DIR/otlp\"|g" "$CONFIG_DIR/config.toml"
+sed -i "s|key_file = \"./grants.key\"|key_file = \"$DATA_DIR/grants.key\"|g" "$CONFIG_DIR/config.toml"
 
 # Initialize database with schema
 if [ ! -f "$DATA_DIR/sia.db" ]; then
@@ -17,6 +17,7 @@ status TEXT
 CREATE INDEX IF NOT EXISTS idx_events_ts ON events(ts);
 CREATE INDEX IF NOT EXISTS idx_events_sev ON events(severity);
 CREATE INDEX IF NOT EXISTS idx_events_service ON events(service_id);
+CREATE INDEX IF NOT EXISTS idx_events_status_ts ON events(status, ts);
 
 
 CREATE TABLE IF NOT EXISTS grants (
@@ -33,4 +34,124 @@ id INTEGER PRIMARY KEY AUTOINCREMENT,
 ts INTEGER,
 kind TEXT,
 payload BLOB
-);
\ No newline at end of file
+);
+
+CREATE INDEX IF NOT EXISTS idx_audits_kind_ts ON audits(kind, ts);
+
+CREATE TABLE IF NOT EXISTS baselines (
+metric TEXT,
+bucket INTEGER,
+mean REAL,
+variance REAL,
+samples INTEGER,
+updated_at INTEGER,
+PRIMARY KEY (metric, bucket)
+);
+
+
+CREATE TABLE IF NOT EXISTS incidents (
+id TEXT PRIMARY KEY,
+opened_at INTEGER,
+updated_at INTEGER,
+status TEXT,
+severity TEXT,
+entities TEXT,
+root_cause TEXT
+);
+
+
+CREATE TABLE IF NOT EXISTS incident_events (
+incident_id TEXT,
+event_id TEXT,
+rank INTEGER,
+PRIMARY KEY (incident_id, event_id)
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_incidents_updated ON incidents(updated_at);
+
+
+CREATE TABLE IF NOT EXISTS silences (
+id TEXT PRIMARY KEY,
+matchers TEXT,
+starts_at INTEGER,
+ends_at INTEGER,
+created_by TEXT,
+comment TEXT,
+created_at INTEGER
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_silences_ends ON silences(ends_at);
+
+
+CREATE TABLE IF NOT EXISTS llm_jobs (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+event_id TEXT,
+payload TEXT,
+status TEXT,
+attempts INTEGER,
+next_attempt_at INTEGER,
+last_error TEXT,
+created_at INTEGER,
+updated_at INTEGER
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_llm_jobs_due ON llm_jobs(status, next_attempt_at);
+
+
+CREATE TABLE IF NOT EXISTS metric_samples (
+name TEXT,
+ts INTEGER,
+value REAL
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_metric_samples_ts ON metric_samples(ts);
+
+
+CREATE TABLE IF NOT EXISTS chat_messages (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+session_id TEXT,
+ts INTEGER,
+role TEXT,
+content TEXT,
+citations TEXT,
+owner TEXT
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_chat_messages_session ON chat_messages(session_id);
+
+
+CREATE TABLE IF NOT EXISTS notifications (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+event_id TEXT,
+event_type TEXT,
+kind TEXT,
+route TEXT,
+sink TEXT,
+payload TEXT,
+status TEXT,
+attempts INTEGER,
+next_attempt_at INTEGER,
+last_error TEXT,
+created_at INTEGER,
+updated_at INTEGER
+);
+
+
+CREATE INDEX IF NOT EXISTS idx_notifications_due ON notifications(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_notifications_event ON notifications(event_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_route ON notifications(route, event_type, created_at);
+
+
+CREATE TABLE IF NOT EXISTS services (
+id TEXT PRIMARY KEY,
+name TEXT,
+discovery TEXT,
+default_scopes TEXT,
+requested_scopes TEXT,
+updated_at INTEGER
+);